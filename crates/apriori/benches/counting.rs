@@ -0,0 +1,59 @@
+//! Compares the structures used to count candidates.
+//! Run with `cargo bench -p apriori`.
+use apriori::{
+    apriori::{Apriori, CountingStructure},
+    apriori_trie::AprioriT,
+};
+use datasets::{random::Random, transaction_set::TransactionSet};
+use tester::bench::time;
+
+/// Creates a dataset where transactions are made of a few random patterns
+fn dataset(transactions: usize, items: usize, seed: u64) -> TransactionSet {
+    let mut random = Random::new(seed);
+    let patterns: Vec<Vec<usize>> = (0..50)
+        .map(|_| (0..(2 + random.below(6))).map(|_| random.below(items)).collect())
+        .collect();
+    let mut data = Vec::new();
+    for _ in 0..transactions {
+        let mut t = Vec::new();
+        for _ in 0..(1 + random.below(3)) {
+            t.extend(&patterns[random.below(patterns.len())]);
+        }
+        for _ in 0..random.below(4) {
+            t.push(random.below(items));
+        }
+        t.sort_unstable();
+        t.dedup();
+        data.push(t);
+    }
+    TransactionSet::new(data, items)
+}
+
+fn main() {
+    let data = dataset(20_000, 500, 42);
+    println!(
+        "{:>8} {:>12} {:>12} {:>12} {:>8}",
+        "support", "hash tree", "flat trie", "trie", "found"
+    );
+    for support in [400, 200, 100] {
+        let (hash, found) = time(|| &data, |data| {
+            let mut n = 0;
+            Apriori::new(support).run_fn(data, |_| n += 1).unwrap();
+            n
+        });
+        let (flat, _) = time(|| &data, |data| {
+            let mut n = 0;
+            let a = Apriori::with_counting(support, CountingStructure::FlatTrie);
+            a.run_fn(data, |_| n += 1).unwrap();
+            n
+        });
+        let (trie, _) = time(|| &data, |data| {
+            let mut a = AprioriT::new(support);
+            a.run(data).unwrap();
+            let mut n = 0;
+            a.trie().for_each(support, |_| n += 1);
+            n
+        });
+        println!("{support:>8} {hash:>12.2?} {flat:>12.2?} {trie:>12.2?} {found:>8}");
+    }
+}
@@ -1,57 +1,199 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    io,
+    ops::{Deref, DerefMut},
+};
 
-use datasets::{transaction_set::TransactionSet, utils::nested_loops};
+use datasets::{
+    budget::{Budget, Limiter, Status, Truncation},
+    constraint::{ItemConstraints, Lengths},
+    transaction_source::TransactionSource,
+    utils::{Item, nested_loops},
+};
 
 use crate::{
     array2d::Array2D,
     candidates::{CandidateType, Candidates},
-    candidates_func::join,
+    candidates_func::{join, join_by},
+    checkpoint::{Checkpoint, CheckpointKind, Checkpointer},
+    flat_trie::FlatTrie,
     hash_tree::AprioriHashTree,
+    observer::{PassCounts, PassObserver, PassTimer},
 };
+/// The constraints of the candidates that are not given any
+static NO_CONSTRAINTS: ItemConstraints = ItemConstraints::NONE;
+/// The structure that candidates are counted with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CountingStructure {
+    #[default]
+    HashTree,
+    FlatTrie,
+}
 /// Runs the Apriori Algorithm
 #[derive(Debug)]
 pub struct Apriori {
     /// Minimum support count
     min_support: u64,
+    /// What the candidates are counted with
+    counting: CountingStructure,
+    /// The lengths of the itemsets found
+    lengths: Lengths,
+    /// The items the itemsets found must or must not have
+    constraints: ItemConstraints,
 }
 impl Apriori {
     /// Constructor
     pub fn new(min_support: u64) -> Self {
-        Apriori { min_support }
+        Self::with_counting(min_support, CountingStructure::default())
     }
-    /// Runs the algorithm
-    pub fn run(self, data: &TransactionSet) -> Vec<Candidates> {
+    /// Constructor with what the candidates are counted with
+    pub fn with_counting(min_support: u64, counting: CountingStructure) -> Self {
+        Apriori {
+            min_support,
+            counting,
+            lengths: Lengths::default(),
+            constraints: ItemConstraints::default(),
+        }
+    }
+    /// Only finds itemsets with these lengths, stopping after the pass of the longest
+    pub fn lengths(mut self, lengths: Lengths) -> Self {
+        self.lengths = lengths;
+        self
+    }
+    /// Only finds itemsets that satisfy the constraints.
+    /// The passes only join the itemsets that can be extended into ones that satisfy them.
+    pub fn constraints(mut self, constraints: ItemConstraints) -> Self {
+        self.constraints = constraints;
+        self
+    }
+    /// Runs the algorithm.
+    /// With constraints, the passes also have the itemsets kept to be joined.
+    pub fn run(self, data: &impl TransactionSource) -> io::Result<Vec<Candidates>> {
         let mut v = Vec::new();
         // First gets the frequent items
-        v.push(apriori_run_one(data, self.min_support));
-        for i in 2.. {
+        v.push(self.run_one(data)?);
+        for i in 2..=self.lengths.max {
             // Creates the next frequent itemsets based on the previous frequent itemsets.
             let prev = v.last().unwrap();
-            let next = AprioriCandidates::new(prev.deref()).run(data, i, self.min_support);
+            let next = AprioriCandidates::with_constraints(prev, &self.constraints).run_with(
+                data,
+                i,
+                self.min_support,
+                self.counting,
+            )?;
             if next.is_empty() {
                 break;
             }
             v.push(next);
         }
-        v
+        Ok(v)
     }
     /// Runs the algorithm
-    pub fn run_fn(self, data: &TransactionSet, mut f: impl FnMut(&[usize])) {
-        let mut prev = apriori_run_one(data, self.min_support);
-        prev.iter().for_each(|v| f(v));
-        for i in 2.. {
+    pub fn run_fn(
+        self,
+        data: &impl TransactionSource,
+        mut f: impl FnMut(&[usize]),
+    ) -> io::Result<()> {
+        self.run_observed(data, &mut (), &Budget::default(), |v, _| f(v)).read()
+    }
+    /// Runs the algorithm, calling f with each frequent itemset and its support,
+    /// telling the observer about each pass, and stopping early if it goes over the budget
+    pub fn run_observed(
+        self,
+        data: &impl TransactionSource,
+        observer: &mut impl PassObserver,
+        budget: &Budget,
+        f: impl FnMut(&[usize], u64),
+    ) -> Status {
+        let mut limiter = budget.start();
+        let resume = Checkpoint::default();
+        self.run_limited(data, observer, &mut limiter, None, resume, f)
+            .into()
+    }
+    /// Runs the algorithm like run_observed, saving the frequent itemsets after each pass.
+    /// A resuming checkpointer continues from the last pass saved,
+    /// calling f with the itemsets of the saved passes first.
+    pub fn run_checkpointed(
+        self,
+        data: &impl TransactionSource,
+        observer: &mut impl PassObserver,
+        budget: &Budget,
+        checkpointer: &mut Checkpointer,
+        f: impl FnMut(&[usize], u64),
+    ) -> io::Result<Status> {
+        let resume =
+            checkpointer.start(CheckpointKind::Apriori, self.min_support, data)?;
+        let mut limiter = budget.start();
+        let result = self.run_limited(data, observer, &mut limiter, Some(checkpointer), resume, f);
+        checkpointer.finish()?;
+        Ok(result.into())
+    }
+    fn run_limited(
+        self,
+        data: &impl TransactionSource,
+        observer: &mut impl PassObserver,
+        limiter: &mut Limiter,
+        mut checkpointer: Option<&mut Checkpointer>,
+        resume: Checkpoint,
+        mut f: impl FnMut(&[usize], u64),
+    ) -> Result<(), Truncation> {
+        let lengths = self.lengths;
+        let constraints = &self.constraints;
+        // The passes have the itemsets that are kept to be joined but do not satisfy them
+        let mut emit_all = |limiter: &mut Limiter, set: &Candidates| {
+            limiter.emit_all(set.iter().filter(|(v, _)| constraints.satisfied(v)), &mut f)
+        };
+        let mut frequent = resume.frequent;
+        for (i, set) in frequent.iter().enumerate() {
+            if lengths.contains(i + 1) {
+                emit_all(limiter, set)?;
+            }
+        }
+        if frequent.is_empty() {
+            let timer = PassTimer::start(1, observer);
+            limiter.candidates(1, data.num_items())?;
+            frequent.push(self.run_one(data)?);
+            let counts = PassCounts::new(data.num_items());
+            timer.end(observer, counts, frequent[0].len());
+            if let Some(c) = checkpointer.as_deref_mut() {
+                c.save(&frequent);
+            }
+            if lengths.contains(1) {
+                emit_all(limiter, &frequent[0])?;
+            }
+        }
+        for i in (frequent.len() + 1)..=lengths.max {
             // Creates the next frequent itemsets based on the previous frequent itemsets.
-            let next = AprioriCandidates::new(prev.deref()).run(data, i, self.min_support);
+            let timer = PassTimer::start(i, observer);
+            let prev = frequent.last().unwrap();
+            let (next, counts) = AprioriCandidates::with_constraints(prev, constraints)
+                .run_counted(data, i, self.min_support, self.counting, limiter)?;
+            timer.end(observer, counts, next.len());
             if next.is_empty() {
                 break;
             }
-            prev = next;
-            prev.iter().for_each(|v| f(v));
+            // Only a checkpoint needs the earlier passes
+            if checkpointer.is_none() {
+                frequent.clear();
+            }
+            frequent.push(next);
+            if let Some(c) = checkpointer.as_deref_mut() {
+                c.save(&frequent);
+            }
+            if lengths.contains(i) {
+                emit_all(limiter, frequent.last().unwrap())?;
+            }
         }
+        Ok(())
+    }
+    /// Apriori pass 1, with only the items the constraints allow
+    fn run_one(&self, data: &impl TransactionSource) -> io::Result<Candidates> {
+        let mut c = apriori_run_one(data, self.min_support)?;
+        c.retain(|v, _| self.constraints.allows(v[0]) && self.constraints.keeps(v));
+        Ok(c)
     }
 }
-/// The wrapper for AprioriCandidates
-pub struct AprioriCandidates<'a>(&'a CandidateType);
+/// The wrapper for AprioriCandidates, with the constraints its joins are kept by
+pub struct AprioriCandidates<'a>(&'a CandidateType, &'a ItemConstraints);
 /// Dereferences to the underlying struct
 impl Deref for AprioriCandidates<'_> {
     type Target = CandidateType;
@@ -64,74 +206,229 @@ impl Deref for AprioriCandidates<'_> {
 impl<'a> AprioriCandidates<'a> {
     /// Constructor
     pub fn new(v: &'a CandidateType) -> Self {
-        Self(v)
+        Self(v, &NO_CONSTRAINTS)
+    }
+    /// Constructor with the constraints of the run,
+    /// where the itemsets are those of the last pass kept to be joined
+    pub fn with_constraints(v: &'a CandidateType, constraints: &'a ItemConstraints) -> Self {
+        Self(v, constraints)
     }
     /// A prune function for Apriori
     pub fn can_be_pruned(&self, v: &[usize]) -> bool {
         if v.len() < 3 {
             return false;
         }
+        if !self.1.is_empty() {
+            // Only the subsets kept to be joined are in the last pass
+            return (0..v.len()).any(|i| {
+                let mut arr = v.to_vec();
+                arr.remove(i);
+                self.1.keeps(&arr) && !self.contains_key(&arr)
+            });
+        }
         // The vector used to check subsets
         let mut arr: Vec<_> = v.iter().cloned().skip(1).collect();
         // Check the subset without the first element.
-        if !self.contains(&arr) {
+        if !self.contains_key(&arr) {
             return true;
         }
         // Checks all the subsets without the nth element up to the last 2
         for i in 0..(v.len() - 3) {
             arr[i] = v[i];
-            if !self.contains(&arr) {
+            if !self.contains_key(&arr) {
                 return true;
             }
         }
         false
     }
-    pub fn create_tree(&self) -> AprioriHashTree {
-        let mut tree = AprioriHashTree::new();
-        // Joins relevant frequent itemsets
-        join(self.iter(), |v| {
+    /// Joins the itemsets into the candidates of the next pass, calling f with each one
+    /// that is not pruned. Returns the number pruned.
+    fn join_pruned(&self, mut f: impl FnMut(Vec<usize>)) -> usize {
+        let mut pruned = 0;
+        if !self.1.is_empty() {
+            // Joins the itemsets in the order of the constraints
+            let c = self.1;
+            let ordered: Vec<_> = self.keys().map(|v| c.ordered(v)).collect();
+            join_by(
+                ordered.iter(),
+                |a, b| c.before(a, b),
+                |mut v| {
+                    if !c.keeps(&v) {
+                        pruned += 1;
+                        return;
+                    }
+                    v.sort_unstable();
+                    if self.can_be_pruned(&v) {
+                        pruned += 1;
+                        return;
+                    }
+                    f(v);
+                },
+            );
+            return pruned;
+        }
+        join(self.keys(), |v| {
             // Prunes
             if self.can_be_pruned(&v) {
+                pruned += 1;
                 return;
             }
+            f(v);
+        });
+        pruned
+    }
+    pub fn create_tree(&self) -> AprioriHashTree {
+        self.create_tree_pruned().0
+    }
+    /// Creates the tree of the candidates, and returns the number of joined itemsets pruned
+    pub fn create_tree_pruned(&self) -> (AprioriHashTree, usize) {
+        let mut tree = AprioriHashTree::new();
+        // Joins relevant frequent itemsets
+        let pruned = self.join_pruned(|v| {
             tree.add(&v);
         });
-        tree
+        (tree, pruned)
+    }
+    /// Creates a flat trie of the candidates, and returns the number of joined itemsets pruned
+    pub fn create_flat_trie(&self) -> (FlatTrie, usize) {
+        let (v, pruned) = self.join_candidates();
+        (FlatTrie::new(v), pruned)
+    }
+    /// Joins the candidates of the next pass, and returns them with the number pruned
+    pub fn join_candidates(&self) -> (Vec<Vec<usize>>, usize) {
+        let mut v = Vec::new();
+        let pruned = self.join_pruned(|c| v.push(c));
+        (v, pruned)
     }
-    /// Counts the dataset
-    pub fn run_count(&self, data: &TransactionSet, i: usize) -> AprioriHashTree {
+    /// Counts the dataset, and returns the number of joined itemsets pruned
+    pub fn run_count(
+        &self,
+        data: &impl TransactionSource,
+        i: usize,
+    ) -> io::Result<(AprioriHashTree, usize)> {
         assert!(i > 2);
-        let mut tree = self.create_tree();
-        for d in data.iter() {
-            apriori_count(d, i, tree.deref_mut(), |_| {});
-        }
-        tree
+        let (mut tree, pruned) = self.create_tree_pruned();
+        data.scan(|d| apriori_count(d, i, tree.deref_mut(), |_| {}))?;
+        Ok((tree, pruned))
     }
     /// Runs the algorithm
-    pub fn run(&self, data: &TransactionSet, i: usize, min_sup: u64) -> Candidates {
+    pub fn run(
+        &self,
+        data: &impl TransactionSource,
+        i: usize,
+        min_sup: u64,
+    ) -> io::Result<Candidates> {
+        let c = self.1;
         if i == 1 {
-            return apriori_run_one(data, min_sup);
+            let mut set = apriori_run_one(data, min_sup)?;
+            set.retain(|v, _| c.allows(v[0]) && c.keeps(v));
+            return Ok(set);
         } else if i == 2 {
-            return apriori_run_two(data, min_sup);
+            let mut set = apriori_run_two(data, min_sup)?;
+            set.retain(|v, _| v.iter().all(|&n| c.allows(n)) && c.keeps(v));
+            return Ok(set);
         }
         // Counts the dataset and creates the frequent itemsets
-        let tree = self.run_count(data, i);
+        let (tree, _) = self.run_count(data, i)?;
         let mut set = Candidates::default();
         for (arr, n) in tree.iter() {
             if n >= min_sup {
-                set.insert(arr.to_vec());
+                set.insert(arr.to_vec(), n);
+            }
+        }
+        Ok(set)
+    }
+    /// Runs the algorithm, counting with the given structure
+    pub fn run_with(
+        &self,
+        data: &impl TransactionSource,
+        i: usize,
+        min_sup: u64,
+        counting: CountingStructure,
+    ) -> io::Result<Candidates> {
+        let budget = Budget::default();
+        // A run without limits is only stopped if the data cannot be read
+        match self.run_counted(data, i, min_sup, counting, &budget.start()) {
+            Ok((set, _)) => Ok(set),
+            Err(Truncation::Read(kind)) => Err(kind.into()),
+            Err(t) => unreachable!("A run without limits was truncated: {t}"),
+        }
+    }
+    /// Same as run_with, but also returns the number of candidates counted and pruned.
+    /// Stops before counting if the limiter does not allow that many candidates.
+    pub fn run_counted(
+        &self,
+        data: &impl TransactionSource,
+        i: usize,
+        min_sup: u64,
+        counting: CountingStructure,
+        limiter: &Limiter,
+    ) -> Result<(Candidates, PassCounts), Truncation> {
+        if i == 1 {
+            limiter.candidates(i, data.num_items())?;
+            return Ok((self.run(data, i, min_sup)?, PassCounts::new(data.num_items())));
+        } else if i == 2 {
+            // Every pair of frequent items
+            let pairs = self.len() * self.len().saturating_sub(1) / 2;
+            limiter.candidates(i, pairs)?;
+            return Ok((self.run(data, i, min_sup)?, PassCounts::new(pairs)));
+        }
+        match counting {
+            CountingStructure::HashTree => {
+                let (mut tree, pruned) = self.create_tree_pruned();
+                let counts = PassCounts {
+                    candidates: tree.len(),
+                    pruned,
+                };
+                limiter.candidates(i, counts.candidates)?;
+                Ok((count_frequent(data, i, min_sup, tree.deref_mut())?, counts))
+            }
+            CountingStructure::FlatTrie => {
+                let (mut trie, pruned) = self.create_flat_trie();
+                let counts = PassCounts {
+                    candidates: AprioriCounting::len(&trie),
+                    pruned,
+                };
+                limiter.candidates(i, counts.candidates)?;
+                Ok((count_frequent(data, i, min_sup, &mut trie)?, counts))
             }
         }
-        set
     }
 }
 pub trait AprioriCounting {
     fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
     fn increment(&mut self, v: &[usize]) -> bool;
     fn for_each_mut(&mut self, f: impl FnMut(&[usize], &mut u64));
+    /// Counts the itemsets of size i in the transaction, calling f with each one found
+    fn count_transaction<I: Item>(&mut self, t: &[I], i: usize, f: impl FnMut(&[usize]))
+    where
+        Self: Sized,
+    {
+        apriori_count(t, i, self, f)
+    }
 }
-pub fn apriori_count(
-    t: &[usize],
+/// Counts the dataset into the counter, and returns the frequent itemsets
+pub fn count_frequent(
+    data: &impl TransactionSource,
+    i: usize,
+    min_sup: u64,
+    counter: &mut impl AprioriCounting,
+) -> io::Result<Candidates> {
+    data.scan(|d| counter.count_transaction(d, i, |_| {}))?;
+    let mut set = Candidates::default();
+    counter.for_each_mut(|v, &mut n| {
+        if n >= min_sup {
+            set.insert(v.to_vec(), n);
+        }
+    });
+    Ok(set)
+}
+/// Counts the itemsets of size i in the transaction t, calling f with each one found
+pub fn apriori_count<I: Item>(
+    t: &[I],
     i: usize,
     d: &mut impl AprioriCounting,
     mut f: impl FnMut(&[usize]),
@@ -155,15 +452,15 @@ pub fn apriori_count(
                     f(v);
                 }
             },
-            &t,
+            t,
             i,
         );
     } else {
         // Otherwise count for each itemset
         d.for_each_mut(|v, n| {
-            let mut iter = t.iter().cloned();
+            let mut iter = t.iter().map(|n| n.index());
             'outer: for &a in v {
-                while let Some(b) = iter.next() {
+                for b in iter.by_ref() {
                     match a.cmp(&b) {
                         std::cmp::Ordering::Less => return,
                         std::cmp::Ordering::Equal => continue 'outer,
@@ -179,57 +476,66 @@ pub fn apriori_count(
 }
 
 /// Apriori pass 1
-pub fn apriori_run_one(d: &TransactionSet, min_sup: u64) -> Candidates {
-    let first = apriori_run_one_count(d);
+pub fn apriori_run_one(d: &impl TransactionSource, min_sup: u64) -> io::Result<Candidates> {
+    let first = apriori_run_one_count(d)?;
     let mut v = Candidates::default();
     for (i, n) in first.into_iter().enumerate() {
         if n >= min_sup {
-            v.insert(vec![i]);
+            v.insert(vec![i], n);
         }
     }
-    v
+    Ok(v)
 }
 /// Apriori pass 1 with the counts
-pub fn apriori_run_one_count(d: &TransactionSet) -> Vec<u64> {
+pub fn apriori_run_one_count(d: &impl TransactionSource) -> io::Result<Vec<u64>> {
+    if let Some(counts) = d.item_counts() {
+        return Ok(counts);
+    }
     // Uses a 1D array
-    let mut first = vec![0u64; d.num_items];
-    for d in d.iter() {
-        for &item in d {
-            first[item] += 1;
+    let mut first = vec![0u64; d.num_items()];
+    d.scan(|d| {
+        for item in d {
+            first[item.index()] += 1;
         }
-    }
-    first
+    })?;
+    Ok(first)
 }
 /// Apriori pass 2 with counts
-pub fn apriori_run_two_count(d: &TransactionSet) -> Array2D<u64> {
+pub fn apriori_run_two_count(d: &impl TransactionSource) -> io::Result<Array2D<u64>> {
     // Counts through a 2D array (implementation is 1D through upper triangle)
-    let mut second = Array2D::new(d.num_items);
-    for d in d.iter() {
+    let mut second = Array2D::new(d.num_items());
+    d.scan(|d| {
         for i in 0..d.len() {
             for j in 0..i {
-                second.increment(d[i], d[j]);
+                second.increment(d[i].index(), d[j].index());
             }
         }
-    }
-    second
+    })?;
+    Ok(second)
 }
 /// Apriori pass 2
-pub fn apriori_run_two(d: &TransactionSet, min_sup: u64) -> Candidates {
-    let second = apriori_run_two_count(d);
+pub fn apriori_run_two(d: &impl TransactionSource, min_sup: u64) -> io::Result<Candidates> {
+    let second = apriori_run_two_count(d)?;
     let mut v = Candidates::default();
     for (r, c, count) in second.iter() {
         if count >= min_sup {
-            v.insert(vec![c, r]);
+            v.insert(vec![c, r], count);
         }
     }
-    v
+    Ok(v)
 }
 
 #[cfg(test)]
 mod tests {
-    use datasets::transaction_set::TransactionSet;
+    use std::ops::Deref;
 
-    use crate::apriori::Apriori;
+    use datasets::{
+        budget::{Budget, Status, Truncation},
+        constraint::{ItemConstraints, Lengths},
+        transaction_set::TransactionSet,
+    };
+
+    use crate::apriori::{Apriori, CountingStructure};
 
     #[test]
     fn test_candidates() {
@@ -248,15 +554,82 @@ mod tests {
             5,
         );
         let apriori = Apriori::new(2);
-        let result = apriori.run(&example);
-        assert!(result[1].contains(&vec![0, 1]));
-        assert!(result[1].contains(&vec![0, 2]));
-        assert!(result[1].contains(&vec![0, 4]));
-        assert!(result[1].contains(&vec![1, 2]));
-        assert!(result[1].contains(&vec![1, 3]));
-        assert!(result[1].contains(&vec![1, 4]));
+        let result = apriori.run(&example).unwrap();
+        assert!(result[1].contains_key(&vec![0, 1]));
+        assert!(result[1].contains_key(&vec![0, 2]));
+        assert!(result[1].contains_key(&vec![0, 4]));
+        assert!(result[1].contains_key(&vec![1, 2]));
+        assert!(result[1].contains_key(&vec![1, 3]));
+        assert!(result[1].contains_key(&vec![1, 4]));
         assert_eq!(result[1].len(), 6);
         assert_eq!(result.len(), 3);
         assert_eq!(result[2].len(), 2);
+        let flat = Apriori::with_counting(2, CountingStructure::FlatTrie).run(&example).unwrap();
+        assert_eq!(flat.len(), 3);
+        for (a, b) in result.iter().zip(flat.iter()) {
+            assert_eq!(a.deref(), b.deref());
+        }
+        // The itemsets are emitted by length
+        let mut found = Vec::new();
+        Apriori::new(2).run_fn(&example, |v| found.push(v.len())).unwrap();
+        assert!(found.is_sorted());
+        // Pass 3 joins 6 itemsets, 4 of which have an infrequent pair
+        let mut passes = Vec::new();
+        Apriori::new(2).run_observed(&example, &mut passes, &Budget::default(), |_, _| {});
+        let counts: Vec<_> = passes
+            .iter()
+            .map(|p| (p.pass, p.candidates, p.pruned, p.frequent))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![(1, 5, 0, 5), (2, 10, 0, 6), (3, 2, 4, 2), (4, 0, 1, 0)]
+        );
+        // Pass 2 has 10 candidates, so only the frequent items are found
+        let budget = Budget {
+            max_candidates: Some(9),
+            ..Default::default()
+        };
+        let mut found = 0;
+        let status = Apriori::new(2).run_observed(&example, &mut (), &budget, |_, _| found += 1);
+        let truncation = Truncation::Candidates {
+            pass: 2,
+            candidates: 10,
+        };
+        assert_eq!((status, found), (Status::Truncated(truncation), 5));
+        let budget = Budget {
+            max_itemsets: Some(7),
+            ..Default::default()
+        };
+        let mut found = 0;
+        let status = Apriori::new(2).run_observed(&example, &mut (), &budget, |_, _| found += 1);
+        assert_eq!((status, found), (Status::Truncated(Truncation::Itemsets), 7));
+        let budget = Budget {
+            max_itemsets: Some(13),
+            ..Default::default()
+        };
+        let status = Apriori::new(2).run_observed(&example, &mut (), &budget, |_, _| {});
+        assert_eq!(status, Status::Complete);
+        // Only the pairs are found, and no pass is run after theirs
+        let mut passes = Vec::new();
+        let mut found = Vec::new();
+        Apriori::new(2)
+            .lengths(Lengths::new(Some(2), Some(2)))
+            .run_observed(&example, &mut passes, &Budget::default(), |v, _| {
+                found.push(v.to_vec())
+            });
+        assert_eq!(passes.len(), 2);
+        assert_eq!(found.len(), 6);
+        assert!(found.iter().all(|v| v.len() == 2));
+        // Only the itemsets with item 3 first are kept, so pass 3 has nothing to join
+        let mut passes = Vec::new();
+        let mut found = Vec::new();
+        Apriori::new(2)
+            .constraints(ItemConstraints::new(vec![3], vec![], None, vec![]))
+            .run_observed(&example, &mut passes, &Budget::default(), |v, _| {
+                found.push(v.to_vec())
+            });
+        assert_eq!(found, vec![vec![3], vec![1, 3]]);
+        assert_eq!(passes[1].frequent, 1);
+        assert_eq!(passes[2].candidates, 0);
     }
 }
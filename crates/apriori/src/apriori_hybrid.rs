@@ -1,122 +1,374 @@
-use std::ops::Deref;
+use std::{
+    fs,
+    mem::size_of,
+    ops::{Deref, DerefMut},
+};
 
-use datasets::transaction_set::TransactionSet;
+use datasets::{
+    budget::{Budget, Limiter, Status, Truncation},
+    constraint::Lengths,
+    transaction_set::TransactionSet,
+    transaction_source::TransactionSource,
+};
 
 use crate::{
-    apriori::{AprioriCandidates, apriori_run_one},
-    apriori_tid::AprioriTiDCandidates,
-    candidates::Candidates,
-    transaction_id::TransactionIDs,
+    apriori::{AprioriCandidates, apriori_count, apriori_run_one, apriori_run_two_count},
+    apriori_tid::{AprioriTiDCandidates, TIDEncoding},
+    candidates::{CandidateType, Candidates},
+    observer::{PassCounts, PassObserver, PassTimer},
+    transaction_id::{TransactionID, TransactionIDs},
+    transaction_index::{IndexedItemsets, TransactionIndex, TransactionIndices},
 };
+/// When AprioriHybrid switches to AprioriTID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchPass {
+    /// Switches at the given pass
+    Fixed(usize),
+    /// Switches when the TIDs are estimated to fit in the memory (in bytes) and shrink
+    Auto(usize),
+}
 /// The AprioriHybrid Algorithm
 pub struct AprioriHybrid {
     min_support: u64,
     /// When to switch to using AprioriTID
-    switch: usize,
+    switch: SwitchPass,
+    /// How the TIDs are stored after the switch
+    encoding: TIDEncoding,
+    /// The pass that was switched at in the last run
+    switched: Option<usize>,
+    /// The lengths of the itemsets found
+    lengths: Lengths,
 }
 
 impl AprioriHybrid {
     /// Constructor
     pub fn new(min_support: u64, switch: usize) -> Self {
+        Self::with_encoding(min_support, switch, TIDEncoding::default())
+    }
+    /// Constructor with the way TIDs are stored
+    pub fn with_encoding(min_support: u64, switch: usize, encoding: TIDEncoding) -> Self {
+        Self::with_switch(min_support, SwitchPass::Fixed(switch), encoding)
+    }
+    /// Constructor with when to switch and the way TIDs are stored
+    pub fn with_switch(min_support: u64, switch: SwitchPass, encoding: TIDEncoding) -> Self {
         AprioriHybrid {
             min_support,
             switch,
+            encoding,
+            switched: None,
+            lengths: Lengths::default(),
         }
     }
+    /// Only finds itemsets with these lengths, stopping after the pass of the longest
+    pub fn lengths(mut self, lengths: Lengths) -> Self {
+        self.lengths = lengths;
+        self
+    }
+    /// The pass that the last run switched to AprioriTID at.
+    /// None if it finished before switching.
+    pub fn switched(&self) -> Option<usize> {
+        self.switched
+    }
     /// Runs the algorithm
-    pub fn run(&self, data: &TransactionSet) -> Vec<Candidates> {
-        // The frequent itemsets generated
-        let mut apriori = vec![apriori_run_one(data, self.min_support)];
-        let mut prev_trans = TransactionIDs::default();
-        for i in 2.. {
-            // When we switch, we generate TIDs from the transaction set
-            if i == self.switch {
-                let prev = apriori.last().unwrap();
-                let (tree, a) =
-                    TransactionIDs::from_transaction(&data.transactions, i - 1, prev.deref());
-                let mut c = Candidates::default();
-                tree.iter().for_each(|(v, count)| {
-                    if count < self.min_support {
-                        return;
-                    }
-                    c.insert(v.to_vec());
-                });
-                prev_trans = a;
-                apriori.push(c);
-                continue;
+    pub fn run(&mut self, data: &TransactionSet) -> Vec<Candidates> {
+        let mut v = Vec::new();
+        let _ = self.run_passes(data, &mut (), &mut Budget::default().start(), |c, _| {
+            v.push(c.map(|(v, &n)| (v.clone(), n)).collect::<CandidateType>().into());
+            Ok(())
+        });
+        v
+    }
+    /// Runs the algorithm
+    pub fn run_fn(&mut self, data: &TransactionSet, mut f: impl FnMut(&[usize])) {
+        self.run_observed(data, &mut (), &Budget::default(), |v, _| f(v));
+    }
+    /// Runs the algorithm, calling f with each frequent itemset and its support,
+    /// telling the observer about each pass, and stopping early if it goes over the budget
+    pub fn run_observed(
+        &mut self,
+        data: &TransactionSet,
+        observer: &mut impl PassObserver,
+        budget: &Budget,
+        mut f: impl FnMut(&[usize], u64),
+    ) -> Status {
+        let lengths = self.lengths;
+        self.run_passes(data, observer, &mut budget.start(), |c, limiter| {
+            limiter.emit_all(c.filter(|(v, _)| lengths.contains(v.len())), &mut f)
+        })
+        .into()
+    }
+    /// Runs the algorithm, calling f with the frequent itemsets of each pass
+    fn run_passes(
+        &mut self,
+        data: &TransactionSet,
+        observer: &mut impl PassObserver,
+        limiter: &mut Limiter,
+        mut f: impl FnMut(&mut dyn Iterator<Item = (&Vec<usize>, &u64)>, &mut Limiter) -> Emitted,
+    ) -> Result<(), Truncation> {
+        self.switched = None;
+        let Some((prev, i)) = self.run_apriori(data, observer, limiter, &mut f)? else {
+            return Ok(());
+        };
+        self.switched = Some(i);
+        match self.encoding {
+            TIDEncoding::Itemsets => self.run_tid(data, prev, i, observer, limiter, f),
+            TIDEncoding::Indices => self.run_tid_indices(data, prev, i, observer, limiter, f),
+        }
+    }
+    /// Runs Apriori until the switch.
+    /// Returns the last frequent itemsets and the pass to switch at,
+    /// or None if there are no more frequent itemsets.
+    fn run_apriori(
+        &self,
+        data: &TransactionSet,
+        observer: &mut impl PassObserver,
+        limiter: &mut Limiter,
+        f: &mut impl FnMut(&mut dyn Iterator<Item = (&Vec<usize>, &u64)>, &mut Limiter) -> Emitted,
+    ) -> Result<Option<(Candidates, usize)>, Truncation> {
+        let timer = PassTimer::start(1, observer);
+        limiter.candidates(1, data.num_items)?;
+        let mut prev = apriori_run_one(data, self.min_support)?;
+        timer.end(observer, PassCounts::new(data.num_items), prev.len());
+        f(&mut prev.iter(), limiter)?;
+        let (mut switch, mut heuristic) = match self.switch {
+            SwitchPass::Fixed(n) => (n, None),
+            SwitchPass::Auto(memory) => (
+                usize::MAX,
+                Some(SwitchHeuristic::new(memory, self.encoding, prev.len())),
+            ),
+        };
+        let mut i = 2;
+        // Stops after the pass of the longest itemsets
+        while self.lengths.extends(i - 1) {
+            if i >= switch {
+                return Ok(Some((prev, i)));
+            }
+            let timer = PassTimer::start(i, observer);
+            let (next, support, counts) =
+                apriori_pass(prev.deref(), data, i, self.min_support, limiter)?;
+            timer.end(observer, counts, next.len());
+            if next.is_empty() {
+                return Ok(None);
             }
-            // Apriori
-            if i < self.switch {
-                let prev = apriori.last().unwrap();
-                let next = AprioriCandidates::new(prev.deref()).run(data, i, self.min_support);
-                if next.is_empty() {
-                    break;
-                }
-                apriori.push(next);
-            } else {
-                // AprioriTID
-                let prev = apriori.last().unwrap();
-                let (next, b) =
-                    AprioriTiDCandidates::new(prev.deref()).next(&prev_trans, self.min_support);
-                if next.is_empty() {
-                    break;
-                }
-                prev_trans = b;
-                apriori.push(next);
+            if let Some(h) = &mut heuristic
+                && h.next_pass(i, support, data.len(), next.len())
+            {
+                switch = i + 1;
             }
+            prev = next;
+            f(&mut prev.iter(), limiter)?;
+            i += 1;
         }
-        apriori
+        Ok(None)
     }
-    /// Runs the algorithm
-    pub fn run_fn(&self, data: &TransactionSet, mut f: impl FnMut(&[usize])) {
-        // The frequent itemsets generated
-        let mut prev = apriori_run_one(data, self.min_support);
-        prev.iter().for_each(|v| f(v));
-        let mut prev_trans = TransactionIDs::default();
-        for i in 2.. {
-            // When we switch, we generate TIDs from the transaction set
-            if i == self.switch {
-                let (tree, a) =
-                    TransactionIDs::from_transaction(&data.transactions, i - 1, prev.deref());
-                let mut c = Candidates::default();
-                tree.iter().for_each(|(v, count)| {
-                    if count < self.min_support {
-                        return;
-                    }
-                    c.insert(v.to_vec());
-                });
-                prev_trans = a;
-                prev = c;
-                prev.iter().for_each(|v| f(v));
+    /// Runs AprioriTID from pass i
+    fn run_tid(
+        &self,
+        data: &TransactionSet,
+        prev: Candidates,
+        i: usize,
+        observer: &mut impl PassObserver,
+        limiter: &mut Limiter,
+        mut f: impl FnMut(&mut dyn Iterator<Item = (&Vec<usize>, &u64)>, &mut Limiter) -> Emitted,
+    ) -> Result<(), Truncation> {
+        // When we switch, we generate TIDs from the transaction set
+        let timer = PassTimer::start(i, observer);
+        let (mut tree, pruned) = AprioriCandidates::new(prev.deref()).create_tree_pruned();
+        let counts = PassCounts {
+            candidates: tree.len(),
+            pruned,
+        };
+        limiter.candidates(i, counts.candidates)?;
+        let mut prev_trans = TransactionIDs::from_tree(data, i - 1, &mut tree);
+        let mut prev = Candidates::default();
+        tree.iter().for_each(|(v, count)| {
+            if count < self.min_support {
+                return;
+            }
+            prev.insert(v.to_vec(), count);
+        });
+        timer.end(observer, counts, prev.len());
+        if prev.is_empty() {
+            return Ok(());
+        }
+        f(&mut prev.iter(), limiter)?;
+        // AprioriTID
+        for i in (i + 1)..=self.lengths.max {
+            let timer = PassTimer::start(i, observer);
+            let (next, b, counts) = AprioriTiDCandidates::new(prev.deref()).next_counted(
+                &prev_trans,
+                self.min_support,
+                i,
+                limiter,
+            )?;
+            timer.end(observer, counts, next.len());
+            if next.is_empty() {
+                break;
+            }
+            prev_trans = b;
+            prev = next;
+            f(&mut prev.iter(), limiter)?;
+        }
+        Ok(())
+    }
+    /// Runs AprioriTID from pass i with TIDs as indices
+    fn run_tid_indices(
+        &self,
+        data: &TransactionSet,
+        prev: Candidates,
+        i: usize,
+        observer: &mut impl PassObserver,
+        limiter: &mut Limiter,
+        mut f: impl FnMut(&mut dyn Iterator<Item = (&Vec<usize>, &u64)>, &mut Limiter) -> Emitted,
+    ) -> Result<(), Truncation> {
+        // At the switch, we generate TIDs from the transaction set
+        let mut timer = Some(PassTimer::start(i, observer));
+        let mut prev = IndexedItemsets::from(prev.deref());
+        let mut prev_trans = TransactionIndices::from_transaction(data, i - 1, &prev);
+        // AprioriTID
+        for i in i..=self.lengths.max {
+            // The pass at the switch also includes generating the TIDs
+            let timer = timer.take().unwrap_or_else(|| PassTimer::start(i, observer));
+            let (next, b, counts) =
+                prev.next_counted(&prev_trans, self.min_support, i, limiter)?;
+            timer.end(observer, counts, next.len());
+            if next.is_empty() {
+                break;
+            }
+            prev_trans = b;
+            prev = next;
+            f(&mut prev.iter(), limiter)?;
+        }
+        Ok(())
+    }
+}
+/// What emitting the frequent itemsets of a pass returns
+type Emitted = Result<(), Truncation>;
+/// Runs pass i of Apriori.
+/// Returns the frequent itemsets, the sum of the supports of all the candidates,
+/// and the number of candidates counted and pruned.
+fn apriori_pass(
+    prev: &CandidateType,
+    data: &TransactionSet,
+    i: usize,
+    min_sup: u64,
+    limiter: &Limiter,
+) -> Result<(Candidates, u64, PassCounts), Truncation> {
+    let mut set = Candidates::default();
+    let mut support = 0;
+    if i == 2 {
+        let pairs = prev.len() * prev.len().saturating_sub(1) / 2;
+        limiter.candidates(i, pairs)?;
+        // The candidates are the pairs of frequent items
+        let mut frequent = vec![false; data.num_items];
+        prev.keys().for_each(|v| frequent[v[0]] = true);
+        for (r, c, count) in apriori_run_two_count(data)?.iter() {
+            if !frequent[c] || !frequent[r] {
                 continue;
             }
-            // Apriori
-            if i < self.switch {
-                let next = AprioriCandidates::new(prev.deref()).run(data, i, self.min_support);
-                if next.is_empty() {
-                    break;
-                }
-                prev = next;
-                prev.iter().for_each(|v| f(v));
-            } else {
-                // AprioriTID
-                let (next, b) =
-                    AprioriTiDCandidates::new(prev.deref()).next(&prev_trans, self.min_support);
-                if next.is_empty() {
-                    break;
-                }
-                prev_trans = b;
-                prev = next;
-                prev.iter().for_each(|v| f(v));
+            support += count;
+            if count >= min_sup {
+                set.insert(vec![c, r], count);
             }
         }
+        return Ok((set, support, PassCounts::new(pairs)));
+    }
+    let (mut tree, pruned) = AprioriCandidates::new(prev).create_tree_pruned();
+    limiter.candidates(i, tree.len())?;
+    data.scan(|d| apriori_count(d, i, tree.deref_mut(), |_| {}))?;
+    for (arr, n) in tree.iter() {
+        support += n;
+        if n >= min_sup {
+            set.insert(arr.to_vec(), n);
+        }
+    }
+    let counts = PassCounts {
+        candidates: tree.len(),
+        pruned,
+    };
+    Ok((set, support, counts))
+}
+/// The heuristic from the AprioriHybrid paper for when to switch to AprioriTID.
+/// The TIDs of a pass have one entry per candidate in each transaction,
+/// so their size is estimated from the sum of the supports of the candidates.
+/// It switches when that estimate fits in memory and the frequent itemsets
+/// are shrinking, as the TIDs should then keep getting smaller.
+#[derive(Debug, Clone)]
+pub struct SwitchHeuristic {
+    /// The memory available for the TIDs in bytes
+    memory: usize,
+    encoding: TIDEncoding,
+    /// The number of frequent itemsets in the previous pass
+    prev_frequent: usize,
+}
+
+impl SwitchHeuristic {
+    /// Constructor with the number of frequent items
+    pub fn new(memory: usize, encoding: TIDEncoding, frequent_items: usize) -> Self {
+        Self {
+            memory,
+            encoding,
+            prev_frequent: frequent_items,
+        }
+    }
+    /// Estimates the size of the TIDs of pass k in bytes
+    pub fn estimate(&self, k: usize, support: u64, transactions: usize) -> usize {
+        let (entry, tid) = match self.encoding {
+            TIDEncoding::Itemsets => (
+                size_of::<Vec<usize>>() + k * size_of::<usize>(),
+                size_of::<TransactionID>(),
+            ),
+            TIDEncoding::Indices => (size_of::<u32>(), size_of::<TransactionIndex>()),
+        };
+        (support as usize)
+            .saturating_mul(entry)
+            .saturating_add(transactions * tid)
+    }
+    /// Called at the end of Apriori pass k (k >= 2) with the sum of the supports of the candidates,
+    /// the number of transactions, and the number of frequent itemsets found.
+    /// Returns true if the next pass should switch to AprioriTID.
+    pub fn next_pass(
+        &mut self,
+        k: usize,
+        support: u64,
+        transactions: usize,
+        frequent: usize,
+    ) -> bool {
+        let shrinking = frequent < self.prev_frequent;
+        self.prev_frequent = frequent;
+        shrinking && self.estimate(k, support, transactions) <= self.memory
+    }
+}
+/// Gets the available memory in bytes.
+/// Reads /proc/meminfo, and defaults to 1 GiB when it is not available.
+pub fn available_memory() -> usize {
+    const DEFAULT: usize = 1 << 30;
+    let Ok(s) = fs::read_to_string("/proc/meminfo") else {
+        return DEFAULT;
+    };
+    for l in s.lines() {
+        let Some(l) = l.strip_prefix("MemAvailable:") else {
+            continue;
+        };
+        // The value is in kB
+        return l
+            .split_whitespace()
+            .next()
+            .and_then(|n| n.parse::<usize>().ok())
+            .map_or(DEFAULT, |n| n * 1024);
     }
+    DEFAULT
 }
 #[cfg(test)]
 mod tests {
+    use std::ops::Deref;
+
     use datasets::transaction_set::TransactionSet;
 
-    use crate::apriori_hybrid::AprioriHybrid;
+    use crate::{
+        apriori_hybrid::{AprioriHybrid, SwitchPass},
+        apriori_tid::TIDEncoding,
+    };
 
     #[test]
     fn test_apriori_tid() {
@@ -134,11 +386,42 @@ mod tests {
             ],
             5,
         );
-        let apriori = AprioriHybrid::new(2, 3);
+        let mut apriori = AprioriHybrid::new(2, 3);
         let result = apriori.run(&example);
         assert_eq!(result.len(), 3);
         assert_eq!(result[0].len(), 5);
         assert_eq!(result[1].len(), 6);
         assert_eq!(result[2].len(), 2);
+        let mut apriori = AprioriHybrid::with_encoding(2, 3, TIDEncoding::Indices);
+        let result2 = apriori.run(&example);
+        assert_eq!(result2.len(), 3);
+        for (a, b) in result.iter().zip(result2.iter()) {
+            assert_eq!(a.deref(), b.deref());
+        }
+    }
+    #[test]
+    fn test_auto_switch() {
+        let example = TransactionSet::new(
+            vec![
+                vec![0, 1, 2, 3],
+                vec![0, 1, 2, 3],
+                vec![0, 1, 2],
+                vec![0, 1, 3],
+                vec![1, 2, 3],
+                vec![0, 4],
+            ],
+            5,
+        );
+        // 4 frequent items, 6 frequent pairs, 4 frequent triples
+        let mut apriori =
+            AprioriHybrid::with_switch(2, SwitchPass::Auto(1 << 20), TIDEncoding::Itemsets);
+        let result = apriori.run(&example);
+        assert_eq!(result.len(), 4);
+        assert_eq!(result[2].len(), 4);
+        assert_eq!(apriori.switched(), Some(4));
+        // Nothing fits into no memory
+        let mut apriori = AprioriHybrid::with_switch(2, SwitchPass::Auto(0), TIDEncoding::Indices);
+        assert_eq!(apriori.run(&example).len(), 4);
+        assert_eq!(apriori.switched(), None);
     }
 }
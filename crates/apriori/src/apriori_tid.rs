@@ -1,30 +1,76 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    io,
+    ops::{Deref, DerefMut},
+};
 
-use datasets::transaction_set::TransactionSet;
+use datasets::{
+    budget::{Budget, Limiter, Status, Truncation},
+    constraint::Lengths,
+    transaction_set::TransactionSet,
+};
 
 use crate::{
     apriori::{AprioriCandidates, apriori_run_one},
     candidates::{CandidateType, Candidates},
+    checkpoint::{Checkpoint, CheckpointKind, Checkpointer, Tids},
     hash_tree::AprioriHashTree,
+    observer::{PassCounts, PassObserver, PassTimer},
     transaction_id::TransactionIDs,
+    transaction_index::{IndexedItemsets, TransactionIndices},
 };
+/// How the TIDs are stored for AprioriTID
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TIDEncoding {
+    /// Each TID is a set of the itemsets in the transaction
+    #[default]
+    Itemsets,
+    /// Each TID is a sorted list of indices into the itemsets of the pass
+    Indices,
+}
 /// The AprioriTID algorithm
 pub struct AprioriTID {
     min_support: u64,
+    encoding: TIDEncoding,
+    lengths: Lengths,
 }
 
 impl AprioriTID {
     /// Constructor
     pub fn new(min_support: u64) -> Self {
-        Self { min_support }
+        Self::with_encoding(min_support, TIDEncoding::default())
+    }
+    /// Constructor with the way TIDs are stored
+    pub fn with_encoding(min_support: u64, encoding: TIDEncoding) -> Self {
+        Self {
+            min_support,
+            encoding,
+            lengths: Lengths::default(),
+        }
+    }
+    /// Only finds itemsets with these lengths, stopping after the pass of the longest
+    pub fn lengths(mut self, lengths: Lengths) -> Self {
+        self.lengths = lengths;
+        self
     }
     /// Runs the algorithm a different (but proper) way, but slower
     pub fn run(&self, data: &TransactionSet) -> Vec<Candidates> {
+        if self.encoding == TIDEncoding::Indices {
+            let mut v = Vec::new();
+            let budget = Budget::default();
+            let mut limiter = budget.start();
+            let resume = Checkpoint::default();
+            let _ = self.run_indices(data, &mut (), &mut limiter, None, resume, |c, _| {
+                v.push(c.to_candidates());
+                Ok(())
+            });
+            return v;
+        }
         // Gets all the frequent items
-        let mut v = vec![apriori_run_one(data, self.min_support)];
+        let first = apriori_run_one(data, self.min_support);
+        let mut v = vec![first.expect("Transactions in memory can always be read")];
         // Generates the TIDs
         let mut prev_transactions = TransactionIDs::from(data);
-        loop {
+        while self.lengths.extends(v.len()) {
             let prev = v.last().unwrap();
             // Finds the frequent itemsets and next TIDs
             let (next, next_t) =
@@ -39,22 +85,176 @@ impl AprioriTID {
     }
     /// Runs the algorithm a different (but proper) way, but slower
     pub fn run_fn(&self, data: &TransactionSet, mut f: impl FnMut(&[usize])) {
-        // Gets all the frequent items
-        let mut prev = apriori_run_one(data, self.min_support);
-        prev.iter().for_each(|v| f(v));
-        // Generates the TIDs
-        let mut prev_transactions = TransactionIDs::from(data);
-        loop {
+        self.run_observed(data, &mut (), &Budget::default(), |v, _| f(v));
+    }
+    /// Runs the algorithm, calling f with each frequent itemset and its support,
+    /// telling the observer about each pass, and stopping early if it goes over the budget
+    pub fn run_observed(
+        &self,
+        data: &TransactionSet,
+        observer: &mut impl PassObserver,
+        budget: &Budget,
+        f: impl FnMut(&[usize], u64),
+    ) -> Status {
+        let mut limiter = budget.start();
+        let resume = Checkpoint::default();
+        self.run_limited(data, observer, &mut limiter, None, resume, f)
+            .into()
+    }
+    /// Runs the algorithm like run_observed, saving the frequent itemsets and TIDs after each pass.
+    /// A resuming checkpointer continues from the last pass saved,
+    /// calling f with the itemsets of the saved passes first.
+    pub fn run_checkpointed(
+        &self,
+        data: &TransactionSet,
+        observer: &mut impl PassObserver,
+        budget: &Budget,
+        checkpointer: &mut Checkpointer,
+        f: impl FnMut(&[usize], u64),
+    ) -> io::Result<Status> {
+        let kind = match self.encoding {
+            TIDEncoding::Itemsets => CheckpointKind::AprioriTID,
+            TIDEncoding::Indices => CheckpointKind::AprioriTIDIndices,
+        };
+        let resume = checkpointer.start(kind, self.min_support, data)?;
+        let mut limiter = budget.start();
+        let result = self.run_limited(data, observer, &mut limiter, Some(checkpointer), resume, f);
+        checkpointer.finish()?;
+        Ok(result.into())
+    }
+    fn run_limited(
+        &self,
+        data: &TransactionSet,
+        observer: &mut impl PassObserver,
+        limiter: &mut Limiter,
+        checkpointer: Option<&mut Checkpointer>,
+        resume: Checkpoint,
+        mut f: impl FnMut(&[usize], u64),
+    ) -> Result<(), Truncation> {
+        if self.encoding == TIDEncoding::Indices {
+            let f = |c: &IndexedItemsets, limiter: &mut Limiter| match c.iter().next() {
+                Some((v, _)) if self.lengths.contains(v.len()) => {
+                    limiter.emit_all(c.iter(), &mut f)
+                }
+                _ => Ok(()),
+            };
+            return self.run_indices(data, observer, limiter, checkpointer, resume, f);
+        }
+        self.run_itemsets(data, observer, limiter, checkpointer, resume, f)
+    }
+    /// Runs the algorithm with TIDs as sets of itemsets
+    fn run_itemsets(
+        &self,
+        data: &TransactionSet,
+        observer: &mut impl PassObserver,
+        limiter: &mut Limiter,
+        mut checkpointer: Option<&mut Checkpointer>,
+        resume: Checkpoint,
+        mut f: impl FnMut(&[usize], u64),
+    ) -> Result<(), Truncation> {
+        let lengths = self.lengths;
+        let mut frequent = resume.frequent;
+        for (i, set) in frequent.iter().enumerate() {
+            if lengths.contains(i + 1) {
+                limiter.emit_all(set.iter(), &mut f)?;
+            }
+        }
+        let mut prev_transactions = match resume.tids {
+            Some(Tids::Itemsets(tids)) => tids,
+            _ => {
+                // Gets all the frequent items
+                let timer = PassTimer::start(1, observer);
+                limiter.candidates(1, data.num_items)?;
+                frequent.push(apriori_run_one(data, self.min_support)?);
+                timer.end(observer, PassCounts::new(data.num_items), frequent[0].len());
+                // Generates the TIDs
+                let tids = TransactionIDs::from(data);
+                if let Some(c) = checkpointer.as_deref_mut() {
+                    c.save_itemsets(&frequent, &tids);
+                }
+                if lengths.contains(1) {
+                    limiter.emit_all(frequent[0].iter(), &mut f)?;
+                }
+                tids
+            }
+        };
+        for i in (frequent.len() + 1)..=lengths.max {
             // Finds the frequent itemsets and next TIDs
-            let (next, next_t) =
-                AprioriTiDCandidates::new(prev.deref()).next(&prev_transactions, self.min_support);
+            let timer = PassTimer::start(i, observer);
+            let prev = frequent.last().unwrap();
+            let (next, next_t, counts) = AprioriTiDCandidates::new(prev.deref())
+                .next_counted(&prev_transactions, self.min_support, i, limiter)?;
+            timer.end(observer, counts, next.len());
+            if next.is_empty() {
+                break;
+            }
+            prev_transactions = next_t;
+            // Only a checkpoint needs the earlier passes
+            if checkpointer.is_none() {
+                frequent.clear();
+            }
+            frequent.push(next);
+            if let Some(c) = checkpointer.as_deref_mut() {
+                c.save_itemsets(&frequent, &prev_transactions);
+            }
+            if lengths.contains(i) {
+                limiter.emit_all(frequent.last().unwrap().iter(), &mut f)?;
+            }
+        }
+        Ok(())
+    }
+    /// Runs the algorithm with TIDs as indices, calling f with the frequent itemsets of each pass
+    fn run_indices(
+        &self,
+        data: &TransactionSet,
+        observer: &mut impl PassObserver,
+        limiter: &mut Limiter,
+        mut checkpointer: Option<&mut Checkpointer>,
+        resume: Checkpoint,
+        mut f: impl FnMut(&IndexedItemsets, &mut Limiter) -> Result<(), Truncation>,
+    ) -> Result<(), Truncation> {
+        // The passes are only kept as Candidates for the checkpoint
+        let mut frequent = resume.frequent;
+        for set in &frequent {
+            f(&IndexedItemsets::from(set.deref()), limiter)?;
+        }
+        let (mut prev, mut prev_transactions) = match (frequent.last(), resume.tids) {
+            (Some(set), Some(Tids::Indices(tids))) => (IndexedItemsets::from(set.deref()), tids),
+            _ => {
+                let timer = PassTimer::start(1, observer);
+                limiter.candidates(1, data.num_items)?;
+                let prev = IndexedItemsets::from(apriori_run_one(data, self.min_support)?.deref());
+                timer.end(observer, PassCounts::new(data.num_items), prev.len());
+                if prev.is_empty() {
+                    return Ok(());
+                }
+                let tids = TransactionIndices::start(data, &prev);
+                if let Some(c) = checkpointer.as_deref_mut() {
+                    frequent.push(prev.to_candidates());
+                    c.save_indices(&frequent, &tids);
+                }
+                f(&prev, limiter)?;
+                (prev, tids)
+            }
+        };
+        let pass = prev.iter().next().map_or(1, |(v, _)| v.len());
+        for i in (pass + 1)..=self.lengths.max {
+            let timer = PassTimer::start(i, observer);
+            let (next, next_t, counts) =
+                prev.next_counted(&prev_transactions, self.min_support, i, limiter)?;
+            timer.end(observer, counts, next.len());
             if next.is_empty() {
                 break;
             }
             prev_transactions = next_t;
             prev = next;
-            prev.iter().for_each(|v| f(v));
+            if let Some(c) = checkpointer.as_deref_mut() {
+                frequent.push(prev.to_candidates());
+                c.save_indices(&frequent, &prev_transactions);
+            }
+            f(&prev, limiter)?;
         }
+        Ok(())
     }
 }
 /// Contains the algorithm for AprioriTID
@@ -67,15 +267,25 @@ impl<'a> AprioriTiDCandidates<'a> {
     /// Generates the frequent itemsets and next TIDs
     pub fn next(&self, data: &TransactionIDs, min_sup: u64) -> (Candidates, TransactionIDs) {
         let (tree, next) = self.count(data);
-        // Returns the new frequent itemsets
-        let mut new_candidates = Candidates::default();
-        tree.iter().for_each(|(v, n)| {
-            if n < min_sup {
-                return;
-            }
-            new_candidates.insert(v.to_vec());
-        });
-        (new_candidates, next)
+        (frequent(&tree, min_sup), next)
+    }
+    /// Same as next for pass i, but also returns the number of candidates counted and pruned.
+    /// Stops before counting if the limiter does not allow that many candidates.
+    pub fn next_counted(
+        &self,
+        data: &TransactionIDs,
+        min_sup: u64,
+        i: usize,
+        limiter: &Limiter,
+    ) -> Result<(Candidates, TransactionIDs, PassCounts), Truncation> {
+        let (mut tree, pruned) = AprioriCandidates::new(self.0).create_tree_pruned();
+        let counts = PassCounts {
+            candidates: tree.len(),
+            pruned,
+        };
+        limiter.candidates(i, counts.candidates)?;
+        let next = data.count(tree.deref_mut());
+        Ok((frequent(&tree, min_sup), next, counts))
     }
     /// Generates the counts for candidate itemsets and next TIDs
     pub fn count(&self, data: &TransactionIDs) -> (AprioriHashTree, TransactionIDs) {
@@ -86,11 +296,26 @@ impl<'a> AprioriTiDCandidates<'a> {
     }
 }
 
+/// The itemsets in the tree with at least the support
+fn frequent(tree: &AprioriHashTree, min_sup: u64) -> Candidates {
+    let mut set = Candidates::default();
+    tree.iter().for_each(|(v, n)| {
+        if n < min_sup {
+            return;
+        }
+        set.insert(v.to_vec(), n);
+    });
+    set
+}
+
 #[cfg(test)]
 mod tests {
-    use datasets::transaction_set::TransactionSet;
+    use datasets::{budget::Budget, transaction_set::TransactionSet};
+
+    use std::ops::Deref;
 
-    use super::AprioriTID;
+    use super::{AprioriTID, TIDEncoding};
+    use crate::{apriori::Apriori, observer::PassStats};
 
     #[test]
     fn test_apriori_tid() {
@@ -115,5 +340,30 @@ mod tests {
         assert_eq!(result[0].len(), 5);
         assert_eq!(result[1].len(), 6);
         assert_eq!(result[2].len(), 2);
+        let apriori = AprioriTID::with_encoding(2, TIDEncoding::Indices);
+        let result2 = apriori.run(&example);
+        assert_eq!(result2.len(), 3);
+        for (a, b) in result.iter().zip(result2.iter()) {
+            assert_eq!(a.deref(), b.deref());
+        }
+        // Both encodings count the same candidates in each pass as Apriori
+        let counts = |passes: Vec<PassStats>| -> Vec<_> {
+            passes
+                .iter()
+                .map(|p| (p.pass, p.candidates, p.pruned, p.frequent))
+                .collect()
+        };
+        let mut expected = Vec::new();
+        Apriori::new(2).run_observed(&example, &mut expected, &Budget::default(), |_, _| {});
+        for encoding in [TIDEncoding::Itemsets, TIDEncoding::Indices] {
+            let mut passes = Vec::new();
+            AprioriTID::with_encoding(2, encoding).run_observed(
+                &example,
+                &mut passes,
+                &Budget::default(),
+                |_, _| {},
+            );
+            assert_eq!(counts(passes), counts(expected.clone()));
+        }
     }
 }
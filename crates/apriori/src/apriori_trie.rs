@@ -1,57 +1,255 @@
-use datasets::transaction_set::TransactionSet;
+use std::{io, thread};
 
-use crate::trie::AprioriTrie;
+use datasets::{
+    budget::{Budget, Limiter, Status, Truncation},
+    constraint::Lengths,
+    transaction_set::TransactionSet,
+    transaction_source::TransactionSource,
+    utils::Item,
+};
+
+use crate::{
+    observer::{PassCounts, PassObserver, PassTimer},
+    trie::AprioriTrie,
+};
+/// How AprioriT trims the transactions between passes
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Trimming {
+    /// Counts the transactions as they are
+    #[default]
+    None,
+    /// Removes the items that are in no candidate itemset,
+    /// and skips the transactions that are too short to contain a candidate.
+    Filter,
+    /// Filters, and keeps a trimmed copy of the transactions to count from once
+    /// it has at most this fraction (0 to 1) of the items of the dataset.
+    Project(f64),
+}
 /// Runs the Apriori Algorithm using a Trie
 #[derive(Debug)]
 pub struct AprioriT {
     min_sup: u64,
     trie: AprioriTrie,
+    trimming: Trimming,
+    /// The number of threads used to count
+    threads: usize,
+    /// The lengths of the itemsets found
+    lengths: Lengths,
 }
 
 impl AprioriT {
     /// Constructor
     pub fn new(min_sup: u64) -> Self {
-        Self { min_sup, trie: AprioriTrie::new() }
+        Self::with_trimming(min_sup, Trimming::default())
+    }
+    /// Constructor with how the transactions are trimmed
+    pub fn with_trimming(min_sup: u64, trimming: Trimming) -> Self {
+        Self::with_threads(min_sup, trimming, 1)
+    }
+    /// Constructor with how the transactions are trimmed and the number of threads to count with
+    pub fn with_threads(min_sup: u64, trimming: Trimming, threads: usize) -> Self {
+        assert!(threads > 0);
+        Self {
+            min_sup,
+            trie: AprioriTrie::new(),
+            trimming,
+            threads,
+            lengths: Lengths::default(),
+        }
+    }
+    /// Only counts itemsets up to the longest of these lengths.
+    /// The trie still has the shorter itemsets, which are needed to find the longer ones.
+    pub fn lengths(mut self, lengths: Lengths) -> Self {
+        self.lengths = lengths;
+        self
     }
     /// Runs the algorithm
-    pub fn run(&mut self, t: &TransactionSet) {
+    pub fn run(&mut self, t: &(impl TransactionSource + Sync)) -> io::Result<()> {
+        self.run_observed(t, &mut (), &Budget::default()).read()
+    }
+    /// Runs the algorithm, telling the observer about each pass,
+    /// and stopping early if it goes over the budget.
+    /// The trie then has the frequent itemsets of the passes that were done.
+    pub fn run_observed(
+        &mut self,
+        t: &(impl TransactionSource + Sync),
+        observer: &mut impl PassObserver,
+        budget: &Budget,
+    ) -> Status {
+        self.run_limited(t, observer, &mut budget.start()).into()
+    }
+    fn run_limited(
+        &mut self,
+        t: &(impl TransactionSource + Sync),
+        observer: &mut impl PassObserver,
+        limiter: &mut Limiter,
+    ) -> Result<(), Truncation> {
+        let timer = PassTimer::start(1, observer);
+        limiter.candidates(1, t.num_items())?;
         // Add all the items to the trie.
-        for i in 0..t.num_items {
+        for i in 0..t.num_items() {
             self.trie.add(&[i]);
         }
         // Count all the items
-        for v in t.iter() {
-            self.trie.transaction_update(v, 1);
+        self.count(t, 1, None, false)?;
+        let frequent = self.trie.count_at(1, self.min_sup);
+        timer.end(observer, PassCounts::new(t.num_items()), frequent);
+        if self.lengths.contains(1) {
+            limiter.found(frequent)?;
+        }
+        // The trimmed copy of the transactions
+        let mut projected: Option<TransactionSet> = None;
+        // The number of items left in the transactions after trimming
+        let mut size = 0;
+        if let Trimming::Project(_) = self.trimming {
+            t.scan(|v| size += v.len())?;
         }
-        for i in 2.. {
+        let total = size;
+        for i in 2..=self.lengths.max {
+            let timer = PassTimer::start(i, observer);
             // Cleans up the tree
             self.trie.cleaup(self.min_sup);
             let prev = self.trie.size();
-            // The join step for Apriori
-            self.trie.join(i, self.min_sup);
+            // The join and prune steps for Apriori
+            let pruned = self.trie.join(i, self.min_sup);
             // If the size does not change, then the algorithm is done
             if self.trie.size() <= prev {
+                // The pass only has stats if the join found itemsets
+                if pruned > 0 {
+                    let counts = PassCounts {
+                        candidates: 0,
+                        pruned,
+                    };
+                    timer.end(observer, counts, 0);
+                }
                 break;
             }
-            // Count up all the itemsets
-            for v in t.iter() {
-                self.trie.transaction_update(v, i);
+            limiter.candidates(i, self.trie.size() - prev)?;
+            if self.trimming == Trimming::None {
+                // Count up all the itemsets
+                self.count(t, i, None, false)?;
+            } else {
+                // Only the items of the candidates can be counted
+                let mut items = vec![false; t.num_items()];
+                self.trie.mark_items(i, &mut items);
+                if let Some(p) = &mut projected {
+                    p.retain_items(|n| items[n], i);
+                    self.count(p, i, None, false)?;
+                } else {
+                    // The trimmed size can only shrink, so the last size decides whether to project
+                    let project = match self.trimming {
+                        Trimming::Project(f) => (size as f64) <= f * (total as f64),
+                        _ => false,
+                    };
+                    let (s, p) = self.count(t, i, Some(&items), project)?;
+                    size = s;
+                    if project {
+                        projected = Some(TransactionSet::new(p, t.num_items()));
+                    }
+                }
+            }
+            let frequent = self.trie.count_at(i, self.min_sup);
+            let counts = PassCounts {
+                candidates: self.trie.count_at(i, 0),
+                pruned,
+            };
+            timer.end(observer, counts, frequent);
+            if self.lengths.contains(i) {
+                limiter.found(frequent)?;
             }
         }
+        Ok(())
+    }
+    /// Counts the itemsets of size i in the transactions, trimmed to the items (if given).
+    /// Returns the number of items counted, and the trimmed transactions if keep is true.
+    fn count(
+        &mut self,
+        data: &(impl TransactionSource + Sync),
+        i: usize,
+        items: Option<&[bool]>,
+        keep: bool,
+    ) -> io::Result<(usize, Vec<Vec<usize>>)> {
+        if self.threads == 1 {
+            let trie = &mut self.trie;
+            return trim_each(data, i, items, keep, |v| trie.transaction_update(v, i));
+        }
+        // Each thread counts a part of the transactions into its own array
+        let len = self.trie.number(i);
+        let trie = &self.trie;
+        let threads = self.threads;
+        let results: Vec<_> = thread::scope(|s| {
+            let handles: Vec<_> = (0..threads)
+                .map(|p| {
+                    s.spawn(move || {
+                        let mut counts = vec![0u64; len];
+                        let r = trim_each(&data.part(p, threads), i, items, keep, |v| {
+                            trie.transaction_count(v, i, &mut counts)
+                        });
+                        (counts, r)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        // Combines the results of the threads
+        let mut counts = vec![0u64; len];
+        let mut size = 0;
+        let mut kept = Vec::new();
+        for (c, r) in results {
+            let (s, k) = r?;
+            for (a, b) in counts.iter_mut().zip(c) {
+                *a += b;
+            }
+            size += s;
+            kept.extend(k);
+        }
+        self.trie.add_counts(i, &counts);
+        Ok((size, kept))
     }
     /// Gets the trie from the algorithm
     pub fn trie(self) -> AprioriTrie {
         self.trie
     }
 }
+/// Calls f with each transaction of size at least i, trimmed to the items (if given).
+/// Returns the number of items in them, and the trimmed transactions if keep is true.
+fn trim_each<T: TransactionSource>(
+    data: &T,
+    i: usize,
+    items: Option<&[bool]>,
+    keep: bool,
+    mut f: impl FnMut(&[T::Item]),
+) -> io::Result<(usize, Vec<Vec<usize>>)> {
+    let mut size = 0;
+    let mut kept = Vec::new();
+    let Some(items) = items else {
+        data.scan(f)?;
+        return Ok((size, kept));
+    };
+    let mut buffer = Vec::new();
+    data.scan(|v| {
+        buffer.clear();
+        buffer.extend(v.iter().copied().filter(|n| items[n.index()]));
+        // Too short to contain a candidate
+        if buffer.len() < i {
+            return;
+        }
+        size += buffer.len();
+        f(&buffer);
+        if keep {
+            kept.push(buffer.iter().map(|n| n.index()).collect());
+        }
+    })?;
+    Ok((size, kept))
+}
 
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
 
-    use datasets::transaction_set::TransactionSet;
+    use datasets::{budget::Budget, transaction_set::TransactionSet};
 
-    use super::AprioriT;
+    use super::{AprioriT, Trimming};
 
     #[test]
     fn test_apriorit() {
@@ -70,7 +268,7 @@ mod tests {
             5,
         );
         let mut a = AprioriT::new(2);
-        a.run(&example);
+        a.run(&example).unwrap();
         let mut set = HashSet::new();
         a.trie.for_each(2, |v| {
             set.insert(v.to_vec());
@@ -82,5 +280,37 @@ mod tests {
         assert!(set.contains(&vec![1, 3]));
         assert!(set.contains(&vec![1, 4]));
         assert_eq!(set.len(), 13);
+        for trimming in [Trimming::Filter, Trimming::Project(1.0)] {
+            let mut b = AprioriT::with_trimming(2, trimming);
+            b.run(&example).unwrap();
+            let mut set2 = HashSet::new();
+            b.trie.for_each(2, |v| {
+                set2.insert(v.to_vec());
+            });
+            assert_eq!(set, set2);
+        }
+        let mut b = AprioriT::with_threads(2, Trimming::Project(0.5), 3);
+        b.run(&example).unwrap();
+        let mut set2 = HashSet::new();
+        b.trie.for_each(2, |v| {
+            set2.insert(v.to_vec());
+        });
+        assert_eq!(set, set2);
+        // Pass 3 joins 6 itemsets, 4 of which have an infrequent pair,
+        // and pass 4 only has a pruned itemset
+        let mut passes = Vec::new();
+        AprioriT::new(2).run_observed(&example, &mut passes, &Budget::default());
+        let counts: Vec<_> = passes
+            .iter()
+            .map(|p| (p.pass, p.candidates, p.pruned, p.frequent))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![(1, 5, 0, 5), (2, 10, 0, 6), (3, 2, 4, 2), (4, 0, 1, 0)]
+        );
+        // Without any itemsets to join, the last pass has no stats
+        let mut passes = Vec::new();
+        AprioriT::new(3).run_observed(&example, &mut passes, &Budget::default());
+        assert_eq!(passes.last().map(|p| p.pass), Some(3));
     }
 }
\ No newline at end of file
@@ -29,7 +29,7 @@ impl<T> Array2D<T> {
         self.0[index] = value;
     }
     /// Iterator over all the element of the 2D array.
-    pub fn iter(&self) -> Array2DIterator<T> {
+    pub fn iter(&self) -> Array2DIterator<'_, T> {
         Array2DIterator::new(self)
     }
 }
@@ -1,6 +1,7 @@
-use std::{collections::{HashMap, HashSet}, ops::{Deref, DerefMut}};
+use std::{collections::HashMap, ops::{Deref, DerefMut}};
 
-pub type CandidateType = HashSet<Vec<usize>>;
+/// The itemsets with their support
+pub type CandidateType = HashMap<Vec<usize>, u64>;
 pub type CandidateCounter = HashMap<Vec<usize>, u64>;
 /// A wrapper for the Candidates
 #[derive(Debug, Default, Clone)]
@@ -1,7 +1,13 @@
 use std::collections::HashMap;
 /// Joins together the itemsets for Apriori
-pub fn join<'a, T: FnMut(Vec<usize>), U: Iterator<Item = &'a Vec<usize>>>(
+pub fn join<'a, T: FnMut(Vec<usize>), U: Iterator<Item = &'a Vec<usize>>>(v: U, f: T) {
+    join_by(v, |a, b| a < b, f);
+}
+/// Joins together the itemsets like join, with the itemsets and their joins
+/// ordered by before instead of by item id
+pub fn join_by<'a, T: FnMut(Vec<usize>), U: Iterator<Item = &'a Vec<usize>>>(
     v: U,
+    before: impl Fn(usize, usize) -> bool,
     mut f: T,
 ) {
     // A map containing the prefixes and the last elements
@@ -20,7 +26,7 @@ pub fn join<'a, T: FnMut(Vec<usize>), U: Iterator<Item = &'a Vec<usize>>>(
                 let c2 = v[j];
                 // Create the join vec
                 let mut join = k.to_vec();
-                if c2 > c1 {
+                if before(c1, c2) {
                     join.push(c1);
                     join.push(c2);
                 } else {
@@ -0,0 +1,457 @@
+use std::{
+    borrow::Borrow,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use datasets::{transaction_source::TransactionSource, utils::Item};
+
+use crate::{
+    candidates::Candidates,
+    transaction_id::{TransactionID, TransactionIDs},
+    transaction_index::{TransactionIndex, TransactionIndices},
+};
+/// The first bytes of a checkpoint
+pub const MAGIC: &[u8; 8] = b"FIMCKP03";
+/// The length of the header, with the magic
+const HEADER_LEN: u64 = 8 + 6 * 8;
+/// The TIDs after the last pass
+const NO_TIDS: u64 = 0;
+const ITEMSET_TIDS: u64 = 1;
+const INDEX_TIDS: u64 = 2;
+/// The algorithm that wrote a checkpoint, which only that algorithm can resume
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckpointKind {
+    Apriori = 1,
+    AprioriTID = 2,
+    AprioriTIDIndices = 3,
+    CountDistribution = 4,
+}
+/// The TIDs of AprioriTID after a pass
+#[derive(Debug)]
+pub enum Tids {
+    Itemsets(TransactionIDs),
+    Indices(TransactionIndices),
+}
+/// The state of a level-wise run after its last completed pass
+#[derive(Debug, Default)]
+pub struct Checkpoint {
+    /// The frequent itemsets of each pass
+    pub frequent: Vec<Candidates>,
+    /// The TIDs after the last pass, for AprioriTID
+    pub tids: Option<Tids>,
+}
+impl Checkpoint {
+    /// The last pass that was completed (0 before the first)
+    pub fn pass(&self) -> usize {
+        self.frequent.len()
+    }
+}
+/// Saves the state of a run to a file after each pass, so it can be resumed.
+/// The file is little endian, and is laid out as
+/// - the header: the magic, then the kind, min support, number of items,
+///   number of transactions, number of entries, and hash of the dataset as u64
+/// - for each pass, the number of frequent itemsets as u64,
+///   and then for each one its items as u32 and its support as u64
+///
+/// Each pass is appended to the file once it is done,
+/// and a pass that was cut off while appending is dropped when resuming.
+/// AprioriTID replaces its TIDs after each pass in a second file, the path with .tids added:
+/// the header, the pass, the kind of TIDs as u64, then the number of TIDs as u64,
+/// and for each TID the number of itemsets (or indices) as u64
+/// and then their items (or indices) as u32.
+#[derive(Debug)]
+pub struct Checkpointer {
+    path: PathBuf,
+    /// Whether the run continues from the checkpoint already in the file
+    resume: bool,
+    /// The kind, min support, and dataset fingerprint of the run
+    header: [u64; 6],
+    /// The file the passes are appended to, once the run has started
+    file: Option<File>,
+    /// The number of passes in the file
+    saved: usize,
+    /// The first error from saving a checkpoint
+    error: Option<io::Error>,
+}
+impl Checkpointer {
+    /// Saves to path, starting from the first pass
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            resume: false,
+            header: [0; 6],
+            file: None,
+            saved: 0,
+            error: None,
+        }
+    }
+    /// Saves to path, continuing from the checkpoint already in it
+    pub fn resume(path: impl Into<PathBuf>) -> Self {
+        Self {
+            resume: true,
+            ..Self::new(path)
+        }
+    }
+    /// Starts a run on data, returning the state to continue from
+    /// (which is empty unless resuming).
+    /// The checkpoint must be from the same algorithm, support, and dataset.
+    pub fn start(
+        &mut self,
+        kind: CheckpointKind,
+        min_support: u64,
+        data: &impl TransactionSource,
+    ) -> io::Result<Checkpoint> {
+        let [num_items, transactions, entries, hash] = fingerprint(data)?;
+        self.header = [kind as u64, min_support, num_items, transactions, entries, hash];
+        self.saved = 0;
+        if !self.resume {
+            let mut out = BufWriter::new(File::create(&self.path)?);
+            write_header(&mut out, self.header)?;
+            let file = out.into_inner()?;
+            file.sync_all()?;
+            self.file = Some(file);
+            // The TIDs of an earlier run must not be read with the new passes
+            return match fs::remove_file(self.tids_path()) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(Checkpoint::default()),
+            };
+        }
+        let mut input = BufReader::new(File::open(&self.path)?);
+        self.read_header(&mut input)?;
+        // The passes, with where each one ends
+        let mut checkpoint = Checkpoint::default();
+        let mut ends = vec![HEADER_LEN];
+        loop {
+            match read_pass(&mut input, checkpoint.pass() + 1) {
+                Ok(set) => {
+                    checkpoint.frequent.push(set);
+                    ends.push(input.stream_position()?);
+                }
+                // The run was stopped while appending the last pass
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+        }
+        let tag = match kind {
+            CheckpointKind::AprioriTID => ITEMSET_TIDS,
+            CheckpointKind::AprioriTIDIndices => INDEX_TIDS,
+            _ => NO_TIDS,
+        };
+        if tag != NO_TIDS {
+            // The TIDs are saved after the pass, so they can be a pass behind
+            let (pass, tids) = self.read_tids(tag, checkpoint.pass())?;
+            checkpoint.frequent.truncate(pass);
+            checkpoint.tids = tids;
+        }
+        let mut file = OpenOptions::new().write(true).open(&self.path)?;
+        file.set_len(ends[checkpoint.pass()])?;
+        file.seek(SeekFrom::End(0))?;
+        self.file = Some(file);
+        self.saved = checkpoint.pass();
+        Ok(checkpoint)
+    }
+    /// Checks the magic and the header of the run
+    fn read_header(&self, input: &mut impl Read) -> io::Result<()> {
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("Not a checkpoint"));
+        }
+        for n in self.header {
+            if read_u64(input)? != n {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "The checkpoint is from a different algorithm, support, or dataset",
+                ));
+            }
+        }
+        Ok(())
+    }
+    /// Reads the TIDs with the pass they are from, which is at most the last pass saved.
+    /// There are none if the run was stopped before the TIDs of the first pass were saved.
+    fn read_tids(&self, tag: u64, last: usize) -> io::Result<(usize, Option<Tids>)> {
+        let mut input = match File::open(self.tids_path()) {
+            Ok(file) => BufReader::new(file),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((0, None)),
+            Err(e) => return Err(e),
+        };
+        self.read_header(&mut input)?;
+        let pass = read_u64(&mut input)? as usize;
+        if pass == 0 || pass > last || read_u64(&mut input)? != tag {
+            return Err(invalid("The checkpoint is corrupted"));
+        }
+        let len = read_u64(&mut input)?;
+        let tids = if tag == ITEMSET_TIDS {
+            let mut v = Vec::new();
+            for _ in 0..len {
+                let mut t = TransactionID::default();
+                for _ in 0..read_u64(&mut input)? {
+                    t.ids_mut().insert(read_itemset(&mut input, pass)?);
+                }
+                v.push(t);
+            }
+            Tids::Itemsets(TransactionIDs::new(v))
+        } else {
+            let mut v = Vec::new();
+            for _ in 0..len {
+                let mut ids = Vec::new();
+                for _ in 0..read_u64(&mut input)? {
+                    ids.push(read_u32(&mut input)?);
+                }
+                v.push(TransactionIndex::new(ids));
+            }
+            Tids::Indices(TransactionIndices::new(v))
+        };
+        Ok((pass, Some(tids)))
+    }
+    /// Saves the frequent itemsets of the passes since the last save
+    pub fn save(&mut self, frequent: &[impl Borrow<Candidates>]) {
+        self.save_with(frequent, None);
+    }
+    /// Saves the frequent itemsets of the new passes, with the TIDs of the last pass
+    pub fn save_itemsets(&mut self, frequent: &[impl Borrow<Candidates>], tids: &TransactionIDs) {
+        self.save_with(
+            frequent,
+            Some((ITEMSET_TIDS, tids.len(), &mut |out| {
+                for t in tids.iter() {
+                    write_u64(out, t.ids().len() as u64)?;
+                    for v in t.ids() {
+                        write_itemset(out, v)?;
+                    }
+                }
+                Ok(())
+            })),
+        );
+    }
+    /// Saves the frequent itemsets of the new passes, with the TIDs of the last pass
+    pub fn save_indices(
+        &mut self,
+        frequent: &[impl Borrow<Candidates>],
+        tids: &TransactionIndices,
+    ) {
+        self.save_with(
+            frequent,
+            Some((INDEX_TIDS, tids.len(), &mut |out| {
+                for t in tids.iter() {
+                    write_u64(out, t.ids().len() as u64)?;
+                    for &n in t.ids() {
+                        out.write_all(&n.to_le_bytes())?;
+                    }
+                }
+                Ok(())
+            })),
+        );
+    }
+    /// Appends the new passes to the file, and then writes the TIDs (if any)
+    /// to a temporary file and moves it over the last ones,
+    /// so a run that is killed while saving leaves the last checkpoint whole.
+    /// Stops saving after an error, which finish returns.
+    fn save_with(&mut self, frequent: &[impl Borrow<Candidates>], tids: Option<TidWriter>) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(e) = self.append(frequent, tids) {
+            self.error = Some(e);
+        }
+    }
+    fn append(
+        &mut self,
+        frequent: &[impl Borrow<Candidates>],
+        tids: Option<TidWriter>,
+    ) -> io::Result<()> {
+        let file = self.file.as_ref().expect("The run was started before saving");
+        let mut out = BufWriter::new(file);
+        for set in &frequent[self.saved..] {
+            let set = set.borrow();
+            write_u64(&mut out, set.len() as u64)?;
+            for (v, &n) in set.iter() {
+                write_itemset(&mut out, v)?;
+                write_u64(&mut out, n)?;
+            }
+        }
+        out.into_inner()?.sync_data()?;
+        self.saved = frequent.len();
+        let Some((tag, len, write)) = tids else {
+            return Ok(());
+        };
+        let path = self.tids_path();
+        let mut temp = path.clone().into_os_string();
+        temp.push(".tmp");
+        let mut out = BufWriter::new(File::create(&temp)?);
+        write_header(&mut out, self.header)?;
+        write_u64(&mut out, self.saved as u64)?;
+        write_u64(&mut out, tag)?;
+        write_u64(&mut out, len as u64)?;
+        write(&mut out)?;
+        out.into_inner()?.sync_all()?;
+        fs::rename(&temp, &path)
+    }
+    /// Ends the run, returning the error if a checkpoint could not be saved
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.file = None;
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+    /// The file the checkpoints are saved to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// The file the TIDs are saved to
+    fn tids_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".tids");
+        path.into()
+    }
+    /// Deletes the checkpoint, once the run is done with it
+    pub fn remove(self) -> io::Result<()> {
+        fs::remove_file(&self.path)?;
+        match fs::remove_file(self.tids_path()) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+/// The kind and number of TIDs to save, and how to write them
+type TidWriter<'a> = (u64, usize, &'a mut dyn FnMut(&mut BufWriter<File>) -> io::Result<()>);
+/// The number of items, transactions, and entries of data, and an FNV-1a hash of its items
+fn fingerprint(data: &impl TransactionSource) -> io::Result<[u64; 4]> {
+    let (mut transactions, mut entries) = (0, 0);
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut add = |n: u64| hash = (hash ^ n).wrapping_mul(0x100_0000_01b3);
+    data.scan(|t| {
+        transactions += 1;
+        entries += t.len() as u64;
+        for &n in t {
+            add(n.index() as u64 + 1);
+        }
+        // Ends the transaction, so the same items split differently hash differently
+        add(0);
+    })?;
+    Ok([data.num_items() as u64, transactions, entries, hash])
+}
+fn write_header(out: &mut impl Write, header: [u64; 6]) -> io::Result<()> {
+    out.write_all(MAGIC)?;
+    header.into_iter().try_for_each(|n| write_u64(out, n))
+}
+/// Reads the frequent itemsets of pass k
+fn read_pass(input: &mut impl Read, k: usize) -> io::Result<Candidates> {
+    let mut set = Candidates::default();
+    for _ in 0..read_u64(input)? {
+        let v = read_itemset(input, k)?;
+        set.insert(v, read_u64(input)?);
+    }
+    Ok(set)
+}
+fn invalid(e: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+fn write_u64(out: &mut impl Write, n: u64) -> io::Result<()> {
+    out.write_all(&n.to_le_bytes())
+}
+fn write_itemset(out: &mut impl Write, v: &[usize]) -> io::Result<()> {
+    for &n in v {
+        out.write_all(&(n as u32).to_le_bytes())?;
+    }
+    Ok(())
+}
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+/// Reads an itemset of size k
+fn read_itemset(input: &mut impl Read, k: usize) -> io::Result<Vec<usize>> {
+    (0..k).map(|_| Ok(read_u32(input)? as usize)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use datasets::transaction_set::TransactionSet;
+
+    use super::{CheckpointKind, Checkpointer, Tids};
+    use crate::{
+        candidates::Candidates,
+        transaction_id::{TransactionID, TransactionIDs},
+    };
+
+    #[test]
+    fn test_checkpoint() {
+        let path = env::temp_dir().join(format!("checkpoint_{}.ckp", std::process::id()));
+        let data = TransactionSet::new(vec![vec![0, 2], vec![1, 2], vec![0, 1, 2]], 3);
+        let set = |v: &[(Vec<usize>, u64)]| Candidates::new(v.iter().cloned().collect());
+        let frequent = vec![
+            set(&[(vec![0], 2), (vec![1], 2), (vec![2], 3)]),
+            set(&[(vec![0, 2], 2), (vec![1, 2], 2)]),
+        ];
+        let tids = TransactionIDs::new(vec![
+            TransactionID::new([vec![0, 2], vec![1, 2]].into_iter().collect()),
+            TransactionID::new([vec![1, 2]].into_iter().collect()),
+        ]);
+        let mut saver = Checkpointer::new(&path);
+        let kind = CheckpointKind::AprioriTID;
+        assert_eq!(saver.start(kind, 2, &data).unwrap().pass(), 0);
+        saver.save_itemsets(&frequent[..1], &tids);
+        let first = fs::read(&path).unwrap();
+        // Only the new pass is appended
+        saver.save_itemsets(&frequent, &tids);
+        saver.finish().unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(&bytes[..first.len()], &first[..]);
+
+        let mut resume = Checkpointer::resume(&path);
+        let checkpoint = resume.start(kind, 2, &data).unwrap();
+        assert_eq!(checkpoint.pass(), 2);
+        for (a, b) in checkpoint.frequent.iter().zip(&frequent) {
+            assert_eq!(**a, **b);
+        }
+        let Some(Tids::Itemsets(read)) = checkpoint.tids else {
+            panic!("The TIDs were not read");
+        };
+        let read: Vec<_> = read.iter().map(|t| t.ids().clone()).collect();
+        let tids: Vec<_> = tids.iter().map(|t| t.ids().clone()).collect();
+        assert_eq!(read, tids);
+        resume.finish().unwrap();
+        // Only the same run on the same dataset can resume it
+        let start = |kind, min_support, data: &TransactionSet| {
+            Checkpointer::resume(&path).start(kind, min_support, data)
+        };
+        assert!(start(CheckpointKind::Apriori, 2, &data).is_err());
+        assert!(start(kind, 3, &data).is_err());
+        let shorter = TransactionSet::new(vec![vec![0, 2], vec![1, 2], vec![0, 2]], 3);
+        assert!(start(kind, 2, &shorter).is_err());
+        // The same number of transactions and entries, with different items
+        let changed = TransactionSet::new(vec![vec![0, 1], vec![1, 2], vec![0, 1, 2]], 3);
+        assert!(start(kind, 2, &changed).is_err());
+        // The TIDs are saved after their pass, so they can't be from a pass that was cut off
+        fs::write(&path, &bytes[..(bytes.len() - 4)]).unwrap();
+        assert!(start(kind, 2, &data).is_err());
+
+        // Without TIDs, the run continues from the last whole pass
+        let mut saver = Checkpointer::new(&path);
+        saver.start(CheckpointKind::Apriori, 2, &data).unwrap();
+        saver.save(&frequent);
+        saver.finish().unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..(bytes.len() - 4)]).unwrap();
+        let mut resume = Checkpointer::resume(&path);
+        let checkpoint = resume.start(CheckpointKind::Apriori, 2, &data).unwrap();
+        assert_eq!(checkpoint.pass(), 1);
+        // The cut off pass is replaced by the next save
+        resume.save(&frequent);
+        resume.finish().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+        resume.remove().unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,243 @@
+use datasets::{
+    budget::{Budget, Limiter, Status, Truncation},
+    constraint::Lengths,
+    taxonomy::{Extended, Taxonomy},
+    transaction_source::TransactionSource,
+};
+
+use crate::{
+    apriori::{AprioriCandidates, CountingStructure, apriori_run_one_count, apriori_run_two_count},
+    candidates::Candidates,
+    observer::{PassCounts, PassObserver, PassTimer},
+};
+/// Runs Cumulate, which finds the generalized itemsets of a taxonomy.
+/// Each transaction is extended with the ancestors of its items, so an itemset can have
+/// items from any level, such as a product and the category of another.
+///
+/// An itemset with an item and its ancestor has the same support as the one without the
+/// ancestor, so those pairs are pruned in pass 2 and no later candidate has one. Each pass
+/// only adds the ancestors that are in an itemset of the last, since the rest are in no
+/// candidate.
+///
+/// Each level of the taxonomy has its own minimum support, and an itemset is frequent when
+/// its support is at least the lowest of those of its items. The passes join the itemsets
+/// with at least the lowest minimum support of any level, since an itemset under its own
+/// can still be extended by an item with a lower one.
+#[derive(Debug)]
+pub struct Cumulate {
+    taxonomy: Taxonomy,
+    /// The minimum support count of each level, with the last for the levels below it
+    supports: Vec<u64>,
+    /// The lengths of the itemsets found
+    lengths: Lengths,
+}
+impl Cumulate {
+    /// Constructor, with the minimum support counts starting at level 0 (the items
+    /// with no parent)
+    pub fn new(taxonomy: Taxonomy, supports: Vec<u64>) -> Self {
+        assert!(!supports.is_empty(), "Each level needs a minimum support");
+        Self {
+            taxonomy,
+            supports,
+            lengths: Lengths::default(),
+        }
+    }
+    /// Only finds itemsets with these lengths, stopping after the pass of the longest
+    pub fn lengths(mut self, lengths: Lengths) -> Self {
+        self.lengths = lengths;
+        self
+    }
+    /// The minimum support of the item, from its level
+    fn min_support(&self, item: usize) -> u64 {
+        let level = self.taxonomy.level(item).min(self.supports.len() - 1);
+        self.supports[level]
+    }
+    /// Runs the algorithm, calling f with each frequent itemset
+    pub fn run_fn(self, data: &impl TransactionSource, mut f: impl FnMut(&[usize])) {
+        self.run_observed(data, &mut (), &Budget::default(), |v, _| f(v));
+    }
+    /// Runs the algorithm, calling f with each frequent itemset and its support,
+    /// telling the observer about each pass, and stopping early if it goes over the budget
+    pub fn run_observed(
+        self,
+        data: &impl TransactionSource,
+        observer: &mut impl PassObserver,
+        budget: &Budget,
+        f: impl FnMut(&[usize], u64),
+    ) -> Status {
+        let mut limiter = budget.start();
+        self.run_limited(data, observer, &mut limiter, f).into()
+    }
+    fn run_limited(
+        &self,
+        data: &impl TransactionSource,
+        observer: &mut impl PassObserver,
+        limiter: &mut Limiter,
+        mut f: impl FnMut(&[usize], u64),
+    ) -> Result<(), Truncation> {
+        let lengths = self.lengths;
+        let least = *self.supports.iter().min().unwrap();
+        // The passes have the itemsets that are kept to be joined but are not frequent
+        let mut emit_all = |limiter: &mut Limiter, set: &Candidates| {
+            let frequent =
+                |(v, n): &(&Vec<usize>, &u64)| v.iter().any(|&i| **n >= self.min_support(i));
+            limiter.emit_all(set.iter().filter(frequent), &mut f)
+        };
+        let mut data = self.taxonomy.extend(data);
+        let timer = PassTimer::start(1, observer);
+        let num_items = data.num_items();
+        limiter.candidates(1, num_items)?;
+        let mut frequent = Candidates::default();
+        for (i, n) in apriori_run_one_count(&data)?.into_iter().enumerate() {
+            if n >= least {
+                frequent.insert(vec![i], n);
+            }
+        }
+        timer.end(observer, PassCounts::new(num_items), frequent.len());
+        if lengths.contains(1) {
+            emit_all(limiter, &frequent)?;
+        }
+        if !lengths.extends(1) {
+            return Ok(());
+        }
+        let timer = PassTimer::start(2, observer);
+        let (pairs, pruned) = self.level2_candidates(&frequent);
+        let counts = PassCounts {
+            candidates: pairs.len(),
+            pruned,
+        };
+        limiter.candidates(2, counts.candidates)?;
+        keep_items(&mut data, &frequent);
+        let mut next = Candidates::default();
+        // Without pairs, there may not be enough items for the array of the pairs
+        if !pairs.is_empty() {
+            let second = apriori_run_two_count(&data)?;
+            for v in pairs {
+                let n = second.get(v[0], v[1]);
+                if n >= least {
+                    next.insert(v, n);
+                }
+            }
+        }
+        timer.end(observer, counts, next.len());
+        frequent = next;
+        if lengths.contains(2) {
+            emit_all(limiter, &frequent)?;
+        }
+        for i in 3..=lengths.max {
+            if frequent.is_empty() {
+                break;
+            }
+            let timer = PassTimer::start(i, observer);
+            keep_items(&mut data, &frequent);
+            // The ancestors make the transactions long, so there are too many of their
+            // subsets to look up each one in a hash tree
+            let counting = CountingStructure::FlatTrie;
+            let (next, counts) = AprioriCandidates::new(&frequent)
+                .run_counted(&data, i, least, counting, limiter)?;
+            timer.end(observer, counts, next.len());
+            frequent = next;
+            if lengths.contains(i) {
+                emit_all(limiter, &frequent)?;
+            }
+        }
+        Ok(())
+    }
+    /// The pairs of frequent items (sorted) where neither is an ancestor of the other,
+    /// and the number of pairs pruned
+    fn level2_candidates(&self, frequent: &Candidates) -> (Vec<Vec<usize>>, usize) {
+        let mut items: Vec<_> = frequent.keys().map(|v| v[0]).collect();
+        items.sort_unstable();
+        let mut pairs = Vec::new();
+        let mut pruned = 0;
+        for (p, &a) in items.iter().enumerate() {
+            for &b in &items[p + 1..] {
+                if self.taxonomy.has_ancestor(&[a, b]) {
+                    pruned += 1;
+                } else {
+                    pairs.push(vec![a, b]);
+                }
+            }
+        }
+        (pairs, pruned)
+    }
+}
+/// Only adds the ancestors that are in one of the itemsets to the transactions,
+/// as the candidates joined from them have no others
+fn keep_items<T>(data: &mut Extended<'_, T>, frequent: &Candidates) {
+    let mut kept = Vec::new();
+    for &i in frequent.keys().flatten() {
+        if kept.len() <= i {
+            kept.resize(i + 1, false);
+        }
+        kept[i] = true;
+    }
+    data.keep(kept);
+}
+
+#[cfg(test)]
+mod tests {
+    use datasets::{taxonomy::Taxonomy, transaction_set::TransactionSet};
+
+    use super::Cumulate;
+
+    #[test]
+    fn test_cumulate() {
+        // Items 0 and 1 are in 4, 2 and 3 are in 5, and 4 and 5 are in 6
+        let taxonomy = Taxonomy::new(&[(0, 4), (1, 4), (2, 5), (3, 5), (4, 6), (5, 6)]).unwrap();
+        let example = TransactionSet::new(
+            vec![
+                vec![0, 2],
+                vec![1, 3],
+                vec![0, 3],
+                vec![1],
+                vec![2],
+                vec![0, 1, 2],
+            ],
+            4,
+        );
+        let run = |c: Cumulate| {
+            let mut found = Vec::new();
+            c.run_observed(&example, &mut (), &Default::default(), |v, n| {
+                found.push((v.to_vec(), n))
+            });
+            found.sort_unstable();
+            found
+        };
+        // No itemset has an item and its ancestor, such as {0, 4} or {4, 6}
+        let found = run(Cumulate::new(taxonomy.clone(), vec![3]));
+        assert_eq!(
+            found,
+            vec![
+                (vec![0], 3),
+                (vec![0, 5], 3),
+                (vec![1], 3),
+                (vec![2], 3),
+                (vec![4], 5),
+                (vec![4, 5], 4),
+                (vec![5], 5),
+                (vec![6], 6),
+            ]
+        );
+        // The products (level 2) only need a support of 2, and an itemset needs the lowest
+        // support of its items, so {4, 5} (level 1) is not found
+        let found = run(Cumulate::new(taxonomy, vec![6, 5, 2]));
+        assert_eq!(
+            found,
+            vec![
+                (vec![0], 3),
+                (vec![0, 2], 2),
+                (vec![0, 5], 3),
+                (vec![1], 3),
+                (vec![1, 5], 2),
+                (vec![2], 3),
+                (vec![2, 4], 2),
+                (vec![3], 2),
+                (vec![3, 4], 2),
+                (vec![4], 5),
+                (vec![5], 5),
+                (vec![6], 6),
+            ]
+        );
+    }
+}
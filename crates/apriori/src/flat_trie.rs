@@ -0,0 +1,231 @@
+use datasets::utils::Item;
+
+use crate::apriori::AprioriCounting;
+/// A Trie for counting candidate itemsets of the same size,
+/// with the nodes of each level stored contiguously in sorted order.
+/// The children of a node are a range of the next level,
+/// so counting walks arrays instead of chasing pointers.
+#[derive(Debug, Default)]
+pub struct FlatTrie {
+    /// The items of the nodes of each level
+    items: Vec<Vec<usize>>,
+    /// For each level except the last, where the children of each node start in the next level
+    /// (with an extra element for the end of the last node)
+    children: Vec<Vec<u32>>,
+    /// The counts of the leaves (the nodes of the last level)
+    counts: Vec<u64>,
+}
+
+impl FlatTrie {
+    /// Constructor from itemsets that all have the same size
+    pub fn new(mut itemsets: Vec<Vec<usize>>) -> Self {
+        itemsets.sort_unstable();
+        itemsets.dedup();
+        let Some(k) = itemsets.first().map(|v| v.len()) else {
+            return Self::default();
+        };
+        assert!(k > 0);
+        let mut items = vec![Vec::new(); k];
+        let mut children = vec![Vec::new(); k - 1];
+        for (j, v) in itemsets.iter().enumerate() {
+            assert_eq!(v.len(), k);
+            // The first level where v is different from the previous itemset
+            let start = if j == 0 {
+                0
+            } else {
+                v.iter()
+                    .zip(&itemsets[j - 1])
+                    .position(|(a, b)| a != b)
+                    .unwrap()
+            };
+            // Creates new nodes from that level on
+            for d in start..k {
+                if d + 1 < k {
+                    children[d].push(items[d + 1].len() as u32);
+                }
+                items[d].push(v[d]);
+            }
+        }
+        for d in 0..(k - 1) {
+            children[d].push(items[d + 1].len() as u32);
+        }
+        let counts = vec![0; itemsets.len()];
+        Self {
+            items,
+            children,
+            counts,
+        }
+    }
+    /// The size of the itemsets
+    pub fn depth(&self) -> usize {
+        self.items.len()
+    }
+    /// The number of itemsets
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+    /// Checks if the trie is empty
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+    /// The range of the children of node n at level d
+    fn child_range(&self, d: usize, n: usize) -> std::ops::Range<usize> {
+        if d + 1 == self.depth() {
+            return 0..0;
+        }
+        (self.children[d][n] as usize)..(self.children[d][n + 1] as usize)
+    }
+    /// Gets the index of the leaf for v
+    fn find(&self, v: &[usize]) -> Option<usize> {
+        if v.len() != self.depth() || self.is_empty() {
+            return None;
+        }
+        let mut range = 0..self.items[0].len();
+        let mut n = 0;
+        for (d, e) in v.iter().enumerate() {
+            n = range.start + self.items[d][range.clone()].binary_search(e).ok()?;
+            range = self.child_range(d, n);
+        }
+        Some(n)
+    }
+    /// Checks if the trie contains v
+    pub fn contains(&self, v: &[usize]) -> bool {
+        self.find(v).is_some()
+    }
+    /// Gets the count of v
+    pub fn get_count(&self, v: &[usize]) -> Option<u64> {
+        self.find(v).map(|n| self.counts[n])
+    }
+    /// Increments v
+    pub fn increment(&mut self, v: &[usize]) -> bool {
+        match self.find(v) {
+            Some(n) => {
+                self.counts[n] += 1;
+                true
+            }
+            None => false,
+        }
+    }
+    /// Counts all the itemsets contained in the sorted transaction,
+    /// calling f with each one.
+    pub fn count_transaction<I: Item>(&mut self, t: &[I], mut f: impl FnMut(&[usize])) {
+        if self.is_empty() || t.len() < self.depth() {
+            return;
+        }
+        let mut stack = vec![0; self.depth()];
+        self.count_helper(t, 0, 0..self.items[0].len(), &mut stack, &mut f);
+    }
+    /// Merges the sorted transaction with the sorted nodes in range at level d
+    fn count_helper<I: Item>(
+        &mut self,
+        t: &[I],
+        d: usize,
+        range: std::ops::Range<usize>,
+        stack: &mut [usize],
+        f: &mut impl FnMut(&[usize]),
+    ) {
+        let last = d + 1 == self.depth();
+        // The items after the last depth - d - 1 cannot start an itemset
+        let end = t.len() + d + 1 - self.depth();
+        let (mut p, mut n) = (0, range.start);
+        while p < end && n < range.end {
+            let item = self.items[d][n];
+            match t[p].index().cmp(&item) {
+                std::cmp::Ordering::Less => p += 1,
+                std::cmp::Ordering::Greater => n += 1,
+                std::cmp::Ordering::Equal => {
+                    stack[d] = item;
+                    if last {
+                        self.counts[n] += 1;
+                        f(stack);
+                    } else {
+                        let r = self.child_range(d, n);
+                        self.count_helper(&t[(p + 1)..], d + 1, r, stack, f);
+                    }
+                    p += 1;
+                    n += 1;
+                }
+            }
+        }
+    }
+    /// A mutable for each loop through the itemsets and their counts
+    pub fn for_each_mut(&mut self, mut f: impl FnMut(&[usize], &mut u64)) {
+        if self.is_empty() {
+            return;
+        }
+        let mut stack = vec![0; self.depth()];
+        self.for_each_helper(0, 0..self.items[0].len(), &mut stack, &mut f);
+    }
+    fn for_each_helper(
+        &mut self,
+        d: usize,
+        range: std::ops::Range<usize>,
+        stack: &mut [usize],
+        f: &mut impl FnMut(&[usize], &mut u64),
+    ) {
+        for n in range {
+            stack[d] = self.items[d][n];
+            if d + 1 == self.depth() {
+                f(stack, &mut self.counts[n]);
+            } else {
+                let r = self.child_range(d, n);
+                self.for_each_helper(d + 1, r, stack, f);
+            }
+        }
+    }
+}
+
+impl AprioriCounting for FlatTrie {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn increment(&mut self, v: &[usize]) -> bool {
+        self.increment(v)
+    }
+
+    fn for_each_mut(&mut self, f: impl FnMut(&[usize], &mut u64)) {
+        self.for_each_mut(f);
+    }
+
+    fn count_transaction<I: Item>(&mut self, t: &[I], _: usize, f: impl FnMut(&[usize])) {
+        self.count_transaction(t, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::FlatTrie;
+
+    #[test]
+    fn test_flat_trie() {
+        let mut trie = FlatTrie::new(vec![
+            vec![1, 3, 4],
+            vec![1, 2, 3],
+            vec![2, 3, 4],
+            vec![1, 2, 4],
+            vec![1, 2, 3],
+        ]);
+        assert_eq!(trie.len(), 4);
+        assert!(trie.contains(&[1, 2, 4]));
+        assert!(!trie.contains(&[1, 2]));
+        assert!(!trie.contains(&[1, 2, 5]));
+        assert!(trie.increment(&[2, 3, 4]));
+        assert_eq!(trie.get_count(&[2, 3, 4]), Some(1));
+        let mut found = Vec::new();
+        trie.count_transaction(&[0usize, 1, 2, 4, 5], |v| found.push(v.to_vec()));
+        assert_eq!(found, vec![vec![1, 2, 4]]);
+        trie.count_transaction(&[1usize, 2, 3, 4], |_| {});
+        let mut counts = HashMap::new();
+        trie.for_each_mut(|v, n| {
+            counts.insert(v.to_vec(), *n);
+        });
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts[&vec![1, 2, 3]], 1);
+        assert_eq!(counts[&vec![1, 2, 4]], 2);
+        assert_eq!(counts[&vec![1, 3, 4]], 1);
+        assert_eq!(counts[&vec![2, 3, 4]], 2);
+    }
+}
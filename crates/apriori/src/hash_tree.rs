@@ -167,8 +167,7 @@ impl<const N: usize> AprioriHashTreeGeneric<N> {
         assert!(!v.is_empty());
         let leaf = self.get_leaf_mut(v);
         if let Some(leaf) = leaf {
-            leaf.increment(v);
-            true
+            leaf.increment(v)
         } else {
             false
         }
@@ -196,7 +195,7 @@ impl<const N: usize> AprioriHashTreeGeneric<N> {
         self.root.for_each_mut(&mut f);
     }
     /// Gets an iterator for the Hash Tree
-    pub fn iter(&self) -> HashTreeIterator<N> {
+    pub fn iter(&self) -> HashTreeIterator<'_, N> {
         HashTreeIterator::new(self)
     }
     /// Gets the number of elements in the tree
@@ -268,10 +267,6 @@ impl HashTreeLeafNode {
             f(v, n);
         }
     }
-    /// Gets the mutable element at v
-    fn find_mut(&mut self, v: &[usize]) -> Option<&mut (Vec<usize>, u64)> {
-        self.0.iter_mut().find(|v2| v2.0.eq(v))
-    }
     /// Checks if self contains v
     fn contains(&self, v: &[usize]) -> bool {
         self.find(v).is_some()
@@ -418,6 +413,11 @@ mod tests {
         assert_eq!(tree.get_count(&[1, 2]), Some(1));
         assert!(!tree.contains(&[1, 3]));
         assert_eq!(tree.get_count(&[1, 3]), None);
+        // An itemset in the same leaf as another is not counted
+        let mut same_leaf = AprioriHashTreeGeneric::<1>::default();
+        same_leaf.add(&[1, 2]);
+        assert!(!same_leaf.increment(&[1, 3]));
+        assert!(same_leaf.increment(&[1, 2]));
         assert_eq!(tree.remove(&[1, 2]), Some((vec![1, 2], 1)));
         assert!(!tree.contains(&[1, 2]));
     }
@@ -4,8 +4,14 @@ pub mod apriori;
 pub mod array2d;
 pub mod candidates_func;
 pub mod transaction_id;
+pub mod transaction_index;
 pub mod apriori_tid;
 pub mod apriori_hybrid;
 pub mod candidates;
 pub mod trie;
-pub mod apriori_trie;
\ No newline at end of file
+pub mod flat_trie;
+pub mod apriori_trie;
+pub mod observer;
+pub mod checkpoint;
+pub mod ms_apriori;
+pub mod cumulate;
\ No newline at end of file
@@ -0,0 +1,324 @@
+use std::{fs, io, ops::DerefMut, path::Path};
+
+use datasets::{
+    budget::{Budget, Limiter, Status, Truncation},
+    constraint::Lengths,
+    transaction_source::TransactionSource,
+};
+
+use crate::{
+    apriori::{apriori_run_one_count, apriori_run_two_count, count_frequent},
+    candidates::Candidates,
+    candidates_func::join_by,
+    hash_tree::AprioriHashTree,
+    observer::{PassCounts, PassObserver, PassTimer},
+};
+/// How the minimum support of each item (its MIS) is set
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemSupports {
+    /// The minimum support count of each item.
+    /// The items past the end have no minimum support of their own,
+    /// so they are only found with items that have one.
+    Given(Vec<u64>),
+    /// beta times the support count of each item, but at least the floor
+    Scaled { beta: f64, floor: u64 },
+}
+impl ItemSupports {
+    /// Reads the minimum supports from a file with line i being the support count of item i
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let supports = fs::read_to_string(path)?
+            .lines()
+            .map(|s| s.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self::Given(supports))
+    }
+    /// The minimum support count of each item, from the support counts of the items.
+    /// Each is at least 1, since itemsets that are in no transaction are never counted.
+    pub fn resolve(&self, counts: &[u64]) -> Vec<u64> {
+        let mis = counts.iter().enumerate().map(|(i, &n)| match self {
+            ItemSupports::Given(v) => v.get(i).copied().unwrap_or(u64::MAX),
+            ItemSupports::Scaled { beta, floor } => ((beta * n as f64).ceil() as u64).max(*floor),
+        });
+        mis.map(|n| n.max(1)).collect()
+    }
+}
+/// Runs MSApriori, where each item has its own minimum support.
+/// An itemset is frequent when its support is at least the lowest minimum support of its items,
+/// and (with a support difference) the supports of its items differ by at most that much.
+///
+/// This is not downward closed, as a subset without the item with the lowest minimum support
+/// can be infrequent. The items are ordered by their minimum supports instead, and itemsets
+/// are joined in that order. Every subset of a frequent itemset with its first item
+/// is frequent (the sorted closure), so only those subsets are used to prune a candidate.
+#[derive(Debug)]
+pub struct MSApriori {
+    supports: ItemSupports,
+    /// The most the supports of the items of an itemset can differ by
+    support_difference: u64,
+    /// The lengths of the itemsets found
+    lengths: Lengths,
+}
+impl MSApriori {
+    /// Constructor
+    pub fn new(supports: ItemSupports) -> Self {
+        Self {
+            supports,
+            support_difference: u64::MAX,
+            lengths: Lengths::default(),
+        }
+    }
+    /// Only finds itemsets whose items have supports that differ by at most this much
+    pub fn support_difference(mut self, support_difference: u64) -> Self {
+        self.support_difference = support_difference;
+        self
+    }
+    /// Only finds itemsets with these lengths, stopping after the pass of the longest
+    pub fn lengths(mut self, lengths: Lengths) -> Self {
+        self.lengths = lengths;
+        self
+    }
+    /// Runs the algorithm, calling f with each frequent itemset
+    pub fn run_fn(self, data: &impl TransactionSource, mut f: impl FnMut(&[usize])) {
+        self.run_observed(data, &mut (), &Budget::default(), |v, _| f(v));
+    }
+    /// Runs the algorithm, calling f with each frequent itemset and its support,
+    /// telling the observer about each pass, and stopping early if it goes over the budget
+    pub fn run_observed(
+        self,
+        data: &impl TransactionSource,
+        observer: &mut impl PassObserver,
+        budget: &Budget,
+        f: impl FnMut(&[usize], u64),
+    ) -> Status {
+        let mut limiter = budget.start();
+        self.run_limited(data, observer, &mut limiter, f).into()
+    }
+    fn run_limited(
+        self,
+        data: &impl TransactionSource,
+        observer: &mut impl PassObserver,
+        limiter: &mut Limiter,
+        mut f: impl FnMut(&[usize], u64),
+    ) -> Result<(), Truncation> {
+        let lengths = self.lengths;
+        let timer = PassTimer::start(1, observer);
+        limiter.candidates(1, data.num_items())?;
+        let counts = apriori_run_one_count(data)?;
+        let order = Order {
+            mis: self.supports.resolve(&counts),
+            counts,
+            support_difference: self.support_difference,
+        };
+        let (seeds, frequent) = order.init_pass();
+        timer.end(observer, PassCounts::new(data.num_items()), frequent.len());
+        if lengths.contains(1) {
+            limiter.emit_all(frequent.iter(), &mut f)?;
+        }
+        if !lengths.extends(1) {
+            return Ok(());
+        }
+        let timer = PassTimer::start(2, observer);
+        let pairs = order.level2_candidates(&seeds);
+        limiter.candidates(2, pairs.len())?;
+        let mut frequent = Candidates::default();
+        // Without pairs, there may not be enough items for the array of the pairs
+        if !pairs.is_empty() {
+            let second = apriori_run_two_count(data)?;
+            for v in &pairs {
+                let n = second.get(v[0], v[1]);
+                if n >= order.mis_of(v) {
+                    frequent.insert(v.clone(), n);
+                }
+            }
+        }
+        timer.end(observer, PassCounts::new(pairs.len()), frequent.len());
+        if lengths.contains(2) {
+            limiter.emit_all(frequent.iter(), &mut f)?;
+        }
+        for i in 3..=lengths.max {
+            if frequent.is_empty() {
+                break;
+            }
+            let timer = PassTimer::start(i, observer);
+            let mut tree = AprioriHashTree::new();
+            let pruned = order.candidates(&frequent, |v| {
+                tree.add(&v);
+            });
+            let counts = PassCounts {
+                candidates: tree.len(),
+                pruned,
+            };
+            limiter.candidates(i, counts.candidates)?;
+            frequent = count_frequent(data, i, 1, tree.deref_mut())?;
+            frequent.retain(|v, n| *n >= order.mis_of(v));
+            timer.end(observer, counts, frequent.len());
+            if lengths.contains(i) {
+                limiter.emit_all(frequent.iter(), &mut f)?;
+            }
+        }
+        Ok(())
+    }
+}
+/// The items in the order of their minimum supports, with their supports
+struct Order {
+    /// The minimum support count of each item
+    mis: Vec<u64>,
+    /// The support count of each item
+    counts: Vec<u64>,
+    support_difference: u64,
+}
+impl Order {
+    /// Whether item a is before item b, by minimum support and then by id
+    fn before(&self, a: usize, b: usize) -> bool {
+        (self.mis[a], a) < (self.mis[b], b)
+    }
+    /// The minimum support of the itemset, which is the lowest of its items
+    fn mis_of(&self, v: &[usize]) -> u64 {
+        v.iter().map(|&i| self.mis[i]).min().unwrap()
+    }
+    /// Whether the supports of the items are close enough to be in an itemset together
+    fn close(&self, a: usize, b: usize) -> bool {
+        self.counts[a].abs_diff(self.counts[b]) <= self.support_difference
+    }
+    /// Finds the items that can be in a frequent itemset (in order), which are those
+    /// at least as frequent as the minimum support of the first item that is frequent,
+    /// and the frequent items
+    fn init_pass(&self) -> (Vec<usize>, Candidates) {
+        let mut items: Vec<_> = (0..self.mis.len()).collect();
+        items.sort_unstable_by_key(|&i| (self.mis[i], i));
+        let first = items.iter().position(|&i| self.counts[i] >= self.mis[i]);
+        let seeds: Vec<_> = match first {
+            Some(p) => {
+                let least = self.mis[items[p]];
+                items[p..]
+                    .iter()
+                    .copied()
+                    .filter(|&i| self.counts[i] >= least)
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        let mut frequent = Candidates::default();
+        for &i in &seeds {
+            if self.counts[i] >= self.mis[i] {
+                frequent.insert(vec![i], self.counts[i]);
+            }
+        }
+        (seeds, frequent)
+    }
+    /// The pairs of items that can be frequent (sorted by id).
+    /// The first item of a pair has the lowest minimum support, so it has to be frequent,
+    /// and the second is only as frequent as that minimum support.
+    fn level2_candidates(&self, seeds: &[usize]) -> Vec<Vec<usize>> {
+        let mut pairs = Vec::new();
+        for (p, &l) in seeds.iter().enumerate() {
+            if self.counts[l] < self.mis[l] {
+                continue;
+            }
+            for &h in &seeds[p + 1..] {
+                if self.counts[h] >= self.mis[l] && self.close(l, h) {
+                    pairs.push(if l < h { vec![l, h] } else { vec![h, l] });
+                }
+            }
+        }
+        pairs
+    }
+    /// Joins the frequent itemsets in the order of their minimum supports, calling f with
+    /// each candidate (sorted by id) that is not pruned. Returns the number pruned.
+    fn candidates(&self, frequent: &Candidates, mut f: impl FnMut(Vec<usize>)) -> usize {
+        let ordered: Vec<_> = frequent
+            .keys()
+            .map(|v| {
+                let mut v = v.clone();
+                v.sort_unstable_by_key(|&i| (self.mis[i], i));
+                v
+            })
+            .collect();
+        let mut pruned = 0;
+        join_by(ordered.iter(), |a, b| self.before(a, b), |c| {
+            let k = c.len();
+            if !self.close(c[k - 2], c[k - 1]) {
+                return;
+            }
+            // The subsets without the first item only have to be frequent
+            // if the second item has the same minimum support
+            let same = self.mis[c[0]] == self.mis[c[1]];
+            let infrequent = (0..k).filter(|&j| j > 0 || same).any(|j| {
+                let mut s = c.clone();
+                s.remove(j);
+                s.sort_unstable();
+                !frequent.contains_key(&s)
+            });
+            if infrequent {
+                pruned += 1;
+                return;
+            }
+            let mut c = c;
+            c.sort_unstable();
+            f(c);
+        });
+        pruned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datasets::transaction_set::TransactionSet;
+
+    use super::{ItemSupports, MSApriori};
+
+    #[test]
+    fn test_ms_apriori() {
+        let example = TransactionSet::new(
+            vec![
+                vec![0, 1, 4],
+                vec![1, 3],
+                vec![1, 2],
+                vec![0, 1, 3],
+                vec![0, 2],
+                vec![1, 2],
+                vec![0, 2],
+                vec![0, 1, 2, 4],
+                vec![0, 1, 2],
+            ],
+            5,
+        );
+        let run = |ms: MSApriori| {
+            let mut found = Vec::new();
+            ms.run_fn(&example, |v| found.push(v.to_vec()));
+            found.sort_unstable();
+            found
+        };
+        // Items 3 and 4 are in 2 transactions, but only 4 has a minimum support of 2,
+        // so {0, 1, 4} is found while {0, 1, 2} (also in 2) is not
+        let supports = ItemSupports::Given(vec![4, 4, 4, 4, 2]);
+        let found = run(MSApriori::new(supports.clone()));
+        assert_eq!(
+            found,
+            vec![
+                vec![0],
+                vec![0, 1],
+                vec![0, 1, 4],
+                vec![0, 2],
+                vec![0, 4],
+                vec![1],
+                vec![1, 2],
+                vec![1, 4],
+                vec![2],
+                vec![4]
+            ]
+        );
+        // Items 0, 1, and 2 have supports of 6, 7, and 6, and 4 has a support of 2
+        let found = run(MSApriori::new(supports).support_difference(1));
+        let expected = [vec![0], vec![0, 1], vec![0, 2], vec![1], vec![1, 2], vec![2], vec![4]];
+        assert_eq!(found, expected);
+        // With beta 0.5, each item needs half of its support
+        let scaled = ItemSupports::Scaled {
+            beta: 0.5,
+            floor: 3,
+        };
+        assert_eq!(scaled.resolve(&[6, 7, 6, 2, 2]), vec![3, 4, 3, 3, 3]);
+        assert_eq!(ItemSupports::Given(vec![0]).resolve(&[1, 1]), vec![1, u64::MAX]);
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::{Duration, Instant};
+
+/// What happened in one pass of a level-wise algorithm
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PassStats {
+    /// The size of the itemsets counted in the pass
+    pub pass: usize,
+    /// The number of itemsets counted
+    pub candidates: usize,
+    /// The number of joined itemsets that were not counted, as they have an infrequent subset
+    pub pruned: usize,
+    /// The number of candidates that are frequent
+    pub frequent: usize,
+    /// How long the pass took, from generating the candidates to finding the frequent ones
+    pub duration: Duration,
+}
+impl PassStats {
+    /// The number of itemsets the join step generated, before they were pruned
+    pub fn generated(&self) -> usize {
+        self.candidates + self.pruned
+    }
+}
+/// The number of candidates of a pass
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PassCounts {
+    /// The number of itemsets counted
+    pub candidates: usize,
+    /// The number of joined itemsets that were pruned
+    pub pruned: usize,
+}
+impl PassCounts {
+    /// The counts of a pass that prunes nothing
+    pub fn new(candidates: usize) -> Self {
+        Self {
+            candidates,
+            pruned: 0,
+        }
+    }
+}
+/// Watches the passes of a level-wise algorithm
+pub trait PassObserver {
+    /// Called before each pass
+    fn pass_start(&mut self, _pass: usize) {}
+    /// Called after each pass
+    fn pass_end(&mut self, stats: &PassStats);
+}
+/// Ignores every pass
+impl PassObserver for () {
+    fn pass_end(&mut self, _: &PassStats) {}
+}
+/// Collects the stats of every pass
+impl PassObserver for Vec<PassStats> {
+    fn pass_end(&mut self, stats: &PassStats) {
+        self.push(stats.clone());
+    }
+}
+/// Times a pass, and tells the observer when it starts and ends
+#[derive(Debug)]
+pub struct PassTimer {
+    pass: usize,
+    start: Instant,
+}
+impl PassTimer {
+    pub fn start(pass: usize, observer: &mut impl PassObserver) -> Self {
+        observer.pass_start(pass);
+        Self {
+            pass,
+            start: Instant::now(),
+        }
+    }
+    pub fn end(self, observer: &mut impl PassObserver, counts: PassCounts, frequent: usize) {
+        observer.pass_end(&PassStats {
+            pass: self.pass,
+            candidates: counts.candidates,
+            pruned: counts.pruned,
+            frequent,
+            duration: self.start.elapsed(),
+        });
+    }
+}
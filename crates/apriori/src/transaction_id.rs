@@ -1,8 +1,8 @@
 use std::{collections::{HashMap, HashSet}, ops::DerefMut};
 
-use datasets::{transaction_set::TransactionSet, utils::nested_loops};
+use datasets::transaction_set::TransactionSet;
 
-use crate::{apriori::{apriori_count, AprioriCandidates}, candidates_func::join, hash_tree::AprioriHashTree};
+use crate::{apriori::{apriori_count, AprioriCandidates}, candidates::CandidateType, candidates_func::join, hash_tree::AprioriHashTree};
 /// The transaction IDs used for AprioriTID
 #[derive(Debug, Default)]
 pub struct TransactionIDs {
@@ -23,9 +23,9 @@ impl TransactionIDs {
         o
     }
     /// Creates the set of TIDs for the first pass only
-    pub fn start(data: &Vec<Vec<usize>>) -> TransactionIDs {
+    pub fn start(data: &TransactionSet) -> TransactionIDs {
         let mut v = Vec::new();
-        for d in data {
+        for d in data.iter() {
             let value = TransactionID::start(d);
             if value.ids().is_empty() {
                 continue;
@@ -36,25 +36,42 @@ impl TransactionIDs {
     }
     /// Generates the TIDs of size k
     pub fn from_transaction(
-        data: &Vec<Vec<usize>>,
+        data: &TransactionSet,
         k: usize,
-        set: &HashSet<Vec<usize>>,
+        set: &CandidateType,
     ) -> (AprioriHashTree, Self) {
         let mut tree = AprioriCandidates::new(set).create_tree();
+        let tids = Self::from_tree(data, k, &mut tree);
+        (tree, tids)
+    }
+    /// Generates the TIDs of size k, counting the candidates of size k + 1 in the tree
+    pub fn from_tree(data: &TransactionSet, k: usize, tree: &mut AprioriHashTree) -> Self {
         let mut v = Vec::new();
-        for d in data {
-            let value = TransactionID::from_transaction(d, k, &mut tree);
+        for d in data.iter() {
+            let value = TransactionID::from_transaction(d, k, tree);
             if value.ids().is_empty() {
                 continue;
             }
             v.push(value);
         }
-        (tree, Self::new(v))
+        Self::new(v)
+    }
+    /// Iterates over the TIDs
+    pub fn iter(&self) -> impl Iterator<Item = &TransactionID> {
+        self.v.iter()
+    }
+    /// The number of TIDs
+    pub fn len(&self) -> usize {
+        self.v.len()
+    }
+    /// Checks if there are no TIDs
+    pub fn is_empty(&self) -> bool {
+        self.v.is_empty()
     }
 }
 impl From<&TransactionSet> for TransactionIDs {
     fn from(transaction_set: &TransactionSet) -> Self {
-        Self::start(&transaction_set.transactions)
+        Self::start(transaction_set)
     }
 }
 /// A Transaction ID for AprioriTID
@@ -79,11 +96,11 @@ impl TransactionID {
         t
     }
     /// Generates the TID for pass 1
-    pub fn start(data: &[usize]) -> Self {
-        Self::new(data.iter().cloned().map(|n| vec![n]).collect())
+    pub fn start(data: &[u32]) -> Self {
+        Self::new(data.iter().map(|&n| vec![n as usize]).collect())
     }
     /// Generates the next TID from the dataset for size k
-    pub fn from_transaction(data: &[usize], k: usize, set: &mut AprioriHashTree) -> Self {
+    pub fn from_transaction(data: &[u32], k: usize, set: &mut AprioriHashTree) -> Self {
         // Generates the TID based on nested looping through the transaction set.
         let mut output = HashSet::new();
         apriori_count(data, k + 1, set.deref_mut(), |v| {
@@ -0,0 +1,380 @@
+use std::ops::{DerefMut, Range};
+
+use datasets::{
+    budget::{Limiter, Truncation},
+    transaction_set::TransactionSet,
+};
+
+use crate::{
+    apriori::apriori_count,
+    candidates::{CandidateType, Candidates},
+    hash_tree::AprioriHashTree,
+    observer::PassCounts,
+};
+/// The frequent itemsets of a pass stored in a sorted array,
+/// so that the TIDs can refer to them by index.
+#[derive(Debug, Default, Clone)]
+pub struct IndexedItemsets {
+    itemsets: Vec<Vec<usize>>,
+    /// The support of each itemset
+    supports: Vec<u64>,
+}
+
+impl IndexedItemsets {
+    /// Constructor from the itemsets and their supports (sorts the itemsets)
+    pub fn new(mut itemsets: Vec<(Vec<usize>, u64)>) -> Self {
+        itemsets.sort_unstable();
+        let (itemsets, supports) = itemsets.into_iter().unzip();
+        Self { itemsets, supports }
+    }
+    /// Gets the itemset at index i
+    pub fn get(&self, i: usize) -> &[usize] {
+        &self.itemsets[i]
+    }
+    /// Gets the index of v
+    pub fn index_of(&self, v: &[usize]) -> Option<usize> {
+        self.itemsets.binary_search_by(|a| a.as_slice().cmp(v)).ok()
+    }
+    /// Gets the support of the itemset at index i
+    pub fn get_support(&self, i: usize) -> u64 {
+        self.supports[i]
+    }
+    /// Iterates over all the itemsets in order, with their supports
+    pub fn iter(&self) -> impl Iterator<Item = (&Vec<usize>, &u64)> {
+        self.itemsets.iter().zip(&self.supports)
+    }
+    /// The number of itemsets
+    pub fn len(&self) -> usize {
+        self.itemsets.len()
+    }
+    /// Checks if there are no itemsets
+    pub fn is_empty(&self) -> bool {
+        self.itemsets.is_empty()
+    }
+    /// Converts the itemsets into Candidates
+    pub fn to_candidates(&self) -> Candidates {
+        self.iter()
+            .map(|(v, &n)| (v.clone(), n))
+            .collect::<CandidateType>()
+            .into()
+    }
+    /// A prune function for Apriori
+    fn can_be_pruned(&self, v: &[usize]) -> bool {
+        // The subsets without the last 2 elements are the ones that were joined
+        let mut arr = v[1..].to_vec();
+        for i in 0..(v.len() - 2) {
+            if i > 0 {
+                arr[i - 1] = v[i - 1];
+            }
+            if self.index_of(&arr).is_none() {
+                return true;
+            }
+        }
+        false
+    }
+    /// Joins together the itemsets that share a prefix into the candidates of the next pass.
+    /// Because the itemsets are sorted, the candidates are created sorted as well.
+    pub fn join(&self) -> IndexedCandidates {
+        let mut c = IndexedCandidates::default();
+        let mut start = 0;
+        while start < self.len() {
+            // The itemsets with the same prefix are next to each other
+            let prefix = &self.itemsets[start][..self.itemsets[start].len() - 1];
+            let mut end = start + 1;
+            while end < self.len() && self.itemsets[end].starts_with(prefix) {
+                end += 1;
+            }
+            for i in start..end {
+                let begin = c.len();
+                for j in (i + 1)..end {
+                    let mut join = self.itemsets[i].clone();
+                    join.push(*self.itemsets[j].last().unwrap());
+                    if self.can_be_pruned(&join) {
+                        c.pruned += 1;
+                        continue;
+                    }
+                    c.push(join, j as u32);
+                }
+                c.extensions.push(begin..c.len());
+            }
+            start = end;
+        }
+        c
+    }
+    /// Generates the frequent itemsets and next TIDs
+    pub fn next(
+        &self,
+        data: &TransactionIndices,
+        min_sup: u64,
+    ) -> (IndexedItemsets, TransactionIndices) {
+        let mut c = self.join();
+        let mut next = data.count(&mut c);
+        let (frequent, map) = c.frequent(min_sup);
+        next.remap(&map);
+        (frequent, next)
+    }
+    /// Same as next for pass i, but also returns the number of candidates counted and pruned.
+    /// Stops before counting if the limiter does not allow that many candidates.
+    pub fn next_counted(
+        &self,
+        data: &TransactionIndices,
+        min_sup: u64,
+        i: usize,
+        limiter: &Limiter,
+    ) -> Result<(IndexedItemsets, TransactionIndices, PassCounts), Truncation> {
+        let mut c = self.join();
+        let counts = PassCounts {
+            candidates: c.len(),
+            pruned: c.pruned,
+        };
+        limiter.candidates(i, counts.candidates)?;
+        let mut next = data.count(&mut c);
+        let (frequent, map) = c.frequent(min_sup);
+        next.remap(&map);
+        Ok((frequent, next, counts))
+    }
+}
+impl From<&CandidateType> for IndexedItemsets {
+    fn from(value: &CandidateType) -> Self {
+        Self::new(value.iter().map(|(v, &n)| (v.clone(), n)).collect())
+    }
+}
+/// The candidate itemsets of a pass.
+/// Every candidate is the join of two itemsets of the previous pass (its generators).
+#[derive(Debug, Default)]
+pub struct IndexedCandidates {
+    itemsets: Vec<Vec<usize>>,
+    counts: Vec<u64>,
+    /// The index of the second generator for each candidate
+    second: Vec<u32>,
+    /// The candidates that each itemset of the previous pass is the first generator of
+    extensions: Vec<Range<usize>>,
+    /// The number of joined itemsets that were pruned
+    pruned: usize,
+}
+
+impl IndexedCandidates {
+    /// Adds a candidate
+    fn push(&mut self, v: Vec<usize>, second: u32) {
+        self.itemsets.push(v);
+        self.counts.push(0);
+        self.second.push(second);
+    }
+    /// The number of candidates
+    pub fn len(&self) -> usize {
+        self.itemsets.len()
+    }
+    /// Checks if there are no candidates
+    pub fn is_empty(&self) -> bool {
+        self.itemsets.is_empty()
+    }
+    /// Gets the count of the candidate at index i
+    pub fn get_count(&self, i: usize) -> u64 {
+        self.counts[i]
+    }
+    /// Keeps the frequent candidates, returning them and a mapping from
+    /// candidate index to their new index (u32::MAX for removed ones).
+    pub fn frequent(self, min_sup: u64) -> (IndexedItemsets, Vec<u32>) {
+        let mut map = vec![u32::MAX; self.len()];
+        let mut itemsets = Vec::new();
+        let mut supports = Vec::new();
+        for (i, (v, n)) in self.itemsets.into_iter().zip(self.counts).enumerate() {
+            if n < min_sup {
+                continue;
+            }
+            map[i] = itemsets.len() as u32;
+            itemsets.push(v);
+            supports.push(n);
+        }
+        // The candidates were already sorted
+        (IndexedItemsets { itemsets, supports }, map)
+    }
+}
+/// The transaction IDs used for AprioriTID, stored as indices into the itemset array
+#[derive(Debug, Default)]
+pub struct TransactionIndices {
+    v: Vec<TransactionIndex>,
+}
+
+impl TransactionIndices {
+    pub fn new(v: Vec<TransactionIndex>) -> Self {
+        Self { v }
+    }
+    /// Creates the set of TIDs for the first pass only
+    pub fn start(data: &TransactionSet, set: &IndexedItemsets) -> Self {
+        // Maps each item to its index
+        let mut map = vec![u32::MAX; data.num_items];
+        for (i, (v, _)) in set.iter().enumerate() {
+            map[v[0]] = i as u32;
+        }
+        let mut v = Vec::new();
+        for d in data.iter() {
+            let value = TransactionIndex::new(
+                d.iter()
+                    .map(|&n| map[n as usize])
+                    .filter(|&n| n != u32::MAX)
+                    .collect(),
+            );
+            if value.ids().len() < 2 {
+                continue;
+            }
+            v.push(value);
+        }
+        Self::new(v)
+    }
+    /// Generates the TIDs of the itemsets in set (which are of size k)
+    pub fn from_transaction(data: &TransactionSet, k: usize, set: &IndexedItemsets) -> Self {
+        let mut tree = AprioriHashTree::new();
+        for (v, _) in set.iter() {
+            tree.add(v);
+        }
+        let mut v = Vec::new();
+        for d in data.iter() {
+            let mut ids = Vec::new();
+            apriori_count(d, k, tree.deref_mut(), |a| {
+                ids.push(set.index_of(a).unwrap() as u32);
+            });
+            if ids.len() < 2 {
+                continue;
+            }
+            ids.sort_unstable();
+            v.push(TransactionIndex::new(ids));
+        }
+        Self::new(v)
+    }
+    /// Counts the candidates, and returns the next set of TIDs
+    pub fn count(&self, c: &mut IndexedCandidates) -> Self {
+        let mut o = Self::default();
+        for d in &self.v {
+            let a = d.count(c);
+            if a.ids().is_empty() {
+                continue;
+            }
+            o.v.push(a);
+        }
+        o
+    }
+    /// Renames the ids through map, removing the ids mapped to u32::MAX
+    pub fn remap(&mut self, map: &[u32]) {
+        for d in &mut self.v {
+            d.v.retain_mut(|n| {
+                *n = map[*n as usize];
+                *n != u32::MAX
+            });
+        }
+        // A TID with less than 2 ids cannot contain any candidate of the next pass
+        self.v.retain(|d| d.ids().len() >= 2);
+    }
+    /// Iterates over the TIDs
+    pub fn iter(&self) -> impl Iterator<Item = &TransactionIndex> {
+        self.v.iter()
+    }
+    /// The number of TIDs
+    pub fn len(&self) -> usize {
+        self.v.len()
+    }
+    /// Checks if there are no TIDs
+    pub fn is_empty(&self) -> bool {
+        self.v.is_empty()
+    }
+}
+/// A Transaction ID for AprioriTID that is a sorted list of indices
+#[derive(Debug, Default)]
+pub struct TransactionIndex {
+    v: Vec<u32>,
+}
+
+impl TransactionIndex {
+    pub fn new(v: Vec<u32>) -> Self {
+        Self { v }
+    }
+    /// Counts the candidates contained in the TID, and returns the next TID
+    pub fn count(&self, c: &mut IndexedCandidates) -> Self {
+        let mut next = Vec::new();
+        for (p, &a) in self.v.iter().enumerate() {
+            // A candidate is contained if both of its generators are.
+            // The second generators are sorted, so this is a merge.
+            let rest = &self.v[(p + 1)..];
+            let mut q = 0;
+            for id in c.extensions[a as usize].clone() {
+                let b = c.second[id];
+                while q < rest.len() && rest[q] < b {
+                    q += 1;
+                }
+                if q >= rest.len() {
+                    break;
+                }
+                if rest[q] == b {
+                    c.counts[id] += 1;
+                    next.push(id as u32);
+                }
+            }
+        }
+        Self { v: next }
+    }
+    pub fn ids(&self) -> &[u32] {
+        &self.v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datasets::transaction_set::TransactionSet;
+
+    use super::{IndexedItemsets, TransactionIndices};
+
+    #[test]
+    fn test_join() {
+        let set = IndexedItemsets::new(vec![
+            (vec![1, 2, 3], 2),
+            (vec![1, 2, 4], 2),
+            (vec![1, 3, 4], 2),
+            (vec![1, 3, 5], 2),
+            (vec![2, 3, 4], 2),
+        ]);
+        let c = set.join();
+        // {1, 3, 4, 5} is pruned because {3, 4, 5} is not frequent
+        assert_eq!(c.len(), 1);
+        assert_eq!(c.itemsets[0], vec![1, 2, 3, 4]);
+        assert_eq!(c.second[0], 1);
+    }
+    #[test]
+    fn test_count() {
+        let example = TransactionSet::new(
+            vec![
+                vec![0, 1, 4],
+                vec![1, 3],
+                vec![1, 2],
+                vec![0, 1, 3],
+                vec![0, 2],
+                vec![1, 2],
+                vec![0, 2],
+                vec![0, 1, 2, 4],
+                vec![0, 1, 2],
+            ],
+            5,
+        );
+        let one = IndexedItemsets::new(vec![
+            (vec![0], 6),
+            (vec![1], 7),
+            (vec![2], 6),
+            (vec![3], 2),
+            (vec![4], 2),
+        ]);
+        let tids = TransactionIndices::start(&example, &one);
+        assert_eq!(tids.len(), 9);
+        let (two, tids) = one.next(&tids, 2);
+        assert_eq!(two.len(), 6);
+        assert!(two.index_of(&[0, 4]).is_some());
+        let (three, tids) = two.next(&tids, 2);
+        assert_eq!(three.len(), 2);
+        assert_eq!(three.get(0), &[0, 1, 2]);
+        assert_eq!(three.get(1), &[0, 1, 4]);
+        assert_eq!(three.get_support(0), 2);
+        let from = TransactionIndices::from_transaction(&example, 2, &two);
+        let (three2, _) = two.next(&from, 2);
+        assert_eq!(three2.len(), 2);
+        let (four, _) = three.next(&tids, 2);
+        assert!(four.is_empty());
+    }
+}
@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+
+use datasets::utils::Item;
 /// A Trie for counting in Apriori
 #[derive(Debug)]
 pub struct AprioriTrie {
@@ -42,11 +44,28 @@ impl AprioriTrie {
             false
         }
     }
-    /// Does the join step for Apriori
-    pub fn join(&mut self, i: usize, sup: u64) {
+    /// Does the join and prune steps for Apriori, adding the itemsets of size i
+    /// whose subsets are all frequent. Returns the number of joined itemsets that were pruned.
+    pub fn join(&mut self, i: usize, sup: u64) -> usize {
         assert!(i >= 2);
-        let c = self.root.join(i - 2, sup);
-        self.size += c;
+        let mut joined = Vec::new();
+        self.root.join(i - 2, sup, &mut Vec::new(), &mut joined);
+        let mut pruned = 0;
+        for v in joined {
+            // The subsets without one of the last two items are the ones joined
+            let frequent = (0..(i - 2)).all(|j| {
+                let mut subset = v.clone();
+                subset.remove(j);
+                self.root.get(&subset).is_some_and(|n| n >= sup)
+            });
+            if !frequent {
+                pruned += 1;
+            } else if self.root.add(&v) {
+                self.size += 1;
+            }
+        }
+        self.root.mark_done(i);
+        pruned
     }
     /// Inserts v into the trie with value n
     pub fn insert(&mut self, v: &[usize], n: u64) {
@@ -59,11 +78,15 @@ impl AprioriTrie {
         }
     }
     /// Counts the transaction into the trie
-    pub fn transaction_update(&mut self, v: &[usize], depth: usize) {
+    pub fn transaction_update<I: Item>(&mut self, v: &[I], depth: usize) {
         self.root.transaction_update(v, depth, 0)
     }
     /// A for each loop through the trie's elements
     pub fn for_each(&self, sup: u64, mut f: impl FnMut(&[usize])) {
+        self.for_each_support(sup, |v, _| f(v))
+    }
+    /// A for each loop through the trie's elements with their supports
+    pub fn for_each_support(&self, sup: u64, mut f: impl FnMut(&[usize], u64)) {
         let mut v = Vec::new();
         self.root.for_each(&mut v, sup, &mut f)
     }
@@ -72,6 +95,30 @@ impl AprioriTrie {
         let c = self.root.cleaup(sup);
         self.size -= c;
     }
+    /// Marks the items that are in some itemset of size depth
+    pub fn mark_items(&self, depth: usize, items: &mut [bool]) {
+        self.root.mark_items(depth, items);
+    }
+    /// Gives the itemsets of size depth consecutive ids (for counting into arrays).
+    /// Returns the number of them.
+    pub fn number(&mut self, depth: usize) -> usize {
+        let mut next = 0;
+        self.root.number(depth, &mut next);
+        next
+    }
+    /// The number of itemsets of size depth with a count of at least sup
+    pub fn count_at(&self, depth: usize, sup: u64) -> usize {
+        self.root.count_at(depth, sup)
+    }
+    /// Counts the transaction into counts instead of the trie,
+    /// indexed by the ids given by number.
+    pub fn transaction_count<I: Item>(&self, v: &[I], depth: usize, counts: &mut [u64]) {
+        self.root.transaction_count(v, depth, 0, counts)
+    }
+    /// Adds the counts (indexed by the ids given by number) to the itemsets of size depth
+    pub fn add_counts(&mut self, depth: usize, counts: &[u64]) {
+        self.root.add_counts(depth, counts)
+    }
 }
 /// The Node of a Trie
 #[derive(Debug)]
@@ -82,6 +129,8 @@ struct Node {
     map: HashMap<usize, Node>,
     /// Whether if all its children cannot be new frequent itemsets.
     done: bool,
+    /// The index of its count when counting into an array
+    id: usize,
 }
 impl Node {
     /// Constructor
@@ -90,6 +139,7 @@ impl Node {
             count: 0,
             map: HashMap::new(),
             done: false,
+            id: 0,
         }
     }
     /// Cleans up the trie
@@ -109,8 +159,23 @@ impl Node {
         }
         removed
     }
+    /// Marks the items of the itemsets at depth below the node.
+    /// Returns true if there are any.
+    fn mark_items(&self, depth: usize, items: &mut [bool]) -> bool {
+        if depth == 0 {
+            return true;
+        }
+        let mut found = false;
+        for (&n, node) in self.map.iter() {
+            if node.mark_items(depth - 1, items) {
+                items[n] = true;
+                found = true;
+            }
+        }
+        found
+    }
     /// Updates the trie based on the transaction
-    fn transaction_update(&mut self, v: &[usize], depth: usize, curr_i: usize) {
+    fn transaction_update<I: Item>(&mut self, v: &[I], depth: usize, curr_i: usize) {
         // If the current depth (curr_i) is equal, increment count
         if depth <= curr_i {
             self.count = self.count.saturating_add(1);
@@ -121,63 +186,112 @@ impl Node {
         }
         // Loop through the next itemsets and count them.
         for i in 0..(v.len() - (depth - curr_i - 1)) {
-            let n = v[i];
+            let n = v[i].index();
             if let Some(a) = self.map.get_mut(&n) { a.transaction_update(&v[(i + 1)..], depth, curr_i + 1) }
         }
     }
+    /// Numbers the nodes at depth below the node
+    fn number(&mut self, depth: usize, next: &mut usize) {
+        if depth == 0 {
+            self.id = *next;
+            *next += 1;
+            return;
+        }
+        for node in self.map.values_mut() {
+            node.number(depth - 1, next);
+        }
+    }
+    /// Same as transaction_update, but increments counts at the ids
+    fn transaction_count<I: Item>(&self, v: &[I], depth: usize, curr_i: usize, counts: &mut [u64]) {
+        if depth <= curr_i {
+            counts[self.id] += 1;
+            return;
+        } else if v.is_empty() || v.len() < depth - curr_i - 1 || self.done {
+            return;
+        }
+        for i in 0..(v.len() - (depth - curr_i - 1)) {
+            if let Some(a) = self.map.get(&v[i].index()) {
+                a.transaction_count(&v[(i + 1)..], depth, curr_i + 1, counts)
+            }
+        }
+    }
+    /// Adds the counts to the nodes at depth below the node
+    fn add_counts(&mut self, depth: usize, counts: &[u64]) {
+        if depth == 0 {
+            self.count = self.count.saturating_add(counts[self.id]);
+            return;
+        }
+        for node in self.map.values_mut() {
+            node.add_counts(depth - 1, counts);
+        }
+    }
     /// A for each through all the elements
-    fn for_each(&self, v: &mut Vec<usize>, sup: u64, f: &mut impl FnMut(&[usize])) {
+    fn for_each(&self, v: &mut Vec<usize>, sup: u64, f: &mut impl FnMut(&[usize], u64)) {
         for (&n, node) in self.map.iter() {
             if node.count < sup {
                 continue;
             }
             v.push(n);
-            f(v);
+            f(v, node.count);
             node.for_each(v, sup, f);
             v.pop();
         }
     }
-    /// Does the join step for Apriori
-    fn join(&mut self, i: usize, sup: u64) -> usize {
+    fn count_at(&self, depth: usize, sup: u64) -> usize {
+        if depth == 1 {
+            return self.map.values().filter(|n| n.count >= sup).count();
+        }
+        self.map.values().map(|n| n.count_at(depth - 1, sup)).sum()
+    }
+    /// Does the join step for Apriori below the node (with the items of prefix),
+    /// pushing the itemsets joined from the frequent ones i + 1 below it
+    fn join(&self, i: usize, sup: u64, prefix: &mut Vec<usize>, joined: &mut Vec<Vec<usize>>) {
         if i == 0 {
-            // All the candidate itemsets
+            // All the frequent itemsets below the node
             let mut v = Vec::new();
-            for (&n, node) in &mut self.map {
+            for (&n, node) in &self.map {
                 if node.count >= sup {
                     v.push(n);
                 }
             }
-            // Add the candidate itemsets and count the number added.
-            let mut count = 0;
-            for i in 0..v.len() {
-                for j in (i + 1)..v.len() {
-                    let n1 = v[i];
-                    let n2 = v[j];
-                    let max = n1.max(n2);
-                    let min = n1.min(n2);
-                    if self.add(&[min, max]) {
-                        count += 1;
-                    };
+            for (p, &a) in v.iter().enumerate() {
+                for &b in &v[(p + 1)..] {
+                    let mut w = prefix.clone();
+                    w.extend([a.min(b), a.max(b)]);
+                    joined.push(w);
                 }
             }
-            return count;
+            return;
         } else if self.done {
             // Exit early
-            return 0;
+            return;
         }
-        // Loop through the next nodes and get the total added
-        let mut total = 0;
-        for node in self.map.values_mut() {
+        for (&n, node) in &self.map {
             if node.count < sup {
                 continue;
             }
-            total += node.join(i - 1, sup);
+            prefix.push(n);
+            node.join(i - 1, sup, prefix, joined);
+            prefix.pop();
+        }
+    }
+    /// Marks the nodes with no itemsets depth below them as done.
+    /// Returns true if the node has any.
+    fn mark_done(&mut self, depth: usize) -> bool {
+        if depth == 0 {
+            return true;
+        } else if self.done {
+            return false;
         }
-        // If none were added, then the node is done.
-        if total == 0 {
+        let mut found = false;
+        for node in self.map.values_mut() {
+            found |= node.mark_done(depth - 1);
+        }
+        // If there are none, then the node is done.
+        if !found {
             self.done = true;
         }
-        total
+        found
     }
     /// Adds a node to the node
     fn add(&mut self, v: &[usize]) -> bool {
@@ -250,14 +364,14 @@ mod tests {
         trie.insert(&[3], 1);
         trie.insert(&[4], 10);
         trie.add(&[1, 2]);
-        trie.transaction_update(&[1, 2, 3], 2);
+        trie.transaction_update(&[1usize, 2, 3], 2);
         assert_eq!(trie.get(&[1, 2]), Some(1));
         assert_eq!(trie.size(), 5);
         trie.join(2, 5);
         assert!(trie.contains(&[1, 4]));
         assert!(trie.contains(&[2, 4]));
         assert_eq!(trie.size(), 7);
-        trie.transaction_update(&[2, 3, 4], 2);
+        trie.transaction_update(&[2usize, 3, 4], 2);
         assert_eq!(trie.get(&[2, 4]), Some(1));
         let mut set = HashSet::new();
         trie.for_each(5, |v| {
@@ -268,4 +382,32 @@ mod tests {
         assert!(set.contains(&vec![4]));
         assert!(set.len() == 3);
     }
+    #[test]
+    fn test_count_array() {
+        let mut trie = AprioriTrie::new();
+        trie.add(&[1, 2]);
+        trie.add(&[1, 3]);
+        trie.add(&[2, 3]);
+        assert_eq!(trie.number(2), 3);
+        let mut counts = vec![0; 3];
+        trie.transaction_count(&[1usize, 2, 3], 2, &mut counts);
+        trie.transaction_count(&[1usize, 3], 2, &mut counts);
+        trie.add_counts(2, &counts);
+        assert_eq!(trie.get(&[1, 2]), Some(1));
+        assert_eq!(trie.get(&[1, 3]), Some(2));
+        assert_eq!(trie.get(&[2, 3]), Some(1));
+    }
+    #[test]
+    fn test_mark_items() {
+        let mut trie = AprioriTrie::new();
+        trie.add(&[1, 2, 3]);
+        trie.add(&[1, 4]);
+        trie.add(&[5]);
+        let mut items = vec![false; 6];
+        trie.mark_items(3, &mut items);
+        assert_eq!(items, vec![false, true, true, true, false, false]);
+        let mut items = vec![false; 6];
+        trie.mark_items(2, &mut items);
+        assert_eq!(items, vec![false, true, true, false, true, false]);
+    }
 }
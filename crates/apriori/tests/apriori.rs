@@ -1,25 +1,46 @@
 use std::collections::HashSet;
 
 use apriori::{
-    apriori::Apriori, apriori_hybrid::AprioriHybrid, apriori_tid::AprioriTID,
-    apriori_trie::AprioriT,
+    apriori::{Apriori, CountingStructure},
+    apriori_hybrid::AprioriHybrid,
+    apriori_tid::{AprioriTID, TIDEncoding},
+    apriori_trie::{AprioriT, Trimming},
+    checkpoint::Checkpointer,
+    observer::PassStats,
 };
-use tester::test_utils::{Solved, test_generic};
+use datasets::{
+    binary::{BinaryFile, write_binary},
+    budget::{Budget, Status, Truncation},
+    transaction_set::TransactionSet,
+    transaction_source::DatFile,
+};
+use tester::test_utils::{DATABASE, Solved, test_generic};
 
 #[test]
 fn test_apriori() {
     test_generic(|t, s| {
         let a = Apriori::new(s);
-        let s = a.run(&t);
+        let s = a.run(&t).unwrap();
         let mut combined = HashSet::new();
         for c in s.iter() {
-            for e in c.iter() {
+            for e in c.keys() {
                 combined.insert(e.clone());
             }
         }
         Solved::new(combined)
     });
 }
+#[test]
+fn test_apriori_flat_trie() {
+    test_generic(|t, s| {
+        let a = Apriori::with_counting(s, CountingStructure::FlatTrie);
+        let mut combined = HashSet::new();
+        a.run_fn(&t, |e| {
+            combined.insert(e.to_vec());
+        }).unwrap();
+        Solved::new(combined)
+    });
+}
 
 #[test]
 fn test_tid() {
@@ -28,7 +49,7 @@ fn test_tid() {
         let s = a.run(&t);
         let mut combined = HashSet::new();
         for c in s.iter() {
-            for e in c.iter() {
+            for e in c.keys() {
                 combined.insert(e.clone());
             }
         }
@@ -38,11 +59,11 @@ fn test_tid() {
 #[test]
 fn test_hybrid() {
     test_generic(|t, s| {
-        let a = AprioriHybrid::new(s, 4);
+        let mut a = AprioriHybrid::new(s, 4);
         let s = a.run(&t);
         let mut combined = HashSet::new();
         for c in s.iter() {
-            for e in c.iter() {
+            for e in c.keys() {
                 combined.insert(e.clone());
             }
         }
@@ -53,7 +74,7 @@ fn test_hybrid() {
 fn test_trie() {
     test_generic(|t, s| {
         let mut a = AprioriT::new(s);
-        a.run(&t);
+        a.run(&t).unwrap();
         let mut combined = HashSet::new();
         a.trie().for_each(s, |e| {
             combined.insert(e.to_vec());
@@ -61,3 +82,182 @@ fn test_trie() {
         Solved::new(combined)
     });
 }
+#[test]
+fn test_tid_indices() {
+    test_generic(|t, s| {
+        let a = AprioriTID::with_encoding(s, TIDEncoding::Indices);
+        let mut combined = HashSet::new();
+        a.run_fn(&t, |e| {
+            combined.insert(e.to_vec());
+        });
+        Solved::new(combined)
+    });
+}
+#[test]
+fn test_hybrid_indices() {
+    test_generic(|t, s| {
+        let mut a = AprioriHybrid::with_encoding(s, 4, TIDEncoding::Indices);
+        let mut combined = HashSet::new();
+        a.run_fn(&t, |e| {
+            combined.insert(e.to_vec());
+        });
+        Solved::new(combined)
+    });
+}
+#[test]
+fn test_trie_trimming() {
+    for trimming in [Trimming::Filter, Trimming::Project(0.5)] {
+        test_generic(|t, s| {
+            let mut a = AprioriT::with_trimming(s, trimming);
+            a.run(&t).unwrap();
+            let mut combined = HashSet::new();
+            a.trie().for_each(s, |e| {
+                combined.insert(e.to_vec());
+            });
+            Solved::new(combined)
+        });
+    }
+}
+#[test]
+fn test_trie_threads() {
+    for trimming in [Trimming::None, Trimming::Filter, Trimming::Project(0.5)] {
+        test_generic(|t, s| {
+            let mut a = AprioriT::with_threads(s, trimming, 4);
+            a.run(&t).unwrap();
+            let mut combined = HashSet::new();
+            a.trie().for_each(s, |e| {
+                combined.insert(e.to_vec());
+            });
+            Solved::new(combined)
+        });
+    }
+}
+#[test]
+fn test_stream() {
+    test_generic(|_, s| {
+        let data = DatFile::open(DATABASE).unwrap();
+        let mut combined = HashSet::new();
+        Apriori::new(s).run_fn(&data, |e| {
+            combined.insert(e.to_vec());
+        }).unwrap();
+        let mut a = AprioriT::with_threads(s, Trimming::Project(0.5), 3);
+        a.run(&data).unwrap();
+        let mut trie = HashSet::new();
+        a.trie().for_each(s, |e| {
+            trie.insert(e.to_vec());
+        });
+        assert_eq!(combined, trie);
+        Solved::new(combined)
+    });
+}
+#[test]
+fn test_stream_removed() {
+    let path = std::env::temp_dir().join(format!("removed_{}.dat", std::process::id()));
+    std::fs::copy(DATABASE, &path).unwrap();
+    let data = DatFile::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    // The file is read again for the first pass, which fails and stops the run
+    let mut found = 0;
+    let status = Apriori::new(10).run_observed(&data, &mut (), &Budget::default(), |_, _| {
+        found += 1;
+    });
+    assert_eq!(
+        status,
+        Status::Truncated(Truncation::Read(std::io::ErrorKind::NotFound))
+    );
+    assert_eq!(found, 0);
+}
+#[test]
+fn test_binary() {
+    let path = std::env::temp_dir().join(format!("test1_{}.bin", std::process::id()));
+    write_binary(&DatFile::open(DATABASE).unwrap(), &path).unwrap();
+    test_generic(|_, s| {
+        let data = BinaryFile::open(&path).unwrap();
+        let mut combined = HashSet::new();
+        Apriori::new(s).run_fn(&data, |e| {
+            combined.insert(e.to_vec());
+        }).unwrap();
+        Solved::new(combined)
+    });
+    std::fs::remove_file(&path).unwrap();
+}
+#[test]
+fn test_resume() {
+    let data = TransactionSet::from_dat(std::fs::File::open(DATABASE).unwrap());
+    let path = std::env::temp_dir().join(format!("resume_{}.ckp", std::process::id()));
+    // Runs to the end, or stops before pass 3 if limited, saving a checkpoint after each pass
+    let run = |encoding: Option<TIDEncoding>,
+               checkpointer: &mut Checkpointer,
+               budget: &Budget,
+               passes: &mut Vec<PassStats>| {
+        let mut found = Vec::new();
+        let f = |v: &[usize], n| found.push((v.to_vec(), n));
+        let status = match encoding {
+            None => Apriori::new(10).run_checkpointed(&data, passes, budget, checkpointer, f),
+            Some(e) => AprioriTID::with_encoding(10, e).run_checkpointed(
+                &data,
+                passes,
+                budget,
+                checkpointer,
+                f,
+            ),
+        }
+        .unwrap();
+        found.sort_unstable();
+        (found, status)
+    };
+    for encoding in [
+        None,
+        Some(TIDEncoding::Itemsets),
+        Some(TIDEncoding::Indices),
+    ] {
+        let mut passes = Vec::new();
+        let (expected, status) = run(
+            encoding,
+            &mut Checkpointer::new(&path),
+            &Budget::default(),
+            &mut passes,
+        );
+        assert_eq!(status, Status::Complete);
+        assert!(passes.len() > 3);
+        let budget = Budget {
+            max_candidates: Some(passes[2].candidates - 1),
+            ..Default::default()
+        };
+        let (_, status) = run(
+            encoding,
+            &mut Checkpointer::new(&path),
+            &budget,
+            &mut Vec::new(),
+        );
+        assert!(matches!(
+            status,
+            Status::Truncated(Truncation::Candidates { pass: 3, .. })
+        ));
+        let mut resumed = Vec::new();
+        let (found, status) = run(
+            encoding,
+            &mut Checkpointer::resume(&path),
+            &Budget::default(),
+            &mut resumed,
+        );
+        assert_eq!(status, Status::Complete);
+        assert_eq!(found, expected);
+        assert_eq!(resumed[0].pass, 3);
+        assert_eq!(resumed.len(), passes.len() - 2);
+    }
+    // A checkpoint can only be resumed by the algorithm that saved it
+    let mut checkpointer = Checkpointer::resume(&path);
+    assert!(
+        Apriori::new(10)
+            .run_checkpointed(
+                &data,
+                &mut (),
+                &Budget::default(),
+                &mut checkpointer,
+                |_, _| {}
+            )
+            .is_err()
+    );
+    checkpointer.remove().unwrap();
+}
@@ -1,51 +1,155 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
+    io,
     sync::Arc,
     thread,
 };
 
-use apriori::candidates::Candidates;
-use datasets::transaction_set::TransactionSet;
+use apriori::{
+    apriori::AprioriCandidates,
+    candidates::Candidates,
+    checkpoint::{Checkpoint, CheckpointKind, Checkpointer},
+    observer::{PassCounts, PassObserver, PassTimer},
+};
+use datasets::{
+    budget::{Budget, Limiter, Status, Truncation},
+    constraint::Lengths,
+    transaction_set::TransactionSet,
+    transaction_source::TransactionSource,
+};
 
 use crate::process::CDProcess;
 /// The main struct used for Count Distribution
 /// This is a parallelized version of Apriori
-pub struct CountDistribution {
+pub struct CountDistribution<T = TransactionSet> {
     /// The dataset
-    data: Arc<TransactionSet>,
+    data: Arc<T>,
     /// Number of threads
     threads: usize,
     /// The frequent itemsets found
     candidates: Vec<Arc<Candidates>>,
     /// Min support count
     min_sup: u64,
+    /// The lengths of the itemsets found
+    lengths: Lengths,
 }
 
-impl CountDistribution {
+impl<T: TransactionSource + Send + Sync + 'static> CountDistribution<T> {
     /// Constructor
-    pub fn new(data: Arc<TransactionSet>, threads: usize, min_sup: u64) -> Self {
+    pub fn new(data: Arc<T>, threads: usize, min_sup: u64) -> Self {
         Self {
             data,
             threads,
             candidates: Vec::new(),
             min_sup,
+            lengths: Lengths::default(),
         }
     }
+    /// Only finds itemsets up to the longest of these lengths, stopping after its pass.
+    /// The itemsets returned still include the shorter ones.
+    pub fn lengths(mut self, lengths: Lengths) -> Self {
+        self.lengths = lengths;
+        self
+    }
     /// Runs the algorithm
-    pub fn run(mut self) -> Vec<Arc<Candidates>> {
-        // Creates a bunch of partitions
-        let partitions = self.partitions();
+    pub fn run(self) -> io::Result<Vec<Arc<Candidates>>> {
+        let (candidates, status) = self.run_observed(&mut (), &Budget::default());
+        status.read()?;
+        Ok(candidates)
+    }
+    /// Runs the algorithm, telling the observer about each pass,
+    /// and stopping early if it goes over the budget.
+    /// Returns the frequent itemsets of the passes that were done.
+    /// The time limit and cancellation are checked between passes,
+    /// so a pass that has started is counted to the end.
+    pub fn run_observed(
+        mut self,
+        observer: &mut impl PassObserver,
+        budget: &Budget,
+    ) -> (Vec<Arc<Candidates>>, Status) {
+        let mut limiter = budget.start();
+        let status = self
+            .run_limited(observer, &mut limiter, None, Checkpoint::default())
+            .into();
+        (self.candidates, status)
+    }
+    /// Runs the algorithm like run_observed, saving the frequent itemsets after each pass.
+    /// A resuming checkpointer continues from the last pass saved,
+    /// and the itemsets returned include the saved passes.
+    pub fn run_checkpointed(
+        mut self,
+        observer: &mut impl PassObserver,
+        budget: &Budget,
+        checkpointer: &mut Checkpointer,
+    ) -> io::Result<(Vec<Arc<Candidates>>, Status)> {
+        let resume =
+            checkpointer.start(CheckpointKind::CountDistribution, self.min_sup, &*self.data)?;
+        let mut limiter = budget.start();
+        let result = self.run_limited(observer, &mut limiter, Some(checkpointer), resume);
+        checkpointer.finish()?;
+        Ok((self.candidates, result.into()))
+    }
+    fn run_limited(
+        &mut self,
+        observer: &mut impl PassObserver,
+        limiter: &mut Limiter,
+        mut checkpointer: Option<&mut Checkpointer>,
+        resume: Checkpoint,
+    ) -> Result<(), Truncation> {
+        self.candidates = resume.frequent.into_iter().map(Arc::new).collect();
+        for (i, set) in self.candidates.iter().enumerate() {
+            if self.lengths.contains(i + 1) {
+                limiter.found(set.len())?;
+            }
+        }
         // Runs pass 1 and 2
-        self.run_one(&partitions);
-        self.run_two(&partitions);
-        for n in 3.. {
+        if self.candidates.is_empty() {
+            let timer = PassTimer::start(1, observer);
+            limiter.candidates(1, self.data.num_items())?;
+            self.run_one()?;
+            let items = self.candidates[0].len();
+            timer.end(observer, PassCounts::new(self.data.num_items()), items);
+            if let Some(c) = checkpointer.as_deref_mut() {
+                c.save(&self.candidates);
+            }
+            if self.lengths.contains(1) {
+                limiter.found(items)?;
+            }
+        }
+        if self.candidates.len() == 1 && self.lengths.extends(1) {
+            let timer = PassTimer::start(2, observer);
+            let items = self.candidates[0].len();
+            let pairs = items * items.saturating_sub(1) / 2;
+            limiter.candidates(2, pairs)?;
+            self.run_two()?;
+            timer.end(observer, PassCounts::new(pairs), self.candidates[1].len());
+            if let Some(c) = checkpointer.as_deref_mut() {
+                c.save(&self.candidates);
+            }
+            if self.lengths.contains(2) {
+                limiter.found(self.candidates[1].len())?;
+            }
+        }
+        for n in (self.candidates.len() + 1)..=self.lengths.max {
+            let timer = PassTimer::start(n, observer);
+            limiter.check()?;
+            let last = AprioriCandidates::new(&self.candidates[n - 2]);
+            let (joined, pruned) = last.join_candidates();
+            let counts = PassCounts {
+                candidates: joined.len(),
+                pruned,
+            };
+            // Stops before counting the candidates, as Apriori does
+            limiter.candidates(n, counts.candidates)?;
+            let joined = Arc::new(joined);
             let mut handles = Vec::new();
-            // Loops through all the partitions and create a thread to generate their itemsets
-            for p in &partitions {
-                let p = Arc::clone(p);
-                let candidates = Arc::clone(&self.candidates[n - 2]);
+            // Loops through all the partitions and create a thread to count their candidates
+            for i in 0..self.threads {
+                let p = Arc::clone(&self.data);
+                let threads = self.threads;
+                let joined = Arc::clone(&joined);
                 let handle = thread::spawn(move || {
-                    let cd = CDProcess::new(p, candidates);
+                    let cd = CDProcess::new(p, i, threads, joined);
                     cd.run(n)
                 });
                 handles.push(handle);
@@ -53,7 +157,7 @@ impl CountDistribution {
             // Put all the results in some Vector
             let mut results = Vec::new();
             for h in handles {
-                results.push(h.join().unwrap());
+                results.push(h.join().unwrap()?);
             }
             // Combine the results into one map
             let mut map = HashMap::new();
@@ -71,24 +175,33 @@ impl CountDistribution {
             let mut set = Candidates::default();
             for (k, v) in map {
                 if v >= self.min_sup {
-                    set.insert(k);
+                    set.insert(k, v);
                 }
             }
+            timer.end(observer, counts, set.len());
             if set.is_empty() {
                 break;
             }
+            let found = set.len();
             self.candidates.push(Arc::new(set));
+            if let Some(c) = checkpointer.as_deref_mut() {
+                c.save(&self.candidates);
+            }
+            if self.lengths.contains(n) {
+                limiter.found(found)?;
+            }
         }
-        self.candidates
+        Ok(())
     }
     /// Runs pass 2 of the algorithm
-    fn run_two(&mut self, p: &[Arc<TransactionSet>]) {
+    fn run_two(&mut self) -> io::Result<()> {
         let mut handles = Vec::new();
         // Create the threads to count the partitions
         for i in 0..self.threads {
-            let p = Arc::clone(&p[i]);
+            let p = Arc::clone(&self.data);
+            let threads = self.threads;
             let handle = thread::spawn(move || {
-                let cd = CDProcess::new(p, Arc::new(Candidates::default()));
+                let cd = CDProcess::new(p, i, threads, Arc::default());
                 cd.run_two()
             });
             handles.push(handle);
@@ -96,7 +209,7 @@ impl CountDistribution {
         // Get the results of all the threads
         let mut results = Vec::new();
         for h in handles {
-            results.push(h.join().unwrap());
+            results.push(h.join().unwrap()?);
         }
         // Combine them into one
         let p = results.split_at_mut(1);
@@ -107,19 +220,21 @@ impl CountDistribution {
         let mut set = Candidates::default();
         for (r, c, v) in results[0].iter() {
             if v >= self.min_sup {
-                set.insert(vec![c, r]);
+                set.insert(vec![c, r], v);
             }
         }
         self.candidates.push(Arc::new(set));
+        Ok(())
     }
     /// Runs pass 1 of the algorithm
-    fn run_one(&mut self, p: &[Arc<TransactionSet>]) {
+    fn run_one(&mut self) -> io::Result<()> {
         let mut handles = Vec::new();
         // Create all the threads for counting
         for i in 0..self.threads {
-            let p = Arc::clone(&p[i]);
+            let p = Arc::clone(&self.data);
+            let threads = self.threads;
             let handle = thread::spawn(move || {
-                let cd = CDProcess::new(p, Arc::new(Candidates::default()));
+                let cd = CDProcess::new(p, i, threads, Arc::default());
                 cd.run_one()
             });
             handles.push(handle);
@@ -127,7 +242,7 @@ impl CountDistribution {
         // Get all the results
         let mut results = Vec::new();
         for h in handles {
-            results.push(h.join().unwrap());
+            results.push(h.join().unwrap()?);
         }
         // Combine them into one at results[0]
         for i in 1..results.len() {
@@ -136,31 +251,14 @@ impl CountDistribution {
             }
         }
         // Creates the frequent itemsets
-        let mut set = HashSet::new();
-        for i in 0..results[0].len() {
-            if results[0][i] >= self.min_sup {
-                set.insert(vec![i]);
+        let mut set = Candidates::default();
+        for (i, &n) in results[0].iter().enumerate() {
+            if n >= self.min_sup {
+                set.insert(vec![i], n);
             }
         }
-        self.candidates.push(Arc::new(set.into()));
-    }
-    /// Partitions the dataset
-    fn partitions(&self) -> Vec<Arc<TransactionSet>> {
-        let mut v = Vec::new();
-        for i in 0..self.threads {
-            v.push(Arc::new(self.partition(i)));
-        }
-        v
-    }
-    /// Creates a partition of the dataset for a thread
-    fn partition(&self, thread: usize) -> TransactionSet {
-        let count = self.data.len() / self.threads;
-        let slice = if thread == self.threads - 1 {
-            &self.data[(count * thread)..self.data.len()]
-        } else {
-            &self.data[(count * thread)..(count * (thread + 1))]
-        };
-        TransactionSet::new(slice.to_vec(), self.data.num_items)
+        self.candidates.push(Arc::new(set));
+        Ok(())
     }
 }
 
@@ -168,7 +266,11 @@ impl CountDistribution {
 mod tests {
     use std::sync::Arc;
 
-    use datasets::transaction_set::TransactionSet;
+    use apriori::observer::PassStats;
+    use datasets::{
+        budget::{Budget, Status, Truncation},
+        transaction_set::TransactionSet,
+    };
 
     use crate::count_distribution::CountDistribution;
 
@@ -189,14 +291,35 @@ mod tests {
             5,
         ));
         let cd = CountDistribution::new(example, 8, 2);
-        let cd = cd.run();
-        assert!(cd[0].contains(&vec![0]));
-        assert!(cd[0].contains(&vec![1]));
-        assert!(cd[0].contains(&vec![2]));
-        assert!(cd[0].contains(&vec![3]));
-        assert!(cd[0].contains(&vec![4]));
+        let cd = cd.run().unwrap();
+        assert_eq!(cd[0].get(&vec![0]), Some(&6));
+        assert_eq!(cd[0].get(&vec![1]), Some(&7));
+        assert_eq!(cd[0].get(&vec![2]), Some(&6));
+        assert_eq!(cd[0].get(&vec![3]), Some(&2));
+        assert_eq!(cd[0].get(&vec![4]), Some(&2));
         assert_eq!(cd[0].len(), 5);
         assert_eq!(cd[1].len(), 6);
         assert_eq!(cd[2].len(), 2);
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_max_candidates() {
+        // The 7 items make 21 pairs and 35 triples
+        let all: Vec<_> = (0..7).collect();
+        let data = Arc::new(TransactionSet::new(vec![all.clone(), all], 7));
+        let budget = Budget {
+            max_candidates: Some(30),
+            ..Default::default()
+        };
+        let mut passes: Vec<PassStats> = Vec::new();
+        let (found, status) =
+            CountDistribution::new(data, 2, 2).run_observed(&mut passes, &budget);
+        let truncation = Truncation::Candidates {
+            pass: 3,
+            candidates: 35,
+        };
+        assert_eq!(status, Status::Truncated(truncation));
+        // Pass 3 is stopped before its candidates are counted
+        assert_eq!(found.len(), 2);
+        assert_eq!(passes.len(), 2);
+    }
+}
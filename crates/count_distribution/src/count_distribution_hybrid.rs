@@ -3,8 +3,18 @@ use std::{
     thread,
 };
 
-use apriori::candidates::Candidates;
-use datasets::transaction_set::TransactionSet;
+use apriori::{
+    apriori::AprioriCandidates,
+    apriori_hybrid::{SwitchHeuristic, SwitchPass},
+    apriori_tid::TIDEncoding,
+    candidates::{CandidateCounter, Candidates},
+    observer::{PassCounts, PassObserver, PassTimer},
+};
+use datasets::{
+    budget::{Budget, Limiter, Status, Truncation},
+    constraint::Lengths,
+    transaction_set::TransactionSet,
+};
 
 use crate::process_hybrid::CDProcessHybrid;
 /// The parallelized version of AprioriHybrid
@@ -17,45 +27,118 @@ pub struct CountDistributionHybrid<'a> {
     candidates: Vec<Arc<Candidates>>,
     /// Minimum support count
     min_sup: u64,
-    /// What pass to switch to AprioriTID
-    switch: usize,
+    /// When to switch to AprioriTID
+    switch: SwitchPass,
+    /// The lengths of the itemsets found
+    lengths: Lengths,
 }
 impl<'a> CountDistributionHybrid<'a> {
     /// Constructor
     pub fn new(data: &'a TransactionSet, threads: usize, min_sup: u64, switch: usize) -> Self {
-        assert!(switch > 2);
+        Self::with_switch(data, threads, min_sup, SwitchPass::Fixed(switch))
+    }
+    /// Constructor with when to switch
+    pub fn with_switch(
+        data: &'a TransactionSet,
+        threads: usize,
+        min_sup: u64,
+        switch: SwitchPass,
+    ) -> Self {
+        if let SwitchPass::Fixed(switch) = switch {
+            assert!(switch > 2);
+        }
         Self {
             data,
             threads,
             candidates: Vec::new(),
             min_sup,
             switch,
+            lengths: Lengths::default(),
         }
     }
+    /// Only finds itemsets up to the longest of these lengths, stopping after its pass.
+    /// The itemsets returned still include the shorter ones.
+    pub fn lengths(mut self, lengths: Lengths) -> Self {
+        self.lengths = lengths;
+        self
+    }
     /// Runs the algorithm
-    pub fn run(mut self) -> Vec<Arc<Candidates>> {
+    pub fn run(self) -> Vec<Arc<Candidates>> {
+        self.run_with_switch().0
+    }
+    /// Runs the algorithm, also returning the pass it switched to AprioriTID at
+    pub fn run_with_switch(self) -> (Vec<Arc<Candidates>>, Option<usize>) {
+        let (candidates, switched, _) = self.run_observed(&mut (), &Budget::default());
+        (candidates, switched)
+    }
+    /// Runs the algorithm, telling the observer about each pass,
+    /// and stopping early if it goes over the budget.
+    /// Returns the frequent itemsets of the passes that were done,
+    /// and the pass it switched to AprioriTID at.
+    /// The time limit and cancellation are checked between passes,
+    /// so a pass that has started is counted to the end.
+    pub fn run_observed(
+        mut self,
+        observer: &mut impl PassObserver,
+        budget: &Budget,
+    ) -> (Vec<Arc<Candidates>>, Option<usize>, Status) {
+        let mut switched = None;
+        let status = self
+            .run_limited(observer, &mut budget.start(), &mut switched)
+            .into();
+        (self.candidates, switched, status)
+    }
+    fn run_limited(
+        &mut self,
+        observer: &mut impl PassObserver,
+        limiter: &mut Limiter,
+        switched: &mut Option<usize>,
+    ) -> Result<(), Truncation> {
+        let (mut switch, memory) = match self.switch {
+            SwitchPass::Fixed(n) => (n, None),
+            SwitchPass::Auto(memory) => (usize::MAX, Some(memory)),
+        };
+        let mut heuristic = None;
         // Contains the thread data
-        let sets = self.partitions();
-        for n in 1.. {
+        let sets = self.partitions(switch);
+        for n in 1..=self.lengths.max {
+            let timer = PassTimer::start(n, observer);
+            limiter.check()?;
+            // The candidates are joined once, and checked before any thread counts them
+            let (joined, counts) = match n {
+                1 => (Vec::new(), PassCounts::new(self.data.num_items)),
+                2 => {
+                    let items = self.candidates[0].len();
+                    let pairs = items * items.saturating_sub(1) / 2;
+                    (Vec::new(), PassCounts::new(pairs))
+                }
+                _ => {
+                    let last = AprioriCandidates::new(self.candidates.last().unwrap());
+                    let (joined, pruned) = last.join_candidates();
+                    let candidates = joined.len();
+                    (joined, PassCounts { candidates, pruned })
+                }
+            };
+            limiter.candidates(n, counts.candidates)?;
+            if n == switch {
+                *switched = Some(n);
+            }
+            let joined = Arc::new(joined);
             // Runs the counting for each thread
             let mut handlers = Vec::new();
             for p in sets.iter() {
                 let p = Arc::clone(p);
-                let c = if n == 1 {
-                    Arc::new(Candidates::default())
-                } else {
-                    Arc::clone(self.candidates.last().unwrap())
-                };
+                let joined = Arc::clone(&joined);
                 let t = thread::spawn(move || {
                     let mut p = p.lock().unwrap();
-                    p.run(n, &c)
+                    p.run(n, &joined)
                 });
                 handlers.push(t);
             }
             // Gets the results of each thread
             let mut result = Vec::new();
             for h in handlers {
-                result.push(h.join().unwrap());
+                result.push(h.join().unwrap()?);
             }
             // Combines them into one map
             let (first, rest) = result.split_at_mut(1);
@@ -73,29 +156,63 @@ impl<'a> CountDistributionHybrid<'a> {
             let mut set = Candidates::default();
             for (k, &v) in &result[0] {
                 if v >= self.min_sup {
-                    set.insert(k.clone());
+                    set.insert(k.clone(), v);
                 }
             }
+            timer.end(observer, counts, set.len());
             if set.is_empty() {
                 break;
             }
+            // Decides whether the next pass switches to AprioriTID
+            if n == 1 {
+                heuristic =
+                    memory.map(|m| SwitchHeuristic::new(m, TIDEncoding::Itemsets, set.len()));
+            } else if n < switch
+                && let Some(h) = &mut heuristic
+            {
+                let support = self.candidate_support(n, &result[0]);
+                if h.next_pass(n, support, self.data.len(), set.len()) {
+                    switch = n + 1;
+                    for p in sets.iter() {
+                        p.lock().unwrap().set_switch(switch);
+                    }
+                }
+            }
+            let found = set.len();
             self.candidates.push(Arc::new(set));
+            if self.lengths.contains(n) {
+                limiter.found(found)?;
+            }
         }
-        self.candidates
+        Ok(())
+    }
+    /// The sum of the supports of the candidates of pass n
+    fn candidate_support(&self, n: usize, counts: &CandidateCounter) -> u64 {
+        if n != 2 {
+            return counts.values().sum();
+        }
+        // Pass 2 counts every pair, but the candidates are only the pairs of frequent items
+        let mut frequent = vec![false; self.data.num_items];
+        self.candidates[0].keys().for_each(|v| frequent[v[0]] = true);
+        counts
+            .iter()
+            .filter(|(k, _)| frequent[k[0]] && frequent[k[1]])
+            .map(|(_, &v)| v)
+            .sum()
     }
     /// Partitions the data into threads
-    fn partitions(&self) -> Vec<Arc<Mutex<CDProcessHybrid>>> {
+    fn partitions(&self, switch: usize) -> Vec<Arc<Mutex<CDProcessHybrid>>> {
         let mut v = Vec::new();
         // Each thread gets a partition of the database and what pass to switch
         for thread in 0..self.threads {
             let count = self.data.len() / self.threads;
-            let slice = if thread == self.threads - 1 {
-                &self.data[(count * thread)..self.data.len()]
+            let range = if thread == self.threads - 1 {
+                (count * thread)..self.data.len()
             } else {
-                &self.data[(count * thread)..(count * (thread + 1))]
+                (count * thread)..(count * (thread + 1))
             };
-            let ts = TransactionSet::new(slice.to_vec(), self.data.num_items);
-            let process = CDProcessHybrid::new(ts, self.switch);
+            let ts = self.data.slice(range);
+            let process = CDProcessHybrid::new(ts, switch);
             v.push(Arc::new(Mutex::new(process)));
         }
         v
@@ -104,7 +221,12 @@ impl<'a> CountDistributionHybrid<'a> {
 
 #[cfg(test)]
 mod tests {
-    use datasets::transaction_set::TransactionSet;
+    use datasets::{
+        budget::{Budget, Status, Truncation},
+        transaction_set::TransactionSet,
+    };
+
+    use apriori::{apriori_hybrid::SwitchPass, observer::PassStats};
 
     use crate::count_distribution_hybrid::CountDistributionHybrid;
 
@@ -125,14 +247,66 @@ mod tests {
             5,
         );
         let cd = CountDistributionHybrid::new(&example, 8, 2, 3);
-        let cd = cd.run();
-        assert!(cd[0].contains(&vec![0]));
-        assert!(cd[0].contains(&vec![1]));
-        assert!(cd[0].contains(&vec![2]));
-        assert!(cd[0].contains(&vec![3]));
-        assert!(cd[0].contains(&vec![4]));
+        let (cd, switched) = cd.run_with_switch();
+        assert_eq!(switched, Some(3));
+        assert!(cd[0].contains_key(&vec![0]));
+        assert!(cd[0].contains_key(&vec![1]));
+        assert!(cd[0].contains_key(&vec![2]));
+        assert!(cd[0].contains_key(&vec![3]));
+        assert!(cd[0].contains_key(&vec![4]));
         assert_eq!(cd[0].len(), 5);
         assert_eq!(cd[1].len(), 6);
         assert_eq!(cd[2].len(), 2);
+        // Pass 3 joins 6 itemsets, 4 of which have an infrequent pair
+        let mut passes: Vec<PassStats> = Vec::new();
+        let budget = Budget::default();
+        CountDistributionHybrid::new(&example, 2, 2, 3).run_observed(&mut passes, &budget);
+        let counts: Vec<_> = passes
+            .iter()
+            .map(|p| (p.pass, p.candidates, p.pruned, p.frequent))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![(1, 5, 0, 5), (2, 10, 0, 6), (3, 2, 4, 2), (4, 0, 1, 0)]
+        );
+    }
+    #[test]
+    fn test_max_candidates() {
+        // The 7 items make 21 pairs and 35 triples
+        let all: Vec<_> = (0..7).collect();
+        let data = TransactionSet::new(vec![all.clone(), all], 7);
+        let budget = Budget {
+            max_candidates: Some(30),
+            ..Default::default()
+        };
+        let (found, switched, status) =
+            CountDistributionHybrid::new(&data, 2, 2, 3).run_observed(&mut (), &budget);
+        let truncation = Truncation::Candidates {
+            pass: 3,
+            candidates: 35,
+        };
+        assert_eq!(status, Status::Truncated(truncation));
+        // Pass 3 is stopped before it switches or counts
+        assert_eq!(found.len(), 2);
+        assert_eq!(switched, None);
+    }
+    #[test]
+    fn test_auto_switch() {
+        let example = TransactionSet::new(
+            vec![
+                vec![0, 1, 2, 3],
+                vec![0, 1, 2, 3],
+                vec![0, 1, 2],
+                vec![0, 1, 3],
+                vec![1, 2, 3],
+                vec![0, 4],
+            ],
+            5,
+        );
+        let cd = CountDistributionHybrid::with_switch(&example, 2, 2, SwitchPass::Auto(1 << 20));
+        let (cd, switched) = cd.run_with_switch();
+        assert_eq!(cd.len(), 4);
+        assert_eq!(cd[3].len(), 1);
+        assert_eq!(switched, Some(4));
     }
 }
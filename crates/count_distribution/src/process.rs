@@ -1,31 +1,54 @@
-use std::{ops::Deref, sync::Arc};
+use std::{io, ops::DerefMut, sync::Arc};
 
-use apriori::{apriori::{apriori_run_one_count, apriori_run_two_count, AprioriCandidates}, array2d::Array2D, candidates::Candidates};
-use datasets::transaction_set::TransactionSet;
+use apriori::{
+    apriori::{apriori_count, apriori_run_one_count, apriori_run_two_count},
+    array2d::Array2D,
+    hash_tree::AprioriHashTree,
+};
+use datasets::transaction_source::TransactionSource;
 /// A thread for Count Distribution
-pub struct CDProcess {
-    /// The partition the thread will use
-    data: Arc<TransactionSet>,
-    /// The candidates from the previous passes
-    candidates: Arc<Candidates>,
+pub struct CDProcess<T> {
+    /// The dataset
+    data: Arc<T>,
+    /// The partition of the dataset the thread will use (out of parts)
+    partition: usize,
+    parts: usize,
+    /// The candidates of the pass, which every thread counts
+    candidates: Arc<Vec<Vec<usize>>>,
 }
 
-impl CDProcess {
+impl<T: TransactionSource> CDProcess<T> {
     /// Constructor
-    pub fn new(data: Arc<TransactionSet>, candidates: Arc<Candidates>) -> Self {
-        Self { data, candidates }
+    pub fn new(
+        data: Arc<T>,
+        partition: usize,
+        parts: usize,
+        candidates: Arc<Vec<Vec<usize>>>,
+    ) -> Self {
+        Self {
+            data,
+            partition,
+            parts,
+            candidates,
+        }
     }
-    /// Runs the algorithm for passes 3+
-    pub fn run(self, n: usize) -> apriori::hash_tree::AprioriHashTree {
+    /// Runs the algorithm for passes 3+, returning the counts of the candidates
+    pub fn run(self, n: usize) -> io::Result<AprioriHashTree> {
         assert!(n > 2);
-        AprioriCandidates::new(self.candidates.deref().deref()).run_count(&self.data, n)
+        let mut tree = AprioriHashTree::new();
+        for v in self.candidates.iter() {
+            tree.add(v);
+        }
+        let data = self.data.part(self.partition, self.parts);
+        data.scan(|d| apriori_count(d, n, tree.deref_mut(), |_| {}))?;
+        Ok(tree)
     }
     /// Runs the algorithm for pass 1
-    pub fn run_one(self) -> Vec<u64> {
-        apriori_run_one_count(&self.data)
+    pub fn run_one(self) -> io::Result<Vec<u64>> {
+        apriori_run_one_count(&self.data.part(self.partition, self.parts))
     }
     /// Runs the algorithm for pass 2
-    pub fn run_two(self) -> Array2D<u64> {
-        apriori_run_two_count(&self.data)
+    pub fn run_two(self) -> io::Result<Array2D<u64>> {
+        apriori_run_two_count(&self.data.part(self.partition, self.parts))
     }
 }
@@ -1,12 +1,12 @@
-use std::ops::Deref;
+use std::{io, ops::DerefMut};
 
 use apriori::{
-    apriori::{AprioriCandidates, apriori_run_one_count, apriori_run_two_count},
-    apriori_tid::AprioriTiDCandidates,
-    candidates::{CandidateCounter, Candidates},
+    apriori::{apriori_count, apriori_run_one_count, apriori_run_two_count},
+    candidates::CandidateCounter,
+    hash_tree::AprioriHashTree,
     transaction_id::TransactionIDs,
 };
-use datasets::transaction_set::TransactionSet;
+use datasets::{transaction_set::TransactionSet, transaction_source::TransactionSource};
 /// The thread for Count Distribution Hybrid
 pub struct CDProcessHybrid {
     /// The partitioned set
@@ -26,38 +26,41 @@ impl CDProcessHybrid {
             switch,
         }
     }
-    /// Runs the counting
-    pub fn run(&mut self, n: usize, c: &Candidates) -> CandidateCounter {
+    /// Sets the pass to switch
+    pub fn set_switch(&mut self, switch: usize) {
+        self.switch = switch;
+    }
+    /// Runs the counting of pass n, with the candidates of the passes after the second
+    pub fn run(&mut self, n: usize, candidates: &[Vec<usize>]) -> io::Result<CandidateCounter> {
         if n == 1 {
             // Pass 1 counting and transforming it into a map
-            let c = apriori_run_one_count(&self.set);
+            let c = apriori_run_one_count(&self.set)?;
             let counter = c
                 .into_iter()
                 .enumerate()
                 .map(|(i, v)| (vec![i], v))
                 .collect();
-            return counter;
+            return Ok(counter);
         } else if n == 2 {
             // Pass 2 counting and transforming it into a map
-            let c = apriori_run_two_count(&self.set);
+            let c = apriori_run_two_count(&self.set)?;
             let counter = c.iter().map(|(r, c, v)| (vec![c, r], v)).collect();
-            return counter;
+            return Ok(counter);
+        }
+        let mut tree = AprioriHashTree::new();
+        for v in candidates {
+            tree.add(v);
         }
         if n == self.switch {
-            let (c, b) = TransactionIDs::from_transaction(&self.set.transactions, n - 1, c);
             // At the switch, create the TIDs from the transactions
-            self.tid = b;
-            let map = c.iter().map(|(v, n)| (v.to_vec(), n)).collect();
-            return map;
+            self.tid = TransactionIDs::from_tree(&self.set, n - 1, &mut tree);
         } else if n > self.switch {
-            let (c, b) = AprioriTiDCandidates::new(c.deref()).count(&self.tid);
-            self.tid = b;
             // AprioriTID counting after the switch
-            c.iter().map(|(v, n)| (v.to_vec(), n)).collect()
+            self.tid = self.tid.count(tree.deref_mut());
         } else {
             // Apriori counting before the switch
-            let c = AprioriCandidates::new(c.deref()).run_count(&self.set, n);
-            c.iter().map(|(v, n)| (v.to_vec(), n)).collect()
+            self.set.scan(|d| apriori_count(d, n, tree.deref_mut(), |_| {}))?;
         }
+        Ok(tree.iter().map(|(v, n)| (v.to_vec(), n)).collect())
     }
 }
@@ -1,6 +1,11 @@
 use std::{collections::HashSet, sync::Arc};
 
-use tester::test_utils::{Solved, test_generic};
+use apriori::{candidates::Candidates, checkpoint::Checkpointer};
+use datasets::{
+    budget::{Budget, Status, Truncation},
+    transaction_source::DatFile,
+};
+use tester::test_utils::{DATABASE, Solved, test_generic};
 use count_distribution::{count_distribution::CountDistribution, count_distribution_hybrid::CountDistributionHybrid};
 
 #[test]
@@ -8,10 +13,24 @@ fn test_cd() {
     test_generic(|t, s| {
         let a = Arc::new(t);
         let a = CountDistribution::new(a, 4, s);
-        let s = a.run();
+        let s = a.run().unwrap();
+        let mut combined = HashSet::new();
+        for c in s.iter() {
+            for e in c.keys() {
+                combined.insert(e.clone());
+            }
+        }
+        Solved::new(combined)
+    });
+}
+#[test]
+fn test_cd_stream() {
+    test_generic(|_, s| {
+        let a = Arc::new(DatFile::open(DATABASE).unwrap());
+        let s = CountDistribution::new(a, 4, s).run().unwrap();
         let mut combined = HashSet::new();
         for c in s.iter() {
-            for e in c.iter() {
+            for e in c.keys() {
                 combined.insert(e.clone());
             }
         }
@@ -25,10 +44,47 @@ fn test_cd_hybrid() {
         let s = a.run();
         let mut combined = HashSet::new();
         for c in s.iter() {
-            for e in c.iter() {
+            for e in c.keys() {
                 combined.insert(e.clone());
             }
         }
         Solved::new(combined)
     });
-}
\ No newline at end of file
+}
+fn sorted(c: Vec<Arc<Candidates>>) -> Vec<Vec<usize>> {
+    let mut v: Vec<_> = c.iter().flat_map(|c| c.keys().cloned()).collect();
+    v.sort_unstable();
+    v
+}
+#[test]
+fn test_cd_resume() {
+    let path = std::env::temp_dir().join(format!("cd_resume_{}.ckp", std::process::id()));
+    let data = Arc::new(DatFile::open(DATABASE).unwrap());
+    let mut passes = Vec::new();
+    let mut checkpointer = Checkpointer::new(&path);
+    let (expected, status) = CountDistribution::new(data.clone(), 4, 10)
+        .run_checkpointed(&mut passes, &Budget::default(), &mut checkpointer)
+        .unwrap();
+    assert_eq!(status, Status::Complete);
+    // Stops before counting pass 3, which has too many candidates
+    let budget = Budget {
+        max_candidates: Some(passes[2].candidates - 1),
+        ..Default::default()
+    };
+    let (_, status) = CountDistribution::new(data.clone(), 4, 10)
+        .run_checkpointed(&mut (), &budget, &mut Checkpointer::new(&path))
+        .unwrap();
+    assert!(matches!(
+        status,
+        Status::Truncated(Truncation::Candidates { pass: 3, .. })
+    ));
+    let mut resumed = Vec::new();
+    let mut checkpointer = Checkpointer::resume(&path);
+    let (found, status) = CountDistribution::new(data, 4, 10)
+        .run_checkpointed(&mut resumed, &Budget::default(), &mut checkpointer)
+        .unwrap();
+    assert_eq!(status, Status::Complete);
+    assert_eq!(sorted(found), sorted(expected));
+    assert_eq!(resumed[0].pass, 3);
+    checkpointer.remove().unwrap();
+}
@@ -0,0 +1,255 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+    slice,
+};
+
+use memmap2::Mmap;
+
+use crate::{transaction_set::TransactionSet, transaction_source::TransactionSource, utils::Item};
+/// The first bytes of a binary dataset
+pub const MAGIC: &[u8; 8] = b"FIMCSR01";
+/// The size of the header (the magic, number of items, transactions, and entries)
+const HEADER: usize = 32;
+/// A binary dataset that is memory mapped.
+/// The file is little endian, and is laid out as
+/// - the header: the magic, then the number of items, transactions, and entries as u64
+/// - the support of each item as u64
+/// - where each transaction starts in the entries as u64 (with one more for the end)
+/// - the items of every transaction as u32
+#[derive(Debug)]
+pub struct BinaryFile {
+    mmap: Mmap,
+    num_items: usize,
+    len: usize,
+    entries: usize,
+}
+impl BinaryFile {
+    /// Maps the file, checking that its header matches its size,
+    /// and that its transactions are sorted and have the supports stored for their items
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let invalid = |e: &str| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+        if cfg!(target_endian = "big") {
+            return Err(invalid(
+                "Binary datasets are only supported on little endian",
+            ));
+        }
+        let file = File::open(path)?;
+        // Safety: the file is not expected to change while it is mapped
+        let mmap = unsafe { Mmap::map(&file)? };
+        if mmap.len() < HEADER || &mmap[..8] != MAGIC {
+            return Err(invalid("Not a binary dataset"));
+        }
+        let read = |i: usize| {
+            let n = u64::from_le_bytes(mmap[i..(i + 8)].try_into().unwrap());
+            usize::try_from(n).map_err(|_| invalid("The header of the binary dataset is too large"))
+        };
+        let (num_items, len, entries) = (read(8)?, read(16)?, read(24)?);
+        // A corrupted header could overflow the size, which would then not be checked
+        let size = len
+            .checked_add(1)
+            .and_then(|n| n.checked_add(num_items))
+            .and_then(|n| n.checked_mul(8))
+            .and_then(|n| n.checked_add(HEADER))
+            .and_then(|n| n.checked_add(entries.checked_mul(4)?));
+        if size != Some(mmap.len()) {
+            return Err(invalid(
+                "The size of the binary dataset does not match its header",
+            ));
+        }
+        let this = Self {
+            mmap,
+            num_items,
+            len,
+            entries,
+        };
+        let offsets = this.offsets();
+        // The supports are counted here, as the first pass takes them instead of a scan
+        let mut counts = vec![0; num_items];
+        let in_range = this.items().iter().all(|&n| match counts.get_mut(n as usize) {
+            Some(c) => {
+                *c += 1;
+                true
+            }
+            None => false,
+        });
+        if offsets[0] != 0
+            || offsets[len] as usize != entries
+            || offsets.windows(2).any(|w| w[0] > w[1])
+            || !in_range
+            || (0..len).any(|i| this.get(i).windows(2).any(|w| w[0] >= w[1]))
+            || counts != this.frequencies()
+        {
+            return Err(invalid("The binary dataset is corrupted"));
+        }
+        Ok(this)
+    }
+    /// Gets the u64s starting at byte start
+    fn u64s(&self, start: usize, n: usize) -> &[u64] {
+        // Safety: the map is page aligned and start is a multiple of 8,
+        // and open checked that the file is large enough
+        unsafe { slice::from_raw_parts(self.mmap[start..].as_ptr() as *const u64, n) }
+    }
+    /// The support of each item
+    pub fn frequencies(&self) -> &[u64] {
+        self.u64s(HEADER, self.num_items)
+    }
+    /// Where each transaction starts in the items
+    fn offsets(&self) -> &[u64] {
+        self.u64s(HEADER + 8 * self.num_items, self.len + 1)
+    }
+    /// The items of every transaction
+    fn items(&self) -> &[u32] {
+        let start = HEADER + 8 * self.num_items + 8 * (self.len + 1);
+        // Safety: start is a multiple of 4 in a page aligned map of the right size
+        unsafe { slice::from_raw_parts(self.mmap[start..].as_ptr() as *const u32, self.entries) }
+    }
+    /// The number of transactions
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Checks if there are no transactions
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Gets transaction i
+    pub fn get(&self, i: usize) -> &[u32] {
+        let offsets = self.offsets();
+        &self.items()[(offsets[i] as usize)..(offsets[i + 1] as usize)]
+    }
+    /// Iterates over the transactions
+    pub fn iter(&self) -> impl Iterator<Item = &[u32]> + '_ {
+        (0..self.len).map(|i| self.get(i))
+    }
+    /// Copies the transactions into memory
+    pub fn to_transaction_set(&self) -> TransactionSet {
+        let offsets = self.offsets().iter().map(|&n| n as usize).collect();
+        TransactionSet::from_parts(self.items().to_vec(), offsets, self.num_items)
+    }
+}
+impl TransactionSource for BinaryFile {
+    type Item = u32;
+    fn num_items(&self) -> usize {
+        self.num_items
+    }
+    fn num_transactions(&self) -> io::Result<usize> {
+        Ok(self.len)
+    }
+    /// The transactions are passed straight from the map, without copying
+    fn scan_part(&self, i: usize, n: usize, mut f: impl FnMut(&[u32])) -> io::Result<()> {
+        let count = self.len / n;
+        let end = if i == n - 1 {
+            self.len
+        } else {
+            count * (i + 1)
+        };
+        for j in (count * i)..end {
+            f(self.get(j));
+        }
+        Ok(())
+    }
+    fn item_counts(&self) -> Option<Vec<u64>> {
+        Some(self.frequencies().to_vec())
+    }
+}
+/// Writes the transactions as a binary dataset.
+/// The source is scanned twice, so only the offsets are kept in memory.
+pub fn write_binary(source: &impl TransactionSource, path: impl AsRef<Path>) -> io::Result<()> {
+    let mut frequencies = vec![0u64; source.num_items()];
+    let mut offsets = vec![0u64];
+    let mut entries = 0;
+    source.scan(|v| {
+        for &n in v {
+            frequencies[n.index()] += 1;
+        }
+        entries += v.len() as u64;
+        offsets.push(entries);
+    })?;
+    if source.num_items() > u32::MAX as usize + 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The items do not fit in u32",
+        ));
+    }
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(MAGIC)?;
+    for n in [source.num_items() as u64, offsets.len() as u64 - 1, entries] {
+        out.write_all(&n.to_le_bytes())?;
+    }
+    for n in frequencies.iter().chain(&offsets) {
+        out.write_all(&n.to_le_bytes())?;
+    }
+    let mut result = Ok(());
+    source.scan(|v| {
+        for &n in v {
+            if result.is_ok() {
+                result = out.write_all(&(n.index() as u32).to_le_bytes());
+            }
+        }
+    })?;
+    result?;
+    out.flush()
+}
+/// Checks if the file starts with the magic of a binary dataset
+pub fn is_binary(path: impl AsRef<Path>) -> io::Result<bool> {
+    let mut magic = [0; 8];
+    let mut file = File::open(path)?;
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{BinaryFile, is_binary, write_binary};
+    use crate::{transaction_set::TransactionSet, transaction_source::TransactionSource};
+
+    #[test]
+    fn test_binary() {
+        let path = env::temp_dir().join(format!("binary_{}.bin", std::process::id()));
+        let set = TransactionSet::new(vec![vec![0, 1, 4], vec![], vec![1, 3], vec![1, 2, 4]], 5);
+        write_binary(&set, &path).unwrap();
+        assert!(is_binary(&path).unwrap());
+        let file = BinaryFile::open(&path).unwrap();
+        assert_eq!(file.num_items(), 5);
+        assert_eq!(file.len(), 4);
+        assert_eq!(file.frequencies(), &[1, 3, 1, 1, 2]);
+        assert_eq!(file.get(2), &[1, 3]);
+        assert_eq!(file.to_transaction_set(), set);
+        let mut parts = Vec::new();
+        for i in 0..3 {
+            file.scan_part(i, 3, |v| {
+                parts.push(v.iter().map(|&n| n as usize).collect::<Vec<_>>())
+            })
+            .unwrap();
+        }
+        assert_eq!(parts, set.to_vec());
+        // A truncated file is rejected
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..(bytes.len() - 4)]).unwrap();
+        assert!(BinaryFile::open(&path).is_err());
+        // So is a header whose size overflows
+        let mut header = bytes.clone();
+        header[8..16].copy_from_slice(&(u64::MAX / 4).to_le_bytes());
+        fs::write(&path, &header).unwrap();
+        assert!(BinaryFile::open(&path).is_err());
+        // And a transaction that is not sorted, which starts after the supports and offsets
+        let mut unsorted = bytes.clone();
+        unsorted[112..120].copy_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        fs::write(&path, &unsorted).unwrap();
+        assert!(BinaryFile::open(&path).is_err());
+        // And a support that does not match the items
+        let mut support = bytes.clone();
+        support[32..40].copy_from_slice(&2u64.to_le_bytes());
+        fs::write(&path, &support).unwrap();
+        assert!(BinaryFile::open(&path).is_err());
+        fs::write(&path, &bytes).unwrap();
+        assert!(BinaryFile::open(&path).is_ok());
+        fs::remove_file(&path).unwrap();
+    }
+}
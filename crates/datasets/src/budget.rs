@@ -0,0 +1,210 @@
+use std::{
+    fmt::{self, Display},
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// A flag that stops a run when it is set, such as from another thread.
+/// Its clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Tells the runs with this token to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+/// Why a run stopped before it found every itemset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Truncation {
+    /// The cancel token was set
+    Cancelled,
+    /// The run took longer than the time limit
+    TimeLimit,
+    /// A pass had more candidates than the limit
+    Candidates { pass: usize, candidates: usize },
+    /// The limit of itemsets was emitted
+    Itemsets,
+    /// The transactions could not be read again during a pass
+    Read(io::ErrorKind),
+}
+impl Display for Truncation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Truncation::Cancelled => write!(f, "it was cancelled"),
+            Truncation::TimeLimit => write!(f, "it ran out of time"),
+            Truncation::Candidates { pass, candidates } => {
+                write!(f, "pass {pass} had too many candidates ({candidates})")
+            }
+            Truncation::Itemsets => write!(f, "it found the most itemsets allowed"),
+            Truncation::Read(kind) => write!(f, "the transactions could not be read ({kind})"),
+        }
+    }
+}
+/// A scan that failed stops the run, keeping the itemsets of the passes before it
+impl From<io::Error> for Truncation {
+    fn from(e: io::Error) -> Self {
+        Truncation::Read(e.kind())
+    }
+}
+/// Whether a run found every itemset.
+/// The itemsets of a truncated run were still emitted, but there may be more.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Complete,
+    Truncated(Truncation),
+}
+impl Status {
+    pub fn is_complete(&self) -> bool {
+        *self == Status::Complete
+    }
+    /// The error of a run that could not read the transactions.
+    /// A run without limits is never truncated otherwise.
+    pub fn read(self) -> io::Result<()> {
+        match self {
+            Status::Truncated(Truncation::Read(kind)) => Err(kind.into()),
+            _ => Ok(()),
+        }
+    }
+}
+impl From<Result<(), Truncation>> for Status {
+    fn from(value: Result<(), Truncation>) -> Self {
+        match value {
+            Ok(()) => Status::Complete,
+            Err(t) => Status::Truncated(t),
+        }
+    }
+}
+/// The limits of a run, which are checked as it goes.
+/// The default has no limits.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    pub cancel: CancelToken,
+    /// The longest the run may take
+    pub time_limit: Option<Duration>,
+    /// The most candidates a pass may count
+    pub max_candidates: Option<usize>,
+    /// The most itemsets the run may emit
+    pub max_itemsets: Option<usize>,
+}
+impl Budget {
+    /// Starts the clock of a run
+    pub fn start(&self) -> Limiter<'_> {
+        Limiter {
+            budget: self,
+            start: Instant::now(),
+            itemsets: 0,
+        }
+    }
+}
+/// Checks a run against its budget
+#[derive(Debug)]
+pub struct Limiter<'a> {
+    budget: &'a Budget,
+    start: Instant,
+    /// The number of itemsets emitted so far
+    itemsets: usize,
+}
+impl Limiter<'_> {
+    /// Checks whether the run was cancelled or is out of time
+    pub fn check(&self) -> Result<(), Truncation> {
+        if self.budget.cancel.is_cancelled() {
+            return Err(Truncation::Cancelled);
+        }
+        if self
+            .budget
+            .time_limit
+            .is_some_and(|t| self.start.elapsed() > t)
+        {
+            return Err(Truncation::TimeLimit);
+        }
+        Ok(())
+    }
+    /// Checks the number of candidates of a pass, before they are counted
+    pub fn candidates(&self, pass: usize, candidates: usize) -> Result<(), Truncation> {
+        if self.budget.max_candidates.is_some_and(|m| candidates > m) {
+            return Err(Truncation::Candidates { pass, candidates });
+        }
+        self.check()
+    }
+    /// Counts an itemset before it is emitted, failing if the limit was already emitted
+    pub fn emit(&mut self) -> Result<(), Truncation> {
+        if self.budget.max_itemsets.is_some_and(|m| self.itemsets >= m) {
+            return Err(Truncation::Itemsets);
+        }
+        self.itemsets += 1;
+        Ok(())
+    }
+    /// Counts the itemsets a pass found, for the runs that emit them once they are done.
+    /// Fails once more than the limit were found, so the run may return more than the limit.
+    pub fn found(&mut self, n: usize) -> Result<(), Truncation> {
+        self.itemsets += n;
+        if self.budget.max_itemsets.is_some_and(|m| self.itemsets > m) {
+            return Err(Truncation::Itemsets);
+        }
+        Ok(())
+    }
+    /// Emits each itemset with its support while the limit allows
+    pub fn emit_all<'v, 'n>(
+        &mut self,
+        v: impl IntoIterator<Item = (&'v Vec<usize>, &'n u64)>,
+        mut f: impl FnMut(&[usize], u64),
+    ) -> Result<(), Truncation> {
+        for (v, &n) in v {
+            self.emit()?;
+            f(v, n);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Budget, Status, Truncation};
+
+    #[test]
+    fn test_budget() {
+        let budget = Budget {
+            max_candidates: Some(10),
+            max_itemsets: Some(2),
+            ..Default::default()
+        };
+        let mut limiter = budget.start();
+        assert_eq!(limiter.candidates(2, 10), Ok(()));
+        assert_eq!(
+            limiter.candidates(3, 11),
+            Err(Truncation::Candidates {
+                pass: 3,
+                candidates: 11
+            })
+        );
+        let mut found = Vec::new();
+        let v = [(vec![0], 3), (vec![1], 2), (vec![2], 2)];
+        let v = v.iter().map(|(v, n)| (v, n));
+        let status = Status::from(limiter.emit_all(v, |v, _| found.push(v.to_vec())));
+        assert_eq!(status, Status::Truncated(Truncation::Itemsets));
+        assert_eq!(found, vec![vec![0], vec![1]]);
+        budget.cancel.cancel();
+        assert_eq!(limiter.check(), Err(Truncation::Cancelled));
+        let budget = Budget {
+            time_limit: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let limiter = budget.start();
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(limiter.check(), Err(Truncation::TimeLimit));
+        assert!(Budget::default().start().check().is_ok());
+    }
+}
@@ -0,0 +1,369 @@
+use std::{fs, io, path::Path, str::FromStr};
+
+/// The lengths of the itemsets a run finds, from min to max inclusive.
+/// The default allows every length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lengths {
+    pub min: usize,
+    pub max: usize,
+}
+impl Default for Lengths {
+    fn default() -> Self {
+        Self {
+            min: 1,
+            max: usize::MAX,
+        }
+    }
+}
+impl Lengths {
+    /// Constructor, with no bound for a length that is not given
+    pub fn new(min: Option<usize>, max: Option<usize>) -> Self {
+        Self {
+            min: min.unwrap_or(1),
+            max: max.unwrap_or(usize::MAX),
+        }
+    }
+    /// Whether itemsets of length n are found
+    pub fn contains(&self, n: usize) -> bool {
+        (self.min..=self.max).contains(&n)
+    }
+    /// Whether itemsets longer than n can be found,
+    /// so a search should go past itemsets of length n
+    pub fn extends(&self, n: usize) -> bool {
+        n < self.max
+    }
+}
+/// The items that the itemsets a run finds must or must not have.
+/// The default has no constraints.
+///
+/// Excluding items (or allowing only some) is anti-monotone, so those items are dropped
+/// before mining. Including items and having at least one of a set are not, so the miners
+/// join the included items first, then the items of the set, then the rest.
+/// An itemset is only extended by items after its own in that order,
+/// so the itemsets that can never satisfy the constraints are not generated.
+///
+/// Bounds on the values of the items are pushed the same way when they are anti-monotone
+/// (like `sum<=B` with no negative values), since no superset of an itemset over them is
+/// within them. The rest are only checked on the itemsets found.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ItemConstraints {
+    /// The items every itemset has, sorted
+    include: Vec<usize>,
+    /// The items no itemset has, sorted
+    exclude: Vec<usize>,
+    /// The only items an itemset may have (sorted), or None for any
+    allowed: Option<Vec<usize>>,
+    /// Every itemset has at least one of these items (sorted), unless there are none
+    any_of: Vec<usize>,
+    /// The value of each item, which the bounds are on
+    values: ItemValues,
+    /// The bounds on the values of the itemsets
+    bounds: Vec<ValueBound>,
+    /// The bounds that are anti-monotone
+    anti_monotone: Vec<ValueBound>,
+}
+impl ItemConstraints {
+    /// No constraints, for the miners that borrow them
+    pub const NONE: Self = Self {
+        include: Vec::new(),
+        exclude: Vec::new(),
+        allowed: None,
+        any_of: Vec::new(),
+        values: ItemValues(Vec::new()),
+        bounds: Vec::new(),
+        anti_monotone: Vec::new(),
+    };
+    /// Constructor, with an empty list for a constraint that is not used
+    pub fn new(
+        include: Vec<usize>,
+        exclude: Vec<usize>,
+        allowed: Option<Vec<usize>>,
+        any_of: Vec<usize>,
+    ) -> Self {
+        let sorted = |mut v: Vec<usize>| {
+            v.sort_unstable();
+            v.dedup();
+            v
+        };
+        Self {
+            include: sorted(include),
+            exclude: sorted(exclude),
+            allowed: allowed.map(sorted),
+            any_of: sorted(any_of),
+            ..Self::NONE
+        }
+    }
+    /// Also only finds the itemsets whose values are within the bounds
+    pub fn bounds(mut self, values: ItemValues, bounds: Vec<ValueBound>) -> Self {
+        self.anti_monotone = bounds
+            .iter()
+            .filter(|b| b.anti_monotone(&values))
+            .copied()
+            .collect();
+        self.values = values;
+        self.bounds = bounds;
+        self
+    }
+    /// Whether every itemset satisfies the constraints
+    pub fn is_empty(&self) -> bool {
+        *self == Self::NONE
+    }
+    /// The items every itemset has, sorted
+    pub fn include(&self) -> &[usize] {
+        &self.include
+    }
+    /// Whether an itemset may have the item
+    pub fn allows(&self, item: usize) -> bool {
+        self.exclude.binary_search(&item).is_err()
+            && self
+                .allowed
+                .as_ref()
+                .is_none_or(|a| a.binary_search(&item).is_ok())
+    }
+    /// Whether the item is one of the set that every itemset has one of
+    pub fn is_any_of(&self, item: usize) -> bool {
+        self.any_of.binary_search(&item).is_ok()
+    }
+    /// Whether itemsets need an item of the set, even with the included items
+    pub fn needs_any_of(&self) -> bool {
+        !self.any_of.is_empty() && !self.include.iter().any(|&i| self.is_any_of(i))
+    }
+    /// Whether the itemset satisfies the constraints
+    pub fn satisfied(&self, v: &[usize]) -> bool {
+        v.iter().all(|&i| self.allows(i))
+            && self.include.iter().all(|i| v.contains(i))
+            && (self.any_of.is_empty() || v.iter().any(|&i| self.is_any_of(i)))
+            && self.bounds.iter().all(|b| b.satisfied(&self.values, v))
+    }
+    /// Whether the itemset is within the anti-monotone bounds,
+    /// which none of its supersets are if it is not
+    pub fn admits(&self, v: &[usize]) -> bool {
+        self.anti_monotone
+            .iter()
+            .all(|b| b.satisfied(&self.values, v))
+    }
+    /// The value of the item
+    pub fn value(&self, item: usize) -> f64 {
+        self.values.get(item)
+    }
+    /// The order of the values that the items of an itemset should be added in
+    /// for its convertible bounds to be pushed, from the first of them:
+    /// descending for a lower bound on the average, and ascending for an upper bound
+    pub fn descending(&self) -> Option<bool> {
+        let b = self.bounds.iter().find(|b| b.aggregate == Aggregate::Avg)?;
+        Some(b.at_least)
+    }
+    /// Whether an itemset with the included items and grown, extended only by items
+    /// with values between floor and ceiling, can be within the bounds on the average.
+    /// Adding an item moves the average toward its value, so no extension has an average
+    /// past both the average and the furthest value that can be added.
+    pub fn converts(&self, grown: &[usize], floor: f64, ceiling: f64) -> bool {
+        let n = (self.include.len() + grown.len()) as f64;
+        let sum: f64 = self.include.iter().chain(grown).map(|&i| self.value(i)).sum();
+        let avg = sum / n;
+        self.bounds
+            .iter()
+            .filter(|b| b.aggregate == Aggregate::Avg)
+            .all(|b| {
+                if b.at_least {
+                    avg.max(ceiling) >= b.bound
+                } else {
+                    avg.min(floor) <= b.bound
+                }
+            })
+    }
+    /// Where the item is joined: the included items, then the set, then the rest
+    fn rank(&self, item: usize) -> (u8, usize) {
+        if self.include.binary_search(&item).is_ok() {
+            (0, item)
+        } else if self.is_any_of(item) {
+            (1, item)
+        } else {
+            (2, item)
+        }
+    }
+    /// Whether a comes before b in the order items are joined in
+    pub fn before(&self, a: usize, b: usize) -> bool {
+        self.rank(a) < self.rank(b)
+    }
+    /// Copies the itemset in the order items are joined in
+    pub fn ordered(&self, v: &[usize]) -> Vec<usize> {
+        let mut v = v.to_vec();
+        v.sort_unstable_by_key(|&i| self.rank(i));
+        v
+    }
+    /// Whether the itemset (in the order items are joined in) can satisfy the constraints
+    /// once it is extended by the items after its own
+    fn extendable(&self, v: &[usize]) -> bool {
+        let n = v.len().min(self.include.len());
+        if v[..n] != self.include[..n] {
+            return false;
+        }
+        !self.needs_any_of()
+            || v.iter().any(|&i| self.is_any_of(i))
+            || v.last().is_none_or(|&i| self.rank(i).0 == 0)
+    }
+    /// Whether the itemset is kept to be joined.
+    /// These are the itemsets within the anti-monotone bounds that without their last item
+    /// (in the order items are joined in) can satisfy the constraints,
+    /// since the joins of those need them.
+    pub fn keeps(&self, v: &[usize]) -> bool {
+        let ordered = self.ordered(v);
+        self.extendable(&ordered[..ordered.len().saturating_sub(1)]) && self.admits(v)
+    }
+}
+/// The value of each item, such as its price
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ItemValues(Vec<f64>);
+impl ItemValues {
+    /// Constructor, with the value of item i at i
+    pub fn new(values: Vec<f64>) -> Self {
+        Self(values)
+    }
+    /// Reads the values from a file with line i being the value of item i
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let values = fs::read_to_string(path)?
+            .lines()
+            .map(|s| s.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self(values))
+    }
+    /// The value of the item, with the items past the table being worth 0
+    pub fn get(&self, item: usize) -> f64 {
+        self.0.get(item).copied().unwrap_or(0.0)
+    }
+}
+/// How the values of the items of an itemset are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+/// A bound on the combined values of the items of an itemset, like `sum<=100`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValueBound {
+    pub aggregate: Aggregate,
+    /// Whether the bound is a lower bound instead of an upper bound
+    pub at_least: bool,
+    pub bound: f64,
+}
+impl ValueBound {
+    /// The combined value of the nonempty itemset
+    pub fn of(&self, values: &ItemValues, v: &[usize]) -> f64 {
+        let v = v.iter().map(|&i| values.get(i));
+        match self.aggregate {
+            Aggregate::Sum => v.sum(),
+            Aggregate::Avg => {
+                let n = v.len() as f64;
+                v.sum::<f64>() / n
+            }
+            Aggregate::Min => v.fold(f64::INFINITY, f64::min),
+            Aggregate::Max => v.fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+    /// Whether the itemset is within the bound
+    pub fn satisfied(&self, values: &ItemValues, v: &[usize]) -> bool {
+        let n = self.of(values, v);
+        if self.at_least {
+            n >= self.bound
+        } else {
+            n <= self.bound
+        }
+    }
+    /// Whether every subset of an itemset within the bound is also within it.
+    /// A sum only grows with more items when none of the values are negative.
+    pub fn anti_monotone(&self, values: &ItemValues) -> bool {
+        match (self.aggregate, self.at_least) {
+            (Aggregate::Sum, false) => values.0.iter().all(|&n| n >= 0.0),
+            (Aggregate::Min, true) | (Aggregate::Max, false) => true,
+            _ => false,
+        }
+    }
+}
+impl FromStr for ValueBound {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (aggregate, at_least, bound) = if let Some((a, b)) = s.split_once(">=") {
+            (a, true, b)
+        } else if let Some((a, b)) = s.split_once("<=") {
+            (a, false, b)
+        } else {
+            return Err(format!("{s} has no >= or <="));
+        };
+        let aggregate = match aggregate.trim() {
+            "sum" => Aggregate::Sum,
+            "avg" => Aggregate::Avg,
+            "min" => Aggregate::Min,
+            "max" => Aggregate::Max,
+            a => return Err(format!("{a} is not sum, avg, min, or max")),
+        };
+        let bound = bound.trim().parse().map_err(|e| format!("{bound}: {e}"))?;
+        Ok(Self {
+            aggregate,
+            at_least,
+            bound,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Aggregate, ItemConstraints, ItemValues, Lengths, ValueBound};
+
+    #[test]
+    fn test_lengths() {
+        let all = Lengths::default();
+        assert!(all.contains(1) && all.contains(100) && !all.contains(0));
+        assert!(all.extends(100));
+        let pairs = Lengths::new(Some(2), Some(3));
+        assert!(!pairs.contains(1) && pairs.contains(2) && pairs.contains(3));
+        assert!(!pairs.contains(4));
+        assert!(pairs.extends(2) && !pairs.extends(3));
+    }
+    #[test]
+    fn test_item_constraints() {
+        assert!(ItemConstraints::default().is_empty());
+        assert!(ItemConstraints::default().keeps(&[3, 1]));
+        let c = ItemConstraints::new(vec![5, 2], vec![7], None, vec![4, 6]);
+        assert!(!c.is_empty());
+        assert_eq!(c.include(), [2, 5]);
+        assert!(c.allows(1) && !c.allows(7));
+        assert!(c.satisfied(&[2, 4, 5]) && c.satisfied(&[1, 2, 5, 6]));
+        assert!(!c.satisfied(&[2, 5]) && !c.satisfied(&[2, 4]) && !c.satisfied(&[2, 4, 5, 7]));
+        // Included, then the set, then the rest
+        assert_eq!(c.ordered(&[1, 2, 4, 5]), vec![2, 5, 4, 1]);
+        assert!(c.before(5, 1) && c.before(6, 0) && !c.before(3, 2));
+        // The prefix of an itemset that is kept has the included items first
+        assert!(c.keeps(&[2]) && c.keeps(&[2, 9]) && c.keeps(&[2, 5, 9]));
+        assert!(!c.keeps(&[5, 9]) && !c.keeps(&[2, 8, 9]));
+        // [2, 5, 9] is kept to join with [2, 5, 4], but [2, 5, 8, 9] can not get an item of the set
+        assert!(c.keeps(&[2, 4, 5, 9]) && !c.keeps(&[2, 5, 8, 9]));
+        let allowed = ItemConstraints::new(vec![], vec![], Some(vec![1, 3]), vec![]);
+        assert!(allowed.allows(3) && !allowed.allows(2));
+        assert!(!allowed.needs_any_of());
+    }
+    #[test]
+    fn test_value_bounds() {
+        let values = ItemValues::new(vec![1.0, 4.0, 10.0]);
+        let bound: ValueBound = "avg >= 5".parse().unwrap();
+        assert_eq!((bound.aggregate, bound.at_least, bound.bound), (Aggregate::Avg, true, 5.0));
+        assert!(bound.satisfied(&values, &[0, 1, 2]) && !bound.satisfied(&values, &[0, 1]));
+        assert!("median<=3".parse::<ValueBound>().is_err());
+        assert!("sum=3".parse::<ValueBound>().is_err());
+        let sum: ValueBound = "sum<=11".parse().unwrap();
+        assert!(sum.anti_monotone(&values) && !bound.anti_monotone(&values));
+        assert!(!sum.anti_monotone(&ItemValues::new(vec![-1.0])));
+        let c = ItemConstraints::default().bounds(values, vec![sum, bound]);
+        assert!(!c.is_empty());
+        // {0, 2} is within both bounds, but {0, 1, 2} is over the sum
+        assert!(c.satisfied(&[0, 2]) && !c.satisfied(&[0]) && !c.satisfied(&[0, 1, 2]));
+        assert!(c.keeps(&[0]) && c.keeps(&[0, 1]) && !c.keeps(&[1, 2]));
+        assert_eq!(c.descending(), Some(true));
+        // {0} averages 1, but adding 10 could reach 5
+        assert!(c.converts(&[0], 0.0, 10.0) && !c.converts(&[0], 0.0, 4.0));
+    }
+}
@@ -1,2 +1,9 @@
+pub mod binary;
+pub mod budget;
+pub mod constraint;
+pub mod quest;
+pub mod random;
+pub mod taxonomy;
 pub mod transaction_set;
+pub mod transaction_source;
 pub mod utils;
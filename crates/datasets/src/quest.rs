@@ -0,0 +1,194 @@
+use crate::{random::Random, transaction_set::TransactionSet};
+
+/// The parameters of the synthetic data generator from "Fast Algorithms for Mining
+/// Association Rules" (Agrawal and Srikant), which the IBM Quest generator implements.
+/// A dataset named T10I4D100K has avg_len 10, avg_pattern_len 4, and 100,000 transactions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuestParams {
+    /// The number of transactions (|D|)
+    pub transactions: usize,
+    /// The average size of a transaction (|T|)
+    pub avg_len: f64,
+    /// The average size of the potentially large itemsets (|I|)
+    pub avg_pattern_len: f64,
+    /// The number of potentially large itemsets (|L|)
+    pub patterns: usize,
+    /// The number of items (N)
+    pub items: usize,
+    /// The mean fraction of each pattern's items that are taken from the pattern before it
+    pub correlation: f64,
+    /// The mean chance that an item is dropped from a pattern when it is put in a transaction
+    pub corruption: f64,
+    pub seed: u64,
+}
+impl Default for QuestParams {
+    /// The defaults from the paper, with T10I4D100K
+    fn default() -> Self {
+        Self {
+            transactions: 100_000,
+            avg_len: 10.0,
+            avg_pattern_len: 4.0,
+            patterns: 2000,
+            items: 1000,
+            correlation: 0.5,
+            corruption: 0.5,
+            seed: 0,
+        }
+    }
+}
+/// A potentially large itemset
+#[derive(Debug)]
+struct Pattern {
+    items: Vec<usize>,
+    /// The chance of dropping each item when it is put in a transaction
+    corruption: f64,
+}
+/// Generates the transactions one at a time, so any number can be written without keeping them
+#[derive(Debug)]
+pub struct Quest {
+    params: QuestParams,
+    random: Random,
+    patterns: Vec<Pattern>,
+    /// The sums of the weights of the patterns up to each one, which end at 1
+    weights: Vec<f64>,
+    /// A pattern that did not fit in the last transaction, which starts the next one
+    carry: Option<Vec<usize>>,
+    /// The number of transactions generated so far
+    generated: usize,
+}
+impl Quest {
+    /// Picks the potentially large itemsets.
+    /// Panics if there are no items or patterns.
+    pub fn new(params: QuestParams) -> Self {
+        assert!(params.items > 0, "There must be at least one item");
+        assert!(params.patterns > 0, "There must be at least one pattern");
+        let mut random = Random::new(params.seed);
+        let mut patterns: Vec<Pattern> = Vec::with_capacity(params.patterns);
+        let mut weights = Vec::with_capacity(params.patterns);
+        let mut total = 0.0;
+        for _ in 0..params.patterns {
+            let len = random
+                .poisson(params.avg_pattern_len)
+                .clamp(1, params.items);
+            let mut items = Vec::with_capacity(len);
+            // Some of the items come from the pattern before, so patterns share items
+            if let Some(prev) = patterns.last() {
+                let fraction = random.exponential(params.correlation).min(1.0);
+                let shared = ((fraction * len as f64).round() as usize).min(prev.items.len());
+                let mut prev = prev.items.clone();
+                for _ in 0..shared {
+                    items.push(prev.swap_remove(random.below(prev.len())));
+                }
+            }
+            while items.len() < len {
+                let n = random.below(params.items);
+                if !items.contains(&n) {
+                    items.push(n);
+                }
+            }
+            total += random.exponential(1.0);
+            weights.push(total);
+            let corruption = random.normal(params.corruption, 0.1).clamp(0.0, 1.0);
+            patterns.push(Pattern { items, corruption });
+        }
+        weights.iter_mut().for_each(|w| *w /= total);
+        Self {
+            params,
+            random,
+            patterns,
+            weights,
+            carry: None,
+            generated: 0,
+        }
+    }
+    /// Picks a pattern by its weight, and drops items from it by its corruption
+    fn pick(&mut self) -> Vec<usize> {
+        let r = self.random.uniform();
+        let i = self
+            .weights
+            .partition_point(|&w| w <= r)
+            .min(self.patterns.len() - 1);
+        let pattern = &self.patterns[i];
+        let mut items = pattern.items.clone();
+        while !items.is_empty() && self.random.uniform() < pattern.corruption {
+            items.swap_remove(self.random.below(items.len()));
+        }
+        items
+    }
+    /// Generates every transaction into a set
+    pub fn generate(params: QuestParams) -> TransactionSet {
+        let mut set = TransactionSet::with_capacity(
+            params.transactions,
+            (params.transactions as f64 * params.avg_len) as usize,
+            params.items,
+        );
+        for t in Self::new(params) {
+            set.push(&t);
+        }
+        set
+    }
+}
+impl Iterator for Quest {
+    type Item = Vec<usize>;
+
+    /// The next transaction, sorted
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.generated == self.params.transactions {
+            return None;
+        }
+        self.generated += 1;
+        let len = self.random.poisson(self.params.avg_len).max(1);
+        let mut t: Vec<usize> = Vec::with_capacity(len);
+        let mut attempts = 0;
+        while t.len() < len && attempts < 8 * len {
+            attempts += 1;
+            let items = match self.carry.take() {
+                Some(items) => items,
+                None => self.pick(),
+            };
+            let new = items.iter().filter(|n| !t.contains(n)).count();
+            // A pattern that does not fit is still added half the time,
+            // and otherwise moved to the next transaction
+            if !t.is_empty() && t.len() + new > len && self.random.uniform() < 0.5 {
+                self.carry = Some(items);
+                break;
+            }
+            for n in items {
+                if !t.contains(&n) {
+                    t.push(n);
+                }
+            }
+        }
+        t.sort_unstable();
+        Some(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Quest, QuestParams};
+
+    #[test]
+    fn test_quest() {
+        let params = QuestParams {
+            transactions: 2000,
+            items: 100,
+            patterns: 50,
+            seed: 7,
+            ..Default::default()
+        };
+        let a: Vec<_> = Quest::new(params).collect();
+        assert_eq!(a.len(), 2000);
+        // The same seed gives the same transactions
+        assert_eq!(a, Quest::new(params).collect::<Vec<_>>());
+        assert_ne!(
+            a,
+            Quest::new(QuestParams { seed: 8, ..params }).collect::<Vec<_>>()
+        );
+        assert!(a.iter().all(|t| t.windows(2).all(|w| w[0] < w[1])));
+        assert!(a.iter().flatten().all(|&n| n < 100));
+        let avg = a.iter().map(|t| t.len()).sum::<usize>() as f64 / a.len() as f64;
+        assert!((8.0..12.0).contains(&avg), "{avg}");
+        assert_eq!(Quest::generate(params).len(), 2000);
+    }
+}
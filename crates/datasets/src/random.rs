@@ -0,0 +1,51 @@
+/// A small seeded random number generator (SplitMix64),
+/// so that a seed gives the same dataset on every platform and version
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+    /// A uniform number in [0, 1)
+    pub fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    /// A uniform number in [0, n)
+    pub fn below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+    /// An exponentially distributed number with the mean
+    pub fn exponential(&mut self, mean: f64) -> f64 {
+        -mean * (1.0 - self.uniform()).ln()
+    }
+    /// A normally distributed number (with the Box-Muller transform)
+    pub fn normal(&mut self, mean: f64, variance: f64) -> f64 {
+        let (u, v) = (1.0 - self.uniform(), self.uniform());
+        mean + variance.sqrt() * (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+    /// A Poisson distributed number with the mean
+    pub fn poisson(&mut self, mean: f64) -> usize {
+        if mean > 30.0 {
+            // exp(-mean) gets too small, so the normal approximation is used
+            return self.normal(mean, mean).round().max(0.0) as usize;
+        }
+        // Multiplies uniform numbers until the product is below exp(-mean)
+        let limit = (-mean).exp();
+        let mut product = self.uniform();
+        let mut n = 0;
+        while product > limit {
+            product *= self.uniform();
+            n += 1;
+        }
+        n
+    }
+}
@@ -0,0 +1,209 @@
+use std::{fs, io, path::Path};
+
+use crate::{transaction_source::TransactionSource, utils::Item};
+
+/// A hierarchy of the items, such as products in subcategories in categories.
+/// An item can have more than one parent, as long as no item is its own ancestor.
+/// The items with no parent (including those not in the taxonomy) are at level 0,
+/// and every other item is one level below its deepest parent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Taxonomy {
+    /// The ancestors of each item, sorted
+    ancestors: Vec<Vec<usize>>,
+    /// The level of each item
+    levels: Vec<usize>,
+}
+impl Taxonomy {
+    /// Constructor from (child, parent) pairs.
+    /// Errors with an item that is its own ancestor, or that is too large to be an item.
+    pub fn new(parents: &[(usize, usize)]) -> Result<Self, String> {
+        let mut num_items = 0;
+        for &(c, p) in parents {
+            // The items of the datasets are u32
+            let n = c.max(p);
+            let end = u32::try_from(n).ok().and_then(|_| n.checked_add(1));
+            let end = end.ok_or_else(|| format!("Item {n} is too large to be an item"))?;
+            num_items = num_items.max(end);
+        }
+        let mut direct = vec![Vec::new(); num_items];
+        for &(c, p) in parents {
+            direct[c].push(p);
+        }
+        let mut this = Self {
+            ancestors: vec![Vec::new(); num_items],
+            levels: vec![0; num_items],
+        };
+        // Whether the ancestors of each item are done, or None while they are being found
+        let mut done = vec![Some(false); num_items];
+        // The items whose ancestors are being found, each with the next parent to visit
+        let mut stack = Vec::new();
+        for i in 0..num_items {
+            if done[i] == Some(false) {
+                done[i] = None;
+                stack.push((i, 0));
+            }
+            while let Some((c, k)) = stack.last_mut() {
+                let Some(&p) = direct[*c].get(*k) else {
+                    // The ancestors of its parents are found
+                    this.add(*c, &direct[*c]);
+                    done[*c] = Some(true);
+                    stack.pop();
+                    continue;
+                };
+                *k += 1;
+                match done[p] {
+                    Some(true) => {}
+                    None => return Err(format!("Item {p} is its own ancestor")),
+                    Some(false) => {
+                        done[p] = None;
+                        stack.push((p, 0));
+                    }
+                }
+            }
+        }
+        Ok(this)
+    }
+    /// Finds the ancestors and level of item i from those of its parents
+    fn add(&mut self, i: usize, parents: &[usize]) {
+        let mut ancestors = Vec::new();
+        for &p in parents {
+            ancestors.push(p);
+            ancestors.extend_from_slice(&self.ancestors[p]);
+            self.levels[i] = self.levels[i].max(self.levels[p] + 1);
+        }
+        ancestors.sort_unstable();
+        ancestors.dedup();
+        self.ancestors[i] = ancestors;
+    }
+    /// Reads the taxonomy from a file with a child and its parent on each line,
+    /// separated by whitespace or a comma
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+        let mut parents = Vec::new();
+        for line in fs::read_to_string(path)?.lines() {
+            let mut ids = line
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.parse::<usize>().map_err(|e| invalid(e.to_string())));
+            match (ids.next(), ids.next(), ids.next()) {
+                (None, _, _) => continue,
+                (Some(c), Some(p), None) => parents.push((c?, p?)),
+                _ => return Err(invalid(format!("Expected a child and a parent: {line}"))),
+            }
+        }
+        Self::new(&parents).map_err(invalid)
+    }
+    /// One more than the largest item in the taxonomy
+    pub fn num_items(&self) -> usize {
+        self.levels.len()
+    }
+    /// The ancestors of the item, sorted
+    pub fn ancestors(&self, item: usize) -> &[usize] {
+        self.ancestors.get(item).map_or(&[], |v| v)
+    }
+    /// The level of the item, with 0 for the items with no parent
+    pub fn level(&self, item: usize) -> usize {
+        self.levels.get(item).copied().unwrap_or(0)
+    }
+    /// Whether a is an ancestor of b
+    pub fn is_ancestor(&self, a: usize, b: usize) -> bool {
+        self.ancestors(b).binary_search(&a).is_ok()
+    }
+    /// Whether the itemset has an item and one of its ancestors
+    pub fn has_ancestor(&self, v: &[usize]) -> bool {
+        v.iter().any(|&b| v.iter().any(|&a| self.is_ancestor(a, b)))
+    }
+    /// The transactions of the source with the ancestors of their items added
+    pub fn extend<'a, T>(&'a self, source: &'a T) -> Extended<'a, T> {
+        Extended {
+            source,
+            taxonomy: self,
+            kept: None,
+        }
+    }
+}
+/// A source whose transactions also have the ancestors of their items
+#[derive(Debug)]
+pub struct Extended<'a, T> {
+    source: &'a T,
+    taxonomy: &'a Taxonomy,
+    /// The ancestors that are added, or None for all of them
+    kept: Option<Vec<bool>>,
+}
+impl<T> Extended<'_, T> {
+    /// Only adds the ancestors that are kept (by id), since the others are in no candidate
+    pub fn keep(&mut self, kept: Vec<bool>) {
+        self.kept = Some(kept);
+    }
+}
+impl<T: TransactionSource> TransactionSource for Extended<'_, T> {
+    type Item = usize;
+    fn num_items(&self) -> usize {
+        self.source.num_items().max(self.taxonomy.num_items())
+    }
+    fn scan_part(&self, i: usize, n: usize, mut f: impl FnMut(&[usize])) -> io::Result<()> {
+        let kept = |&a: &usize| self.kept.as_ref().is_none_or(|k| k.get(a) == Some(&true));
+        let mut buffer = Vec::new();
+        self.source.scan_part(i, n, |t| {
+            buffer.clear();
+            buffer.extend(t.iter().map(|n| n.index()));
+            for item in t {
+                buffer.extend(self.taxonomy.ancestors(item.index()).iter().filter(|a| kept(a)));
+            }
+            buffer.sort_unstable();
+            buffer.dedup();
+            f(&buffer);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{transaction_set::TransactionSet, transaction_source::TransactionSource};
+
+    use super::Taxonomy;
+
+    #[test]
+    fn test_taxonomy() {
+        // 0 and 1 are in 3, 2 is in 4, and 3 and 4 are in 5
+        let taxonomy = Taxonomy::new(&[(0, 3), (1, 3), (2, 4), (3, 5), (4, 5)]).unwrap();
+        assert_eq!(taxonomy.ancestors(0), [3, 5]);
+        assert_eq!(taxonomy.ancestors(5), [] as [usize; 0]);
+        assert_eq!(taxonomy.ancestors(9), [] as [usize; 0]);
+        assert_eq!(
+            (0..7).map(|i| taxonomy.level(i)).collect::<Vec<_>>(),
+            [2, 2, 2, 1, 1, 0, 0]
+        );
+        assert!(taxonomy.is_ancestor(5, 2));
+        assert!(!taxonomy.is_ancestor(2, 5));
+        assert!(taxonomy.has_ancestor(&[1, 4, 5]));
+        assert!(!taxonomy.has_ancestor(&[1, 4]));
+        // An item is a level below its deepest parent
+        let dag = Taxonomy::new(&[(0, 1), (1, 2), (0, 2)]).unwrap();
+        assert_eq!(dag.ancestors(0), [1, 2]);
+        assert_eq!(dag.level(0), 2);
+        assert!(Taxonomy::new(&[(0, 1), (1, 2), (2, 0)]).is_err());
+        assert!(Taxonomy::new(&[(0, 0)]).is_err());
+        assert!(Taxonomy::new(&[(0, usize::MAX)]).is_err());
+        assert!(Taxonomy::new(&[(u32::MAX as usize + 1, 0)]).is_err());
+        // A long chain is not visited recursively, so it fits in a small stack
+        let chain = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(|| {
+                let parents: Vec<_> = (0..2000).map(|i| (i, i + 1)).collect();
+                Taxonomy::new(&parents).unwrap().level(0)
+            })
+            .unwrap();
+        assert_eq!(chain.join().unwrap(), 2000);
+
+        let data = TransactionSet::new(vec![vec![0, 2], vec![1, 3], vec![6]], 7);
+        let mut extended = taxonomy.extend(&data);
+        let mut found = Vec::new();
+        extended.scan(|t| found.push(t.to_vec())).unwrap();
+        assert_eq!(found, [vec![0, 2, 3, 4, 5], vec![1, 3, 5], vec![6]]);
+        extended.keep(vec![false, false, false, true]);
+        found.clear();
+        extended.scan(|t| found.push(t.to_vec())).unwrap();
+        assert_eq!(found, [vec![0, 2, 3], vec![1, 3], vec![6]]);
+    }
+}
@@ -1,54 +1,198 @@
-use std::{fs::File, io::{BufRead, BufReader}, ops::{Deref, DerefMut}};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    ops::Range,
+    path::Path,
+};
+
+use crate::binary::{BinaryFile, is_binary};
 
 /// A 0-indexed item set
-/// A Transactional Database
-#[derive(Debug, Default)]
+/// A Transactional Database.
+/// The items of every transaction are stored one after another as u32,
+/// with where each transaction starts, so there is one allocation for the whole set.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TransactionSet {
-    pub transactions: Vec<Vec<usize>>,
-    pub num_items: usize
-}
-// Dereferences to the underlying Vector
-impl Deref for TransactionSet {
-    type Target = Vec<Vec<usize>>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.transactions
-    }
-}
-impl DerefMut for TransactionSet {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.transactions
-    }
+    /// The items of every transaction
+    items: Vec<u32>,
+    /// Where each transaction starts in the items, with one more for the end
+    offsets: Vec<usize>,
+    pub num_items: usize,
 }
 
 impl TransactionSet {
     /// Constructor
     pub fn new(transactions: Vec<Vec<usize>>, num_items: usize) -> Self {
-        Self { transactions, num_items }
+        let mut set = Self::with_capacity(
+            transactions.len(),
+            transactions.iter().map(|v| v.len()).sum(),
+            num_items,
+        );
+        for t in &transactions {
+            set.push(t);
+        }
+        set
+    }
+    /// An empty set with room for len transactions of entries items in total
+    pub fn with_capacity(len: usize, entries: usize, num_items: usize) -> Self {
+        let mut offsets = Vec::with_capacity(len + 1);
+        offsets.push(0);
+        Self {
+            items: Vec::with_capacity(entries),
+            offsets,
+            num_items,
+        }
+    }
+    /// Constructs the set from the items of every transaction and where each starts
+    pub(crate) fn from_parts(items: Vec<u32>, offsets: Vec<usize>, num_items: usize) -> Self {
+        Self {
+            items,
+            offsets,
+            num_items,
+        }
+    }
+    /// Adds a sorted transaction
+    pub fn push(&mut self, t: &[usize]) {
+        assert!(
+            t.iter().all(|&n| n < self.num_items),
+            "The items must be less than num_items"
+        );
+        // The ids are stored as u32, so a larger one would be changed into another item
+        self.items.extend(
+            t.iter()
+                .map(|&n| u32::try_from(n).expect("The items must fit in u32")),
+        );
+        self.offsets.push(self.items.len());
+    }
+    /// The number of transactions
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+    /// Checks if there are no transactions
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Gets transaction i
+    pub fn get(&self, i: usize) -> &[u32] {
+        &self.items[self.offsets[i]..self.offsets[i + 1]]
     }
     /// Iterates over all the transactions
-    pub fn iter(&self) -> impl Iterator<Item = &Vec<usize>> {
-        self.transactions.iter()
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &[u32]> + Clone + '_ {
+        self.offsets.windows(2).map(|w| &self.items[w[0]..w[1]])
+    }
+    /// Iterates over the transactions in the range
+    pub fn range(&self, r: Range<usize>) -> impl ExactSizeIterator<Item = &[u32]> + Clone + '_ {
+        self.offsets[r.start..=r.end]
+            .windows(2)
+            .map(|w| &self.items[w[0]..w[1]])
+    }
+    /// Copies the transactions in the range into a set of their own
+    pub fn slice(&self, r: Range<usize>) -> Self {
+        let start = self.offsets[r.start];
+        let items = self.items[start..self.offsets[r.end]].to_vec();
+        let offsets = self.offsets[r.start..=r.end]
+            .iter()
+            .map(|n| n - start)
+            .collect();
+        Self::from_parts(items, offsets, self.num_items)
+    }
+    /// Keeps the items that f returns true for,
+    /// then removes the transactions with fewer than min_len items left
+    pub fn retain_items(&mut self, mut f: impl FnMut(usize) -> bool, min_len: usize) {
+        // The items and transactions kept are moved down in place
+        let (mut end, mut len, mut start) = (0, 0, 0);
+        for i in 0..self.len() {
+            let next = self.offsets[i + 1];
+            let t_start = end;
+            for j in start..next {
+                let n = self.items[j];
+                if f(n as usize) {
+                    self.items[end] = n;
+                    end += 1;
+                }
+            }
+            start = next;
+            if end - t_start >= min_len {
+                len += 1;
+                self.offsets[len] = end;
+            } else {
+                end = t_start;
+            }
+        }
+        self.items.truncate(end);
+        self.offsets.truncate(len + 1);
+    }
+    /// Copies the transactions out as vectors
+    pub fn to_vec(&self) -> Vec<Vec<usize>> {
+        self.iter()
+            .map(|t| t.iter().map(|&n| n as usize).collect())
+            .collect()
+    }
+    /// Loads the set from a .dat file or a binary dataset
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        if is_binary(&path)? {
+            return Ok(BinaryFile::open(path)?.to_transaction_set());
+        }
+        Ok(Self::from_dat(File::open(path)?))
     }
     /// Constructs the set from a .dat file
     /// .dat file is a file with one transaction per line.
     /// Each transaction is a space-separated list of ids.
     pub fn from_dat(f: File) -> Self {
         let mut max = 0;
-        let mut transactions = Vec::new();
+        let mut items = Vec::new();
+        let mut offsets = vec![0];
         // Loops through each line of the file
         for l in BufReader::new(f).lines() {
             if l.is_err() {
                 continue;
             }
             let line = l.unwrap();
-            // Parses the transaction
-            let mut items: Vec<usize> = line.split_whitespace().map(|s| s.parse::<usize>().unwrap()).collect();
+            // Parses the transaction onto the end of the items
+            let start = items.len();
+            items.extend(line.split_whitespace().map(|s| s.parse::<u32>().unwrap()));
             // Sorts the items and sets the max
-            items.sort();
-            max = (*items.iter().max().unwrap()).max(max);
-            transactions.push(items);
+            let t = &mut items[start..];
+            t.sort_unstable();
+            max = t.last().map_or(max, |&n| max.max(n as usize));
+            offsets.push(items.len());
         }
-        Self { transactions, num_items: max + 1 }
+        items.shrink_to_fit();
+        Self::from_parts(items, offsets, max + 1)
     }
-}
\ No newline at end of file
+}
+impl Default for TransactionSet {
+    fn default() -> Self {
+        Self::with_capacity(0, 0, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TransactionSet;
+
+    #[test]
+    fn test_transaction_set() {
+        let set = TransactionSet::new(vec![vec![0, 2], vec![], vec![1, 2, 3]], 4);
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.get(2), &[1, 2, 3]);
+        assert!(set.get(1).is_empty());
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            vec![&[0, 2][..], &[], &[1, 2, 3]]
+        );
+        assert_eq!(set.range(1..3).count(), 2);
+        let slice = set.slice(1..3);
+        assert_eq!(slice.to_vec(), vec![vec![], vec![1, 2, 3]]);
+        assert_eq!(TransactionSet::default().len(), 0);
+        let mut set = set;
+        set.retain_items(|n| n != 2, 1);
+        assert_eq!(set.to_vec(), vec![vec![0], vec![1, 3]]);
+    }
+    #[test]
+    #[should_panic(expected = "The items must fit in u32")]
+    fn test_push_large_item() {
+        let n = u32::MAX as usize + 1;
+        TransactionSet::new(vec![vec![n]], n + 1);
+    }
+}
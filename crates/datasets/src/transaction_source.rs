@@ -0,0 +1,265 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    binary::{BinaryFile, is_binary},
+    transaction_set::TransactionSet,
+    utils::Item,
+};
+/// Transactions that can be scanned once per pass
+pub trait TransactionSource {
+    /// The type of the items as they are passed to a scan
+    type Item: Item;
+    /// The number of items (one more than the largest id)
+    fn num_items(&self) -> usize;
+    /// The number of transactions, which takes a scan unless it is known
+    fn num_transactions(&self) -> io::Result<usize> {
+        let mut n = 0;
+        self.scan(|_| n += 1)?;
+        Ok(n)
+    }
+    /// Calls f with each sorted transaction, in the same order each time.
+    /// Errors if the transactions could not be read, such as from a file that changed.
+    fn scan(&self, f: impl FnMut(&[Self::Item])) -> io::Result<()> {
+        self.scan_part(0, 1, f)
+    }
+    /// Calls f with each transaction of part i of n.
+    /// The n parts together have every transaction once.
+    fn scan_part(&self, i: usize, n: usize, f: impl FnMut(&[Self::Item])) -> io::Result<()>;
+    /// The support of each item, if it is known without a scan
+    fn item_counts(&self) -> Option<Vec<u64>> {
+        None
+    }
+    /// Part i of n as a source of its own
+    fn part(&self, i: usize, n: usize) -> Part<'_, Self>
+    where
+        Self: Sized,
+    {
+        Part { source: self, i, n }
+    }
+}
+/// Part i of n of a source
+#[derive(Debug)]
+pub struct Part<'a, T> {
+    source: &'a T,
+    i: usize,
+    n: usize,
+}
+impl<T: TransactionSource> TransactionSource for Part<'_, T> {
+    type Item = T::Item;
+    fn num_items(&self) -> usize {
+        self.source.num_items()
+    }
+    /// The parts of a part take every nth transaction of it
+    fn scan_part(&self, i: usize, n: usize, mut f: impl FnMut(&[T::Item])) -> io::Result<()> {
+        let mut k = 0;
+        self.source.scan_part(self.i, self.n, |v| {
+            if k % n == i {
+                f(v);
+            }
+            k += 1;
+        })
+    }
+}
+impl TransactionSource for TransactionSet {
+    type Item = u32;
+    fn num_items(&self) -> usize {
+        self.num_items
+    }
+    fn num_transactions(&self) -> io::Result<usize> {
+        Ok(self.len())
+    }
+    fn scan_part(&self, i: usize, n: usize, f: impl FnMut(&[u32])) -> io::Result<()> {
+        // The last part gets the rest
+        let count = self.len() / n;
+        let end = if i == n - 1 {
+            self.len()
+        } else {
+            count * (i + 1)
+        };
+        self.range((count * i)..end).for_each(f);
+        Ok(())
+    }
+}
+/// A .dat file that is read again on every scan, so it is never all in memory
+#[derive(Debug, Clone)]
+pub struct DatFile {
+    path: PathBuf,
+    num_items: usize,
+    /// The number of transactions
+    len: usize,
+    /// The size of the file in bytes
+    size: u64,
+}
+impl DatFile {
+    /// Opens the file, reading it once to find the number of items
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let size = File::open(&path)?.metadata()?.len();
+        let mut this = Self {
+            path,
+            num_items: 0,
+            len: 0,
+            size,
+        };
+        let (mut max, mut len) = (None, 0);
+        this.read(0, size, |v| {
+            len += 1;
+            max = v.last().copied().max(max);
+        })?;
+        this.num_items = max.map_or(0, |m| m as usize + 1);
+        this.len = len;
+        Ok(this)
+    }
+    /// The number of transactions
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Checks if there are no transactions
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Calls f with each transaction of the lines that start in the bytes from start to end
+    fn read(&self, start: u64, end: u64, mut f: impl FnMut(&[u32])) -> io::Result<()> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut pos = start;
+        let mut line = String::new();
+        if start > 0 {
+            // The line that start is in belongs to the part before, unless it starts there
+            reader.seek(SeekFrom::Start(start - 1))?;
+            let mut byte = [0];
+            reader.read_exact(&mut byte)?;
+            if byte[0] != b'\n' {
+                pos += reader.read_line(&mut line)? as u64;
+            }
+        }
+        let mut items = Vec::new();
+        while pos < end {
+            line.clear();
+            let n = reader.read_line(&mut line)?;
+            if n == 0 {
+                break;
+            }
+            pos += n as u64;
+            items.clear();
+            for s in line.split_whitespace() {
+                let item = s
+                    .parse()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                items.push(item);
+            }
+            items.sort_unstable();
+            f(&items);
+        }
+        Ok(())
+    }
+}
+impl TransactionSource for DatFile {
+    type Item = u32;
+    fn num_items(&self) -> usize {
+        self.num_items
+    }
+    fn num_transactions(&self) -> io::Result<usize> {
+        Ok(self.len)
+    }
+    /// Each part is the lines that start in a range of bytes of the file,
+    /// which errors if the file cannot be read again
+    fn scan_part(&self, i: usize, n: usize, f: impl FnMut(&[u32])) -> io::Result<()> {
+        let start = self.size * i as u64 / n as u64;
+        let end = self.size * (i as u64 + 1) / n as u64;
+        self.read(start, end, f)
+    }
+}
+/// A .dat file or a binary dataset, depending on what the file is
+#[derive(Debug)]
+pub enum FileSource {
+    Dat(DatFile),
+    Binary(BinaryFile),
+}
+impl FileSource {
+    /// Opens the file without loading it into memory
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        if is_binary(&path)? {
+            return Ok(Self::Binary(BinaryFile::open(path)?));
+        }
+        Ok(Self::Dat(DatFile::open(path)?))
+    }
+}
+impl TransactionSource for FileSource {
+    type Item = u32;
+    fn num_items(&self) -> usize {
+        match self {
+            FileSource::Dat(d) => d.num_items(),
+            FileSource::Binary(b) => b.num_items(),
+        }
+    }
+    fn num_transactions(&self) -> io::Result<usize> {
+        Ok(match self {
+            FileSource::Dat(d) => d.len(),
+            FileSource::Binary(b) => b.len(),
+        })
+    }
+    fn scan_part(&self, i: usize, n: usize, f: impl FnMut(&[u32])) -> io::Result<()> {
+        match self {
+            FileSource::Dat(d) => d.scan_part(i, n, f),
+            FileSource::Binary(b) => b.scan_part(i, n, f),
+        }
+    }
+    fn item_counts(&self) -> Option<Vec<u64>> {
+        match self {
+            FileSource::Dat(d) => d.item_counts(),
+            FileSource::Binary(b) => b.item_counts(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{DatFile, TransactionSource};
+    use crate::transaction_set::TransactionSet;
+
+    #[test]
+    fn test_scan_part() {
+        let set = TransactionSet::new(vec![vec![0], vec![1], vec![2], vec![3], vec![4]], 5);
+        let mut parts = Vec::new();
+        for i in 0..2 {
+            let mut part = Vec::new();
+            set.scan_part(i, 2, |v| part.push(v[0])).unwrap();
+            parts.push(part);
+        }
+        assert_eq!(parts, vec![vec![0, 1], vec![2, 3, 4]]);
+        let mut odd = Vec::new();
+        set.part(1, 2).scan_part(1, 2, |v| odd.push(v[0])).unwrap();
+        assert_eq!(odd, vec![3]);
+    }
+    #[test]
+    fn test_dat_file() {
+        let path = env::temp_dir().join(format!("dat_file_{}.dat", std::process::id()));
+        fs::write(&path, "4 1 2\n3\n\n0 2 5\n1 2 3 4\n2").unwrap();
+        let file = DatFile::open(&path).unwrap();
+        assert_eq!(file.num_items(), 6);
+        assert_eq!(file.len(), 6);
+        let parts: usize = (0..4).map(|i| file.part(i, 4).num_transactions().unwrap()).sum();
+        assert_eq!(parts, 6);
+        let mut all = Vec::new();
+        file.scan(|v| all.push(v.to_vec())).unwrap();
+        assert_eq!(all[0], vec![1, 2, 4]);
+        assert_eq!(all[2], Vec::<u32>::new());
+        // The parts have every line once, in order, for any number of parts
+        for n in 1..20 {
+            let mut parts = Vec::new();
+            for i in 0..n {
+                file.scan_part(i, n, |v| parts.push(v.to_vec())).unwrap();
+            }
+            assert_eq!(parts, all);
+        }
+        fs::remove_file(&path).unwrap();
+        // The file is read again on each scan
+        assert!(file.scan(|_| {}).is_err());
+    }
+}
@@ -1,12 +1,27 @@
+/// An item id as it is stored in a transaction
+pub trait Item: Copy + Ord {
+    /// The id as an index
+    fn index(self) -> usize;
+}
+impl Item for usize {
+    fn index(self) -> usize {
+        self
+    }
+}
+impl Item for u32 {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
 /// A nested loop function that loops through the data, sending an array of k size to the function
-pub fn nested_loops<T: FnMut(&[usize])>(mut f: T, data: &[usize], k: usize) {
+pub fn nested_loops<T: FnMut(&[usize]), I: Item>(mut f: T, data: &[I], k: usize) {
     let mut stack = vec![0; k];
     nested_loops_helper(&mut f, data, 0, 0, k, &mut stack);
 }
 /// The recursive helper for nested_loops
-fn nested_loops_helper<T: FnMut(&[usize])>(
+fn nested_loops_helper<T: FnMut(&[usize]), I: Item>(
     f: &mut T,
-    data: &[usize],
+    data: &[I],
     i: usize,
     start: usize,
     k: usize,
@@ -19,7 +34,7 @@ fn nested_loops_helper<T: FnMut(&[usize])>(
     }
     // Loop through the rest of the data, add it to the stack, and recursively call
     for j in start..data.len() {
-        stack[i] = data[j];
+        stack[i] = data[j].index();
         nested_loops_helper(f, data, i + 1, j + 1, k, stack);
     }
 }
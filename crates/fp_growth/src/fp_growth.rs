@@ -1,41 +1,175 @@
-use datasets::transaction_set::TransactionSet;
+use std::cmp::Ordering;
 
-use crate::fp_tree::FPTree;
+use datasets::{
+    budget::{Budget, Limiter, Status, Truncation},
+    constraint::{ItemConstraints, Lengths},
+    transaction_set::TransactionSet,
+};
+
+use crate::fp_tree::{FPTree, Search};
 
 pub struct FPGrowth {
     min_sup: u64,
     data: TransactionSet,
+    lengths: Lengths,
+    constraints: ItemConstraints,
 }
 
 impl FPGrowth {
     pub fn new(min_sup: u64, data: TransactionSet) -> Self {
-        Self { min_sup, data }
+        Self {
+            min_sup,
+            data,
+            lengths: Lengths::default(),
+            constraints: ItemConstraints::default(),
+        }
+    }
+    /// Only finds itemsets with these lengths, without growing itemsets past the longest
+    pub fn lengths(mut self, lengths: Lengths) -> Self {
+        self.lengths = lengths;
+        self
     }
-    fn create_fp_tree(&mut self) -> FPTree {
+    /// Only finds itemsets that satisfy the constraints.
+    /// The tree is built from the transactions with the included items (without them),
+    /// and is only mined from the items of the set that itemsets need one of.
+    pub fn constraints(mut self, constraints: ItemConstraints) -> Self {
+        self.constraints = constraints;
+        self
+    }
+    /// Whether the transaction is in the tree, which needs every included item
+    fn conditional(&self, t: &[u32]) -> bool {
+        let mut t = t.iter();
+        let include = self.constraints.include();
+        include.iter().all(|&e| t.any(|&a| a as usize == e))
+    }
+    /// Builds the tree of the transactions with the included items
+    fn create_fp_tree(&self) -> Conditional {
+        let c = &self.constraints;
         let mut map = vec![0u64; self.data.num_items];
-        for t in self.data.iter() {
+        let mut transactions = 0;
+        for t in self.data.iter().filter(|t| self.conditional(t)) {
+            transactions += 1;
             for &n in t {
-                map[n] += 1;
+                map[n as usize] += 1;
             }
         }
+        // The included items are in every itemset, so they are not in the tree
+        let include = c.include();
+        let in_tree = |x: usize| {
+            map[x] >= self.min_sup
+                && c.allows(x)
+                && include.binary_search(&x).is_err()
+                && c.admits(&[include, &[x]].concat())
+        };
+        // The items of the set are last, so mining from them finds the itemsets with them
+        let last = |x: usize| c.needs_any_of() && c.is_any_of(x);
+        // Itemsets are grown by items before theirs, so the values are in the opposite order
+        let by_value = |a: usize, b: usize| match c.descending() {
+            Some(true) => c.value(a).total_cmp(&c.value(b)),
+            Some(false) => c.value(b).total_cmp(&c.value(a)),
+            None => Ordering::Equal,
+        };
+        let order = |&a: &usize, &b: &usize| {
+            last(a)
+                .cmp(&last(b))
+                .then_with(|| by_value(a, b))
+                .then(map[b].cmp(&map[a]))
+                .then(a.cmp(&b))
+        };
         let mut tree = FPTree::new(self.min_sup);
-        for t in self.data.iter_mut() {
-            t.retain(|&x| map[x] >= self.min_sup);
-            t.sort_by(|&a, &b| map[b].cmp(&map[a]));
-            tree.insert_transaction(&t);
+        // The frequent items of each transaction, most frequent first
+        let mut buffer = Vec::new();
+        for t in self.data.iter().filter(|t| self.conditional(t)) {
+            buffer.clear();
+            buffer.extend(t.iter().map(|&x| x as usize).filter(|&x| in_tree(x)));
+            buffer.sort_by(order);
+            tree.insert_transaction(&buffer);
+        }
+        let mut items: Vec<_> = (0..self.data.num_items).filter(|&x| in_tree(x)).collect();
+        items.sort_by(order);
+        Conditional {
+            tree,
+            transactions,
+            items,
         }
-        tree
     }
-    pub fn run(mut self) -> Vec<Vec<usize>> {
-        let mut tree = self.create_fp_tree();
+    pub fn run(self) -> Vec<Vec<usize>> {
         let mut v = Vec::new();
-        tree.mine(|vec| v.push(vec.to_vec()));
+        self.run_fn(|vec| v.push(vec.to_vec()));
         v
     }
-    pub fn run_fn(mut self, f: impl FnMut(&[usize])) {
-        let mut tree = self.create_fp_tree();
-        tree.mine(f)
+    pub fn run_fn(self, mut f: impl FnMut(&[usize])) {
+        self.run_budgeted(&Budget::default(), |v, _| f(v));
     }
+    /// Runs the algorithm, calling f with each frequent itemset and its support,
+    /// and stopping early if the budget runs out
+    pub fn run_budgeted(self, budget: &Budget, f: impl FnMut(&[usize], u64)) -> Status {
+        let mut limiter = budget.start();
+        self.mine(&mut limiter, f).into()
+    }
+    /// Mines the tree of the transactions with the included items,
+    /// adding them to each itemset found
+    fn mine(
+        &self,
+        limiter: &mut Limiter,
+        mut f: impl FnMut(&[usize], u64),
+    ) -> Result<(), Truncation> {
+        let c = &self.constraints;
+        let include = c.include();
+        if !include.iter().all(|&x| c.allows(x)) {
+            return Ok(());
+        }
+        if !c.admits(include) {
+            return Ok(());
+        }
+        let Conditional {
+            mut tree,
+            transactions,
+            items,
+        } = self.create_fp_tree();
+        let n = include.len();
+        let frequent = transactions >= self.min_sup;
+        if n > 0 && frequent && c.satisfied(include) && self.lengths.contains(n) {
+            limiter.emit()?;
+            f(include, transactions);
+        }
+        // The itemsets of the tree are found with the included items
+        let lengths = Lengths {
+            min: self.lengths.min.saturating_sub(n).max(1),
+            max: self.lengths.max.saturating_sub(n),
+        };
+        if lengths.max == 0 {
+            return Ok(());
+        }
+        // The lowest and highest values of the items before each item in the tree,
+        // which are the only items an itemset grown last by it can still be grown by
+        let mut range = vec![(f64::INFINITY, f64::NEG_INFINITY); self.data.num_items];
+        let mut before = (f64::INFINITY, f64::NEG_INFINITY);
+        for x in items {
+            range[x] = before;
+            before = (before.0.min(c.value(x)), before.1.max(c.value(x)));
+        }
+        let with_include = |v: &[usize]| [v, include].concat();
+        let search = Search {
+            roots: |x| !c.needs_any_of() || c.is_any_of(x),
+            grows: |v: &[usize]| {
+                let (floor, ceiling) = range[*v.last().unwrap()];
+                c.admits(&with_include(v)) && c.converts(v, floor, ceiling)
+            },
+            finds: |v: &[usize]| c.satisfied(&with_include(v)),
+        };
+        tree.mine_search(&search, lengths, limiter, |v, support| {
+            f(&with_include(v), support)
+        })
+    }
+}
+/// The tree of the transactions with the included items
+struct Conditional {
+    tree: FPTree,
+    /// The number of transactions in the tree
+    transactions: u64,
+    /// The items of the tree, in its order
+    items: Vec<usize>,
 }
 
 #[cfg(test)]
@@ -53,8 +187,8 @@ mod tests {
             ],
             10,
         );
-        let mut growth = FPGrowth::new(1, transactionset);
-        let tree = growth.create_fp_tree();
+        let growth = FPGrowth::new(1, transactionset);
+        let tree = growth.create_fp_tree().tree;
         assert_eq!(tree.get(&[2]), 4); // {I3:4}
         assert_eq!(tree.get(&[4]), 1); // {I5:1}
         assert_eq!(tree.get(&[2, 0]), 1); // {I3->I1:1}
@@ -66,4 +200,49 @@ mod tests {
         assert_eq!(tree.get(&[2, 4, 0, 1]), 1); // {I3->I5->I1->I2:2}
         assert_eq!(tree.get(&[2, 4, 1, 5]), 1); // {I3->I5->I2->I6:1}
     }
+    #[test]
+    fn test_run_budgeted() {
+        let data = TransactionSet::new(
+            vec![vec![0, 2, 3], vec![1, 2, 4], vec![0, 1, 2, 4], vec![1, 4]],
+            5,
+        );
+        let budget = Budget {
+            max_itemsets: Some(4),
+            ..Default::default()
+        };
+        let mut found = Vec::new();
+        let status =
+            FPGrowth::new(2, data.clone()).run_budgeted(&budget, |v, _| found.push(v.to_vec()));
+        assert_eq!(status, Status::Truncated(Truncation::Itemsets));
+        assert_eq!(found.len(), 4);
+        let budget = Budget::default();
+        budget.cancel.cancel();
+        let mut n = 0;
+        let status = FPGrowth::new(2, data.clone()).run_budgeted(&budget, |_, _| n += 1);
+        assert_eq!(status, Status::Truncated(Truncation::Cancelled));
+        assert_eq!(n, 0);
+        let mut found = Vec::new();
+        let status = FPGrowth::new(2, data)
+            .run_budgeted(&Budget::default(), |v, n| found.push((v.to_vec(), n)));
+        assert_eq!(status, Status::Complete);
+        assert_eq!(found.len(), 9);
+        // {1, 2, 4} is in 2 transactions
+        let support = found.iter().find(|(v, _)| {
+            let mut v = v.clone();
+            v.sort_unstable();
+            v == [1, 2, 4]
+        });
+        assert_eq!(support.map(|s| s.1), Some(2));
+    }
+    #[test]
+    fn test_deterministic() {
+        let data = TransactionSet::new(
+            vec![vec![0, 2, 3], vec![1, 2, 4], vec![0, 1, 2, 4], vec![1, 4]],
+            5,
+        );
+        let first = FPGrowth::new(1, data.clone()).run();
+        for _ in 0..4 {
+            assert_eq!(FPGrowth::new(1, data.clone()).run(), first);
+        }
+    }
 }
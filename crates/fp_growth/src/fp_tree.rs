@@ -1,24 +1,44 @@
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     rc::{Rc, Weak},
 };
 
+use datasets::{
+    budget::{Budget, Limiter, Truncation},
+    constraint::Lengths,
+};
+
 type MRc<T> = Rc<RefCell<T>>;
 type Map = HashMap<usize, MRc<FPNode>>;
+/// The first and last node of each item, kept in order of the items
+/// so the tree is mined the same way every run
+type Header = BTreeMap<usize, MRc<FPNode>>;
+/// Which itemsets the mining of a tree visits.
+/// The itemsets are passed with their items in the order they were grown in,
+/// where each item is before the ones already in the itemset in the order of the tree.
+pub struct Search<R, G, E> {
+    /// Whether the mining starts from the item of the header,
+    /// so only the itemsets whose last item in the order of the tree is a root are found
+    pub roots: R,
+    /// Whether the itemset or any itemset grown from it can be found
+    pub grows: G,
+    /// Whether the itemset is found
+    pub finds: E,
+}
 #[derive(Debug)]
 pub struct FPTree {
     root: MRc<FPNode>,
-    header: Map,
-    tails: Map,
+    header: Header,
+    tails: Header,
     sup: u64,
 }
 impl FPTree {
     pub fn new(sup: u64) -> Self {
         Self {
             root: Rc::new(RefCell::new(FPNode::new(usize::MAX, None))),
-            header: HashMap::new(),
-            tails: HashMap::new(),
+            header: BTreeMap::new(),
+            tails: BTreeMap::new(),
             sup,
         }
     }
@@ -39,38 +59,109 @@ impl FPTree {
         0
     }
     pub fn mine(&mut self, mut f: impl FnMut(&[usize])) {
+        let budget = Budget::default();
+        let lengths = Lengths::default();
+        self.mine_limited(lengths, &mut budget.start(), |v, _| f(v))
+            .unwrap();
+    }
+    /// Mines the tree until the limiter stops it, calling f with each itemset and its support.
+    /// Only itemsets with the lengths are found, and no conditional tree is built
+    /// for an itemset of the longest length.
+    pub fn mine_limited(
+        &mut self,
+        lengths: Lengths,
+        limiter: &mut Limiter,
+        f: impl FnMut(&[usize], u64),
+    ) -> Result<(), Truncation> {
+        let search = Search {
+            roots: |_| true,
+            grows: |_: &[usize]| true,
+            finds: |_: &[usize]| true,
+        };
+        self.mine_search(&search, lengths, limiter, f)
+    }
+    /// Mines the tree like mine_limited, only visiting the itemsets the search allows
+    pub fn mine_search<R, G, E>(
+        &mut self,
+        search: &Search<R, G, E>,
+        lengths: Lengths,
+        limiter: &mut Limiter,
+        mut f: impl FnMut(&[usize], u64),
+    ) -> Result<(), Truncation>
+    where
+        R: Fn(usize) -> bool,
+        G: Fn(&[usize]) -> bool,
+        E: Fn(&[usize]) -> bool,
+    {
         let mut v2 = Vec::new();
-        self.mine_helper(&mut f, &mut v2);
+        self.mine_helper(&mut f, &mut v2, search, lengths, limiter)
     }
-    fn mine_helper(&mut self, f: &mut impl FnMut(&[usize]), v: &mut Vec<usize>) {
+    fn mine_helper<R, G, E>(
+        &mut self,
+        f: &mut impl FnMut(&[usize], u64),
+        v: &mut Vec<usize>,
+        search: &Search<R, G, E>,
+        lengths: Lengths,
+        limiter: &mut Limiter,
+    ) -> Result<(), Truncation>
+    where
+        R: Fn(usize) -> bool,
+        G: Fn(&[usize]) -> bool,
+        E: Fn(&[usize]) -> bool,
+    {
         for (&k, node) in self.header.iter() {
-            let node = node.clone();
+            if v.is_empty() && !(search.roots)(k) {
+                continue;
+            }
+            limiter.check()?;
             v.push(k);
-            f(v);
-            let mut conditional_tree = FPTree::new(self.sup);
+            if !(search.grows)(v) {
+                v.pop();
+                continue;
+            }
+            let node = node.clone();
+            // The itemsets found below v are longer than it
+            let extends = lengths.extends(v.len());
             let mut current_node = Some(node.clone());
             let mut map = HashMap::new();
+            // The support is the sum of the counts of the nodes linked from the header
+            let mut support = 0;
             while let Some(curr_node) = current_node {
-                let prefix = FPNode::get_prefix(curr_node.clone());
+                let prefix = if extends {
+                    FPNode::get_prefix(curr_node.clone())
+                } else {
+                    Vec::new()
+                };
                 let curr_node_b = curr_node.borrow();
                 current_node = curr_node_b.link.clone();
+                support += curr_node_b.count;
                 for n in prefix {
                     map.entry(n)
                         .and_modify(|n| *n += curr_node_b.count)
                         .or_insert(curr_node_b.count);
                 }
             }
+            if lengths.contains(v.len()) && (search.finds)(v) {
+                limiter.emit()?;
+                f(v, support);
+            }
+            if !extends {
+                v.pop();
+                continue;
+            }
+            let mut conditional_tree = FPTree::new(self.sup);
             current_node = Some(node.clone());
             while let Some(curr_node) = current_node {
                 let mut prefix = FPNode::get_prefix(curr_node.clone());
                 let curr_node_b = curr_node.borrow();
                 current_node = curr_node_b.link.clone();
-                prefix.retain(|n| map.get(n).unwrap_or(&0).clone() >= self.sup);
+                prefix.retain(|n| *map.get(n).unwrap_or(&0) >= self.sup);
                 conditional_tree.insert_conditional(&prefix, curr_node_b.count);
             }
-            conditional_tree.mine_helper(f, v);
+            conditional_tree.mine_helper(f, v, search, lengths, limiter)?;
             v.pop();
         }
+        Ok(())
     }
     fn insert_conditional(&mut self, items: &[usize], n: u64) {
         let mut i = 0;
@@ -85,11 +176,11 @@ impl FPTree {
             match node_b.children.get_mut(&item) {
                 Some(n) => {
                     curr_node = Some(n.clone());
-                },
+                }
                 None => {
                     let child =
                         Rc::new(RefCell::new(FPNode::new(item, Some(Rc::downgrade(&node)))));
-                    node_b.children.insert(item.clone(), child.clone());
+                    node_b.children.insert(item, child.clone());
                     match self.tails.get_mut(&item) {
                         Some(tail) => {
                             tail.borrow_mut().link = Some(child.clone());
@@ -139,7 +230,7 @@ impl FPNode {
                 break;
             }
             prefix.push(curr_node.item);
-            if let Some(parent) = curr_node.parent.clone().take() {
+            if let Some(parent) = curr_node.parent.clone() {
                 let parent_rc = parent.upgrade();
                 node = parent_rc;
             } else {
@@ -0,0 +1,53 @@
+//! Compares the orderings of the tail items of MaxMiner, and MAFIA.
+//! Run with `cargo bench -p maxminer`.
+use datasets::{random::Random, transaction_set::TransactionSet};
+use maxminer::{
+    mafia::Mafia,
+    max_miner::{ItemOrdering, MaxMiner},
+};
+use tester::bench::time;
+
+/// Creates a dense dataset where each item is in a transaction with its own probability
+fn dataset(transactions: usize, items: usize, seed: u64) -> TransactionSet {
+    let mut random = Random::new(seed);
+    // Between 30% and 97%
+    let chance: Vec<f64> = (0..items).map(|_| 0.3 + 0.67 * random.uniform()).collect();
+    let data = (0..transactions)
+        .map(|_| (0..items).filter(|&i| random.uniform() < chance[i]).collect())
+        .collect();
+    TransactionSet::new(data, items)
+}
+
+fn main() {
+    let data = dataset(2_000, 50, 42);
+    println!(
+        "{:>8} {:>12} {:>12} {:>12} {:>12} {:>8}",
+        "support", "id", "support", "dynamic", "mafia", "found"
+    );
+    for support in [1000, 800, 700] {
+        let mut times = Vec::new();
+        let mut found = 0;
+        for ordering in [
+            ItemOrdering::Id,
+            ItemOrdering::Support,
+            ItemOrdering::Dynamic,
+        ] {
+            let (t, n) = time(|| data.clone(), |data| {
+                let mut n = 0;
+                MaxMiner::with_ordering(support, data, ordering).run(|_| n += 1).unwrap();
+                n
+            });
+            times.push(t);
+            found = n;
+        }
+        let (mafia, _) = time(|| data.clone(), |data| {
+            let mut n = 0;
+            Mafia::new(support, data).run(|_| n += 1).unwrap();
+            n
+        });
+        println!(
+            "{support:>8} {:>12.2?} {:>12.2?} {:>12.2?} {mafia:>12.2?} {found:>8}",
+            times[0], times[1], times[2]
+        );
+    }
+}
@@ -10,7 +10,15 @@ impl Frequent {
     }
 
     pub fn add(&mut self, v: &[usize]) {
-        self.root.add(v);
+        self.root.add(v, None);
+    }
+    /// Adds v with its support (None if it is not known)
+    pub fn insert(&mut self, v: &[usize], support: Option<u64>) {
+        self.root.add(v, support);
+    }
+    /// Gets the support of v if it is in the set and its support is known
+    pub fn get_support(&self, v: &[usize]) -> Option<u64> {
+        self.root.get(v)?.support
     }
 
     pub fn remove(&mut self, v: &[usize]) {
@@ -20,8 +28,16 @@ impl Frequent {
     pub fn contains(&mut self, v: &[usize]) -> bool {
         self.root.contains(v)
     }
+    /// Checks if the set has a superset of the sorted v (or v itself if not proper)
+    pub fn contains_superset(&self, v: &[usize], proper: bool) -> bool {
+        self.root.contains_superset(v, proper)
+    }
 
-    pub fn for_each(&self, f: impl FnMut(&[usize])) {
+    pub fn for_each(&self, mut f: impl FnMut(&[usize])) {
+        self.root.for_each(|v, _| f(v))
+    }
+    /// A for each loop with the supports (None if it is not known)
+    pub fn for_each_support(&self, f: impl FnMut(&[usize], Option<u64>)) {
         self.root.for_each(f)
     }
 
@@ -39,14 +55,23 @@ impl Default for Frequent {
 struct Node {
     map: HashMap<usize, Node>,
     is_in: bool,
+    /// The support of the itemset if known
+    support: Option<u64>,
 }
 impl Node {
     fn new() -> Self {
         Self {
             map: HashMap::new(),
             is_in: false,
+            support: None,
         }
     }
+    fn get(&self, v: &[usize]) -> Option<&Self> {
+        if v.is_empty() {
+            return if self.is_in { Some(self) } else { None };
+        }
+        self.map.get(&v[0])?.get(&v[1..])
+    }
     pub fn add_proper_powerset(&mut self, v: &[usize], len: usize) {
         if len == 0 {
             return;
@@ -69,6 +94,24 @@ impl Node {
         }
     }
 
+    fn contains_superset(&self, v: &[usize], proper: bool) -> bool {
+        if v.is_empty() {
+            // Any itemset below this node adds items to v
+            return (!proper && self.is_in) || self.map.values().any(|n| n.any());
+        }
+        self.map.iter().any(|(&k, n)| {
+            if k == v[0] {
+                n.contains_superset(&v[1..], proper)
+            } else {
+                // Skipping k means any superset found is proper
+                k < v[0] && n.contains_superset(v, false)
+            }
+        })
+    }
+    /// Checks if this node or any below it is in the set
+    fn any(&self) -> bool {
+        self.is_in || self.map.values().any(|n| n.any())
+    }
     pub fn contains(&mut self, v: &[usize]) -> bool {
         if v.is_empty() {
             return self.is_in;
@@ -79,22 +122,24 @@ impl Node {
             false
         }
     }
-    pub fn add(&mut self, v: &[usize]) {
+    pub fn add(&mut self, v: &[usize], support: Option<u64>) {
         if v.is_empty() {
             self.is_in = true;
+            self.support = support.or(self.support);
             return;
         }
         if let Some(n) = self.map.get_mut(&v[0]) {
-            n.add(&v[1..])
+            n.add(&v[1..], support)
         } else {
             let mut n = Node::new();
-            n.add(&v[1..]);
+            n.add(&v[1..], support);
             self.map.insert(v[0], n);
         }
     }
     pub fn remove(&mut self, v: &[usize]) -> bool {
         if v.is_empty() {
             self.is_in = false;
+            self.support = None;
             return self.map.is_empty();
         }
         if let Some(n) = self.map.get_mut(&v[0]) {
@@ -108,13 +153,13 @@ impl Node {
             false
         }
     }
-    pub fn for_each(&self, mut f: impl FnMut(&[usize])) {
+    pub fn for_each(&self, mut f: impl FnMut(&[usize], Option<u64>)) {
         let mut v = Vec::new();
         self.for_each_helper(&mut f, &mut v);
     }
-    fn for_each_helper(&self, f: &mut impl FnMut(&[usize]), v: &mut Vec<usize>) {
+    fn for_each_helper(&self, f: &mut impl FnMut(&[usize], Option<u64>), v: &mut Vec<usize>) {
         if self.is_in {
-            f(v)
+            f(v, self.support)
         }
         for (&k, n) in self.map.iter() {
             v.push(k);
@@ -132,8 +177,6 @@ impl Default for Node {
 
 #[cfg(test)]
 mod tests {
-    use datasets::utils::nested_loops;
-
     use crate::frequent::Frequent;
 
     #[test]
@@ -153,6 +196,17 @@ mod tests {
         assert!(frequent.contains(&[0, 1, 3, 5]));
         frequent.remove(&[0]);
         assert!(frequent.contains(&[0, 1, 3, 5]));
+
+        assert!(frequent.contains_superset(&[1, 5], false));
+        assert!(frequent.contains_superset(&[0, 1, 3, 5], false));
+        assert!(!frequent.contains_superset(&[0, 1, 3, 5], true));
+        assert!(frequent.contains_superset(&[3], true));
+        assert!(!frequent.contains_superset(&[2], false));
+
+        frequent.insert(&[2, 4], Some(3));
+        assert_eq!(frequent.get_support(&[2, 4]), Some(3));
+        assert_eq!(frequent.get_support(&[0, 1, 3, 5]), None);
+        assert_eq!(frequent.get_support(&[2]), None);
     }
     #[test]
     fn test_powerset() {
@@ -1,3 +1,5 @@
 pub mod tree;
 pub mod max_miner;
-pub mod frequent;
\ No newline at end of file
+pub mod frequent;
+pub mod mafia;
+pub mod verify;
@@ -0,0 +1,273 @@
+use std::io;
+
+use apriori::apriori::apriori_run_one_count;
+use datasets::{
+    budget::{Budget, Limiter, Status, Truncation},
+    constraint::Lengths,
+    transaction_set::TransactionSet,
+};
+
+use crate::{frequent::Frequent, max_miner::MaximalMiner};
+/// A set of transactions stored as bits
+#[derive(Debug, Clone)]
+struct Bitmap {
+    words: Vec<u64>,
+}
+impl Bitmap {
+    /// A bitmap of n transactions that are all set
+    fn full(n: usize) -> Self {
+        let mut words = vec![u64::MAX; n.div_ceil(64)];
+        if !n.is_multiple_of(64) {
+            *words.last_mut().unwrap() = (1 << (n % 64)) - 1;
+        }
+        Self { words }
+    }
+    /// A bitmap of n transactions that are all unset
+    fn empty(n: usize) -> Self {
+        Self {
+            words: vec![0; n.div_ceil(64)],
+        }
+    }
+    fn set(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+    /// The transactions in both
+    fn and(&self, other: &Self) -> Self {
+        let words = self
+            .words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| a & b)
+            .collect();
+        Self { words }
+    }
+    /// The number of transactions
+    fn count(&self) -> u64 {
+        self.words.iter().map(|w| w.count_ones() as u64).sum()
+    }
+}
+/// The MAFIA algorithm for maximal itemsets.
+/// It searches depth first, with a bitmap of the transactions that contain each item,
+/// so the support of an itemset is the number of bits in the AND of its items' bitmaps.
+pub struct Mafia {
+    min_sup: u64,
+    data: TransactionSet,
+    lengths: Lengths,
+}
+
+impl Mafia {
+    pub fn new(min_sup: u64, data: TransactionSet) -> Self {
+        Self {
+            min_sup,
+            data,
+            lengths: Lengths::default(),
+        }
+    }
+    /// Only finds the itemsets with these lengths that have no frequent superset
+    /// up to the longest length, which the search does not go past
+    pub fn lengths(mut self, lengths: Lengths) -> Self {
+        self.lengths = lengths;
+        self
+    }
+    pub fn run(self, mut f: impl FnMut(&[usize])) -> io::Result<()> {
+        self.run_support(|v, _| f(v))
+    }
+    /// Runs the algorithm, calling f with each maximal itemset and its support
+    pub fn run_support(self, f: impl FnMut(&[usize], u64)) -> io::Result<()> {
+        self.run_budgeted(&Budget::default(), f).read()
+    }
+    /// Runs the algorithm like run_support, stopping early if the budget runs out
+    pub fn run_budgeted(self, budget: &Budget, mut f: impl FnMut(&[usize], u64)) -> Status {
+        let mut limiter = budget.start();
+        let c = match apriori_run_one_count(&self.data) {
+            Ok(c) => c,
+            Err(e) => return Status::Truncated(e.into()),
+        };
+        let mut items = vec![None; c.len()];
+        for (i, &n) in c.iter().enumerate() {
+            if n >= self.min_sup {
+                items[i] = Some(Bitmap::empty(self.data.len()));
+            }
+        }
+        for (j, t) in self.data.iter().enumerate() {
+            for &i in t {
+                if let Some(b) = &mut items[i as usize] {
+                    b.set(j);
+                }
+            }
+        }
+        let mut tail: Vec<_> = (0..c.len()).filter(|&i| items[i].is_some()).collect();
+        tail.sort_by_key(|&i| c[i]);
+        let search = Search {
+            min_sup: self.min_sup,
+            max_len: self.lengths.max,
+            items,
+        };
+        let mut mfi = Frequent::new();
+        let bits = Bitmap::full(self.data.len());
+        let mut result = search.search(
+            &mut Vec::new(),
+            &bits,
+            self.data.len() as u64,
+            &tail,
+            &mut mfi,
+            &limiter,
+        );
+        // An itemset found earlier can be a subset of one found after it
+        let mut emitted = Ok(());
+        mfi.for_each_support(|v, n| {
+            if emitted.is_ok() && self.lengths.contains(v.len()) && !mfi.contains_superset(v, true)
+            {
+                emitted = limiter.emit();
+                if emitted.is_ok() {
+                    f(v, n.unwrap());
+                }
+            }
+        });
+        if result.is_ok() {
+            result = emitted;
+        }
+        result.into()
+    }
+}
+impl MaximalMiner for Mafia {
+    fn run_support(self, f: impl FnMut(&[usize], u64)) -> io::Result<()> {
+        Mafia::run_support(self, f)
+    }
+    fn run_budgeted(self, budget: &Budget, f: impl FnMut(&[usize], u64)) -> Status {
+        Mafia::run_budgeted(self, budget, f)
+    }
+}
+/// The state shared through the search
+struct Search {
+    min_sup: u64,
+    /// The longest itemset searched
+    max_len: usize,
+    /// The bitmaps of the frequent items
+    items: Vec<Option<Bitmap>>,
+}
+impl Search {
+    /// Searches the itemsets that start with head (with the transactions in bits and its support),
+    /// and are extended by the items in tail, adding the maximal ones to mfi.
+    fn search(
+        &self,
+        head: &mut Vec<usize>,
+        bits: &Bitmap,
+        support: u64,
+        tail: &[usize],
+        mfi: &mut Frequent,
+        limiter: &Limiter,
+    ) -> Result<(), Truncation> {
+        limiter.check()?;
+        // HUTMFI: the head with its tail is a subset of a maximal itemset already found
+        if mfi.contains_superset(&sorted(head.iter().chain(tail)), false) {
+            return Ok(());
+        }
+        let len = head.len();
+        // A head that is as long as allowed is not extended
+        let tail = if len < self.max_len { tail } else { &[] };
+        // An item in every transaction with the head could be left out of a maximal itemset
+        // that is as long as allowed, so it is only moved into the head if they all fit
+        let equivalence = len + tail.len() <= self.max_len;
+        let mut next = Vec::new();
+        for &i in tail {
+            let b = bits.and(self.items[i].as_ref().unwrap());
+            let n = b.count();
+            if n == support && equivalence {
+                // Parent equivalence pruning: every transaction with the head has i,
+                // so i is in every maximal itemset with the head
+                head.push(i);
+            } else if n >= self.min_sup {
+                next.push((i, b, n));
+            }
+        }
+        if next.is_empty() {
+            let v = sorted(head.iter());
+            if !v.is_empty() && !mfi.contains_superset(&v, false) {
+                mfi.insert(&v, Some(support));
+            }
+            head.truncate(len);
+            return Ok(());
+        }
+        // FHUT: the head with its whole tail is frequent
+        if next.len() > 1 && head.len() + next.len() <= self.max_len {
+            let mut b = next[0].1.clone();
+            for (_, c, _) in &next[1..] {
+                b = b.and(c);
+            }
+            let n = b.count();
+            if n >= self.min_sup {
+                let v = sorted(head.iter().chain(next.iter().map(|(i, _, _)| i)));
+                if !mfi.contains_superset(&v, false) {
+                    mfi.insert(&v, Some(n));
+                }
+                head.truncate(len);
+                return Ok(());
+            }
+        }
+        // The items most frequent with the head are last, so they are in the most tails
+        next.sort_by_key(|&(i, _, n)| (n, i));
+        let items: Vec<_> = next.iter().map(|&(i, _, _)| i).collect();
+        for (p, (i, b, n)) in next.iter().enumerate() {
+            head.push(*i);
+            let result = self.search(head, b, *n, &items[(p + 1)..], mfi, limiter);
+            head.pop();
+            if result.is_err() {
+                head.truncate(len);
+                return result;
+            }
+        }
+        head.truncate(len);
+        Ok(())
+    }
+}
+/// Copies the items sorted by id
+fn sorted<'a>(v: impl Iterator<Item = &'a usize>) -> Vec<usize> {
+    let mut v: Vec<_> = v.copied().collect();
+    v.sort_unstable();
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use datasets::transaction_set::TransactionSet;
+
+    use super::{Bitmap, Mafia};
+
+    #[test]
+    fn test_bitmap() {
+        let mut a = Bitmap::empty(70);
+        a.set(1);
+        a.set(65);
+        assert_eq!(a.count(), 2);
+        assert_eq!(Bitmap::full(70).count(), 70);
+        assert_eq!(a.and(&Bitmap::full(70)).count(), 2);
+        let mut b = Bitmap::empty(70);
+        b.set(65);
+        assert_eq!(a.and(&b).count(), 1);
+    }
+    #[test]
+    fn test_mafia() {
+        let example = TransactionSet::new(
+            vec![
+                vec![0, 1, 4],
+                vec![1, 3],
+                vec![1, 2],
+                vec![0, 1, 3],
+                vec![0, 2],
+                vec![1, 2],
+                vec![0, 2],
+                vec![0, 1, 2, 4],
+                vec![0, 1, 2],
+            ],
+            5,
+        );
+        let mut found = Vec::new();
+        Mafia::new(2, example).run_support(|v, n| found.push((v.to_vec(), n))).unwrap();
+        found.sort_unstable();
+        assert_eq!(
+            found,
+            vec![(vec![0, 1, 2], 2), (vec![0, 1, 4], 2), (vec![1, 3], 2)]
+        );
+    }
+}
@@ -1,58 +1,220 @@
-use apriori::{
-    apriori::{apriori_run_one, apriori_run_one_count},
-    trie::AprioriTrie,
+use std::io;
+
+use apriori::apriori::apriori_run_one_count;
+use datasets::{
+    budget::{Budget, Limiter, Status, Truncation},
+    constraint::Lengths,
+    transaction_set::TransactionSet,
+    transaction_source::TransactionSource,
+    utils::Item,
 };
-use datasets::{transaction_set::TransactionSet, utils::nested_loops};
 
 use crate::{frequent::Frequent, tree::Trie};
-
-pub struct MaxMiner {
+/// An algorithm that finds the maximal frequent itemsets
+pub trait MaximalMiner {
+    /// Runs the algorithm, calling f with each maximal itemset (sorted by id) and its support.
+    /// Errors if the transactions could not be read.
+    fn run_support(self, f: impl FnMut(&[usize], u64)) -> io::Result<()>;
+    /// Runs the algorithm like run_support, stopping early if the budget runs out.
+    /// The itemsets of a truncated run are frequent, but may be subsets of ones it did not find.
+    fn run_budgeted(self, budget: &Budget, f: impl FnMut(&[usize], u64)) -> Status;
+    /// Runs the algorithm like run_budgeted, without the supports.
+    /// A miner that has to count some supports after the search can skip that.
+    fn run_itemsets(self, budget: &Budget, mut f: impl FnMut(&[usize])) -> Status
+    where
+        Self: Sized,
+    {
+        self.run_budgeted(budget, |v, _| f(v))
+    }
+}
+/// How the tail items of each candidate group are ordered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ItemOrdering {
+    /// By item id
+    Id,
+    /// By increasing support of the items
+    Support,
+    /// By increasing support of the head with each item, at each candidate group.
+    /// The items that are frequent with the head end up in the most tails,
+    /// so the lookahead finds large itemsets sooner.
+    #[default]
+    Dynamic,
+}
+pub struct MaxMiner<T = TransactionSet> {
     min_sup: u64,
-    data: TransactionSet,
+    data: T,
+    ordering: ItemOrdering,
+    lengths: Lengths,
 }
 
-impl MaxMiner {
-    pub fn new(min_sup: u64, data: TransactionSet) -> Self {
-        Self { min_sup, data }
-    }
-    pub fn run(self, mut f: impl FnMut(&[usize])) {
-        let c = apriori_run_one_count(&self.data);
-        let mut trie = Trie::new();
-        trie.initial_groups(&c, self.min_sup);
+impl<T: TransactionSource> MaxMiner<T> {
+    pub fn new(min_sup: u64, data: T) -> Self {
+        Self::with_ordering(min_sup, data, ItemOrdering::default())
+    }
+    /// Constructor with how the tail items are ordered
+    pub fn with_ordering(min_sup: u64, data: T, ordering: ItemOrdering) -> Self {
+        Self {
+            min_sup,
+            data,
+            ordering,
+            lengths: Lengths::default(),
+        }
+    }
+    /// Only finds the itemsets with these lengths that have no frequent superset
+    /// up to the longest length, which no candidate group grows past
+    pub fn lengths(mut self, lengths: Lengths) -> Self {
+        self.lengths = lengths;
+        self
+    }
+    pub fn run(self, f: impl FnMut(&[usize])) -> io::Result<()> {
+        self.run_itemsets(&Budget::default(), f).read()
+    }
+    /// Runs the algorithm like run_budgeted, calling f with each maximal itemset
+    /// without counting the supports the search did not find
+    pub fn run_itemsets(self, budget: &Budget, mut f: impl FnMut(&[usize])) -> Status {
+        let mut limiter = budget.start();
+        let (frequent, mut result) = self.mine(&limiter);
+        let mut emitted = Ok(());
+        frequent.for_each(|v| {
+            if emitted.is_ok() && self.lengths.contains(v.len()) {
+                emitted = limiter.emit();
+                if emitted.is_ok() {
+                    f(v);
+                }
+            }
+        });
+        if result.is_ok() {
+            result = emitted;
+        }
+        result.into()
+    }
+    /// Runs the algorithm, calling f with each maximal itemset and its support
+    pub fn run_support(self, f: impl FnMut(&[usize], u64)) -> io::Result<()> {
+        self.run_budgeted(&Budget::default(), f).read()
+    }
+    /// Runs the algorithm like run_support, stopping early if the budget runs out
+    pub fn run_budgeted(self, budget: &Budget, mut f: impl FnMut(&[usize], u64)) -> Status {
+        let mut limiter = budget.start();
+        let (frequent, mut result) = self.mine(&limiter);
+        // Itemsets found from a lower bound have no known support, so they are counted here
+        let mut unknown = Vec::new();
+        frequent.for_each_support(|v, s| {
+            if s.is_none() && self.lengths.contains(v.len()) {
+                unknown.push((v.to_vec(), 0));
+            }
+        });
+        if !unknown.is_empty() {
+            let counted = self.data.scan(|t| {
+                for (v, n) in unknown.iter_mut() {
+                    if is_subset(v, t) {
+                        *n += 1;
+                    }
+                }
+            });
+            // Without their supports, those itemsets are left out
+            if let Err(e) = counted {
+                unknown.clear();
+                if result.is_ok() {
+                    result = Err(e.into());
+                }
+            }
+        }
+        let mut unknown = unknown.into_iter();
+        // for_each visits the itemsets in the same order both times
+        let mut emitted = Ok(());
+        frequent.for_each_support(|v, s| {
+            if !self.lengths.contains(v.len()) {
+                return;
+            }
+            let Some(s) = s.or_else(|| unknown.next().map(|(_, n)| n)) else {
+                return;
+            };
+            if emitted.is_ok() {
+                emitted = limiter.emit();
+                if emitted.is_ok() {
+                    f(v, s);
+                }
+            }
+        });
+        if result.is_ok() {
+            result = emitted;
+        }
+        result.into()
+    }
+    /// Finds the maximal itemsets, or those found so far if the limiter stops it
+    fn mine(&self, limiter: &Limiter) -> (Frequent, Result<(), Truncation>) {
         let mut frequent = Frequent::new();
-        for i in (0..c.len()).rev() {
-            if c[i] >= self.min_sup {
-                frequent.add(&[i]);
-                break;
+        let c = match apriori_run_one_count(&self.data) {
+            Ok(c) => c,
+            Err(e) => return (frequent, Err(e.into())),
+        };
+        // Only the items are found, so there are no groups to grow
+        if !self.lengths.extends(1) {
+            for (i, &n) in c.iter().enumerate() {
+                if n >= self.min_sup {
+                    frequent.insert(&[i], Some(n));
+                }
             }
+            return (frequent, Ok(()));
+        }
+        let mut trie = Trie::with_ordering(self.ordering);
+        let order = trie.initial_groups(&c, self.min_sup);
+        // The last item has an empty tail
+        if let Some(&i) = order.last() {
+            frequent.insert(&[i], Some(c[i]));
         }
         for i in 1.. {
-            for s in self.data.iter() {
-                trie.count(s, i);
+            let mut groups = 0;
+            trie.for_each_tails(|_| groups += 1, i);
+            if let Err(t) = limiter.candidates(i, groups) {
+                return (frequent, Err(t));
+            }
+            if let Err(e) = self.data.scan(|s| trie.count(s, i)) {
+                return (frequent, Err(e.into()));
             }
             let mut count = 0;
             trie.count_frequent(
                 i,
-                |v| {
+                |v, s| {
                     count += 1;
-                    frequent.add(v);
+                    frequent.insert(v, s);
                 },
                 self.min_sup,
+                self.lengths.max,
             );
-            let mut to_remove = Frequent::new();
+            // Removes the itemsets that are not maximal
+            let mut to_remove = Vec::new();
             frequent.for_each(|v| {
-                to_remove.add_proper_powerset(v);
+                if frequent.contains_superset(v, true) {
+                    to_remove.push(v.to_vec());
+                }
             });
-            trie.tails_filter(|v| !to_remove.contains(v), i + 1);
-            to_remove.for_each(|v| {
-                frequent.remove(v);
-            });
-            if count == 0 {
+            for v in to_remove {
+                frequent.remove(&v);
+            }
+            // Prunes the groups whose head and tail is a subset of a frequent itemset
+            trie.tails_filter(|v| !frequent.contains_superset(v, false), i + 1);
+            // The groups of the next pass would find itemsets that are too long
+            if count == 0 || !self.lengths.extends(i + 1) {
                 break;
             }
         }
-        frequent.for_each(|v| {
-            f(v);
-        });
+        (frequent, Ok(()))
     }
 }
+impl<T: TransactionSource> MaximalMiner for MaxMiner<T> {
+    fn run_support(self, f: impl FnMut(&[usize], u64)) -> io::Result<()> {
+        MaxMiner::run_support(self, f)
+    }
+    fn run_budgeted(self, budget: &Budget, f: impl FnMut(&[usize], u64)) -> Status {
+        MaxMiner::run_budgeted(self, budget, f)
+    }
+    fn run_itemsets(self, budget: &Budget, f: impl FnMut(&[usize])) -> Status {
+        MaxMiner::run_itemsets(self, budget, f)
+    }
+}
+/// Checks if the sorted v is a subset of the sorted t
+pub(crate) fn is_subset(v: &[usize], t: &[impl Item]) -> bool {
+    let mut t = t.iter();
+    v.iter().all(|&e| t.any(|a| a.index() == e))
+}
@@ -1,28 +1,50 @@
 use std::collections::HashMap;
+
+use datasets::utils::Item;
+
+use crate::max_miner::ItemOrdering;
 #[derive(Debug)]
 pub struct Trie {
     root: Node,
+    /// How the tail items of each node are ordered
+    ordering: ItemOrdering,
+    /// The position of each item in the initial ordering
+    rank: Vec<usize>,
 }
 impl Trie {
     pub fn new() -> Self {
-        Self { root: Node::new() }
+        Self::with_ordering(ItemOrdering::default())
     }
-    pub fn initial_groups(&mut self, v: &[u64], sup: u64) {
-        for i in 0..v.len() {
-            if v[i] < sup {
-                continue;
-            }
+    /// Constructor with how the tail items are ordered
+    pub fn with_ordering(ordering: ItemOrdering) -> Self {
+        Self {
+            root: Node::new(),
+            ordering,
+            rank: Vec::new(),
+        }
+    }
+    /// Creates a candidate group for each frequent item, whose tail is the frequent items after it.
+    /// Returns the frequent items in order.
+    pub fn initial_groups(&mut self, v: &[u64], sup: u64) -> Vec<usize> {
+        let mut order: Vec<_> = (0..v.len()).filter(|&i| v[i] >= sup).collect();
+        if self.ordering != ItemOrdering::Id {
+            // The most frequent items are last, so they appear in the most tails
+            order.sort_by_key(|&i| v[i]);
+        }
+        self.rank = vec![usize::MAX; v.len()];
+        for (p, &i) in order.iter().enumerate() {
+            self.rank[i] = p;
             self.root.insert(&[i], v[i]);
-            for j in (i + 1)..v.len() {
-                if v[j] < sup {
-                    continue;
-                }
+            for &j in order.iter().skip(p + 1) {
                 self.root.add(&[i, j]);
             }
         }
+        order
     }
-    pub fn count(&mut self, data: &[usize], i: usize) {
-        self.root.count(data, i);
+    pub fn count<I: Item>(&mut self, data: &[I], i: usize) {
+        // Tails after the first level are only larger items if ordered by id
+        let suffix = self.ordering == ItemOrdering::Id;
+        self.root.count(data, i, suffix);
     }
     pub fn get_count(&self, v: &[usize]) -> Option<u64> {
         Some(self.root.get(v)?.count)
@@ -33,14 +55,28 @@ impl Trie {
     pub fn contains(&self, v: &[usize]) -> bool {
         self.root.get(v).is_some()
     }
-    pub fn count_frequent(&mut self, i: usize, f: impl FnMut(&[usize]), sup: u64) {
-        self.root.count_frequent(f, i, sup);
+    /// Finds the frequent itemsets at depth i, calling f with each and its support.
+    /// The support is None when the itemset was only known to be frequent from a lower bound.
+    /// The itemsets are passed sorted by id, and are at most max_len long,
+    /// so no groups are created for the next depth once its itemsets would be longer.
+    pub fn count_frequent(
+        &mut self,
+        i: usize,
+        f: impl FnMut(&[usize], Option<u64>),
+        sup: u64,
+        max_len: usize,
+    ) {
+        let order = Order {
+            ordering: self.ordering,
+            rank: &self.rank,
+        };
+        self.root.count_frequent(f, i, sup, max_len, &order);
     }
 
     pub fn for_each_tails(&self, f: impl FnMut(&[usize]), i: usize) {
         self.root.for_each_tails(f, i)
     }
-    ///
+    /// Calls f with the union of the head and tail of each node at depth i (sorted by id).
     /// The closure returns true to retain the set
     pub fn tails_filter(&mut self, f: impl FnMut(&[usize]) -> bool, i: usize) {
         self.root.tails_filter(f, i)
@@ -60,6 +96,30 @@ impl Default for Trie {
         Self::new()
     }
 }
+/// How the tails of a node are ordered when its children are created
+struct Order<'a> {
+    ordering: ItemOrdering,
+    rank: &'a [usize],
+}
+impl Order<'_> {
+    /// Sorts the tail items of node
+    fn sort(&self, next: &mut [usize], node: &Node) {
+        match self.ordering {
+            ItemOrdering::Id => next.sort_unstable(),
+            ItemOrdering::Support => next.sort_unstable_by_key(|k| self.rank[*k]),
+            // By the support of the head with each item
+            ItemOrdering::Dynamic => {
+                next.sort_unstable_by_key(|k| (node.tails[k].count, self.rank[*k]))
+            }
+        }
+    }
+}
+/// Copies v sorted by id
+fn sorted(v: &[usize]) -> Vec<usize> {
+    let mut v = v.to_vec();
+    v.sort_unstable();
+    v
+}
 #[derive(Debug)]
 struct Node {
     count: u64,
@@ -79,10 +139,10 @@ impl Node {
         if v.is_empty() {
             return true;
         }
-        if let Some(n) = self.tails.get_mut(&v[0]) {
-            if n.remove(&v[1..]) {
-                self.tails.remove(&v[0]);
-            }
+        if let Some(n) = self.tails.get_mut(&v[0])
+            && n.remove(&v[1..])
+        {
+            self.tails.remove(&v[0]);
         }
         self.tails.is_empty()
     }
@@ -101,15 +161,10 @@ impl Node {
             if self.tails.is_empty() {
                 return false;
             }
-            let mut map: Vec<_> = self.tails.keys().copied().collect();
-            map.sort_unstable();
-            for n in map.iter().cloned() {
-                v.push(n);
-            }
-            let b = f(v);
-            for _ in 0..map.len() {
-                v.pop();
-            }
+            let len = v.len();
+            v.extend(self.tails.keys());
+            let b = f(&sorted(v));
+            v.truncate(len);
             return b;
         }
         self.tails.retain(|&k, n| {
@@ -144,26 +199,47 @@ impl Node {
             n.for_each_tails_helper(f, v, i - 1);
         }
     }
-    pub fn count_frequent(&mut self, mut f: impl FnMut(&[usize]), i: usize, sup: u64) {
+    fn count_frequent(
+        &mut self,
+        mut f: impl FnMut(&[usize], Option<u64>),
+        i: usize,
+        sup: u64,
+        max_len: usize,
+        order: &Order,
+    ) {
         let mut stack = Vec::new();
-        self.count_frequent_helper(&mut f, &mut stack, i, sup);
+        self.count_frequent_helper(&mut f, &mut stack, i, sup, max_len, order);
     }
     fn count_frequent_helper(
         &mut self,
-        f: &mut impl FnMut(&[usize]),
+        f: &mut impl FnMut(&[usize], Option<u64>),
         v: &mut Vec<usize>,
         i: usize,
         sup: u64,
+        max_len: usize,
+        order: &Order,
     ) {
         if i == 0 {
             self.tails.retain(|_, n| n.count >= sup);
             let mut next: Vec<_> = self.tails.iter().map(|(&k, _)| k).collect();
-            next.sort_unstable();
-            if self.tail_count >= sup {
+            order.sort(&mut next, self);
+            // The head with one tail item is as long as allowed, so each is found on its own
+            if v.len() + 1 >= max_len {
+                for &k in next.iter() {
+                    v.push(k);
+                    f(&sorted(v), Some(self.tails[&k].count));
+                    v.pop();
+                }
+                if next.is_empty() {
+                    f(&sorted(v), Some(self.count))
+                }
+                return;
+            }
+            if self.tail_count >= sup && v.len() + next.len() <= max_len {
                 for &k in next.iter() {
                     v.push(k);
                 }
-                f(v);
+                f(&sorted(v), Some(self.tail_count));
                 for _ in 0..next.len() {
                     v.pop();
                 }
@@ -174,7 +250,7 @@ impl Node {
                     self.add(&[i1, j1]);
                 }
                 let node = self.tails.get(&i1).unwrap();
-                if node.lower_bound(self) >= sup {
+                if v.len() + next.len() - i <= max_len && node.lower_bound(self) >= sup {
                     for &j1 in next.iter().skip(i + 1) {
                         self.remove(&[i1, j1]);
                     }
@@ -182,7 +258,7 @@ impl Node {
                     for &j1 in next.iter().skip(i + 1) {
                         v.push(j1);
                     }
-                    f(v);
+                    f(&sorted(v), None);
                     for _ in (i + 1)..next.len() {
                         v.pop();
                     }
@@ -191,17 +267,18 @@ impl Node {
                 }
             }
             if next.is_empty() {
-                f(v)
+                f(&sorted(v), Some(self.count))
             } else {
-                v.push(*next.last().unwrap());
-                f(v);
+                let &last = next.last().unwrap();
+                v.push(last);
+                f(&sorted(v), Some(self.tails[&last].count));
                 v.pop();
             }
             return;
         }
         for (&k, n) in self.tails.iter_mut() {
             v.push(k);
-            n.count_frequent_helper(f, v, i - 1, sup);
+            n.count_frequent_helper(f, v, i - 1, sup, max_len, order);
             v.pop();
         }
     }
@@ -218,12 +295,6 @@ impl Node {
         }
         self.tails.get(&v[0])?.get(&v[1..])
     }
-    fn get_mut(&mut self, v: &[usize]) -> Option<&mut Self> {
-        if v.is_empty() {
-            return Some(self);
-        }
-        self.tails.get_mut(&v[0])?.get_mut(&v[1..])
-    }
     fn add(&mut self, v: &[usize]) {
         self.insert(v, 0);
     }
@@ -241,11 +312,13 @@ impl Node {
             }
         }
     }
-    fn count(&mut self, transaction: &[usize], i: usize) {
+    /// Counts the nodes at depth i + 1 contained in the transaction.
+    /// With suffix, the tails of a node only have items after it.
+    fn count<I: Item>(&mut self, transaction: &[I], i: usize, suffix: bool) {
         if i == 0 {
             let mut increments = 0;
-            for &n in transaction {
-                if let Some(n) = self.tails.get_mut(&n) {
+            for n in transaction {
+                if let Some(n) = self.tails.get_mut(&n.index()) {
                     n.count += 1;
                     increments += 1;
                 }
@@ -255,9 +328,26 @@ impl Node {
             }
             return;
         }
-        for (j, &n) in transaction.iter().enumerate() {
-            if let Some(node) = self.tails.get_mut(&n) {
-                node.count(&transaction[(j + 1)..], i - 1);
+        if !suffix {
+            // Every tail can be anywhere in the transaction, so the smaller side is searched
+            if self.tails.len() < transaction.len() {
+                for (k, node) in self.tails.iter_mut() {
+                    if transaction.binary_search_by_key(k, |n| n.index()).is_ok() {
+                        node.count(transaction, i - 1, suffix);
+                    }
+                }
+            } else {
+                for n in transaction {
+                    if let Some(node) = self.tails.get_mut(&n.index()) {
+                        node.count(transaction, i - 1, suffix);
+                    }
+                }
+            }
+            return;
+        }
+        for (j, n) in transaction.iter().enumerate() {
+            if let Some(node) = self.tails.get_mut(&n.index()) {
+                node.count(&transaction[(j + 1)..], i - 1, suffix);
             }
         }
     }
@@ -265,7 +355,7 @@ impl Node {
 
 #[cfg(test)]
 mod tests {
-    use crate::tree::Trie;
+    use crate::{max_miner::ItemOrdering, tree::Trie};
 
     #[test]
     fn test_initial() {
@@ -275,11 +365,25 @@ mod tests {
         assert!(trie.contains(&[0, 1]));
         assert!(trie.contains(&[0, 3]));
         assert!(trie.contains(&[1, 3]));
-        trie.root.count(&[0, 1, 3], 1);
+        trie.count(&[0usize, 1, 3], 1);
         assert_eq!(trie.get_count(&[0, 1]), Some(1));
         assert_eq!(trie.get_tail_count(&[0]), Some(1));
     }
 
+    #[test]
+    fn test_initial_support() {
+        let items = vec![3, 1, 0, 2];
+        let mut trie = Trie::with_ordering(ItemOrdering::Support);
+        assert_eq!(trie.initial_groups(&items, 1), vec![1, 3, 0]);
+        assert!(trie.contains(&[1, 3]));
+        assert!(trie.contains(&[1, 0]));
+        assert!(trie.contains(&[3, 0]));
+        assert!(!trie.contains(&[0, 1]));
+        // The tails are not after the head in the transaction
+        trie.count(&[0usize, 1, 3], 1);
+        assert_eq!(trie.get_count(&[3, 0]), Some(1));
+        assert_eq!(trie.get_tail_count(&[1]), Some(1));
+    }
     #[test]
     fn test_tails_filter() {
         let mut trie = Trie::new();
@@ -0,0 +1,145 @@
+use std::fmt::Display;
+
+use datasets::transaction_set::TransactionSet;
+
+use crate::max_miner::is_subset;
+/// Why an itemset reported as maximal is wrong
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The itemset has less than the minimum support
+    Infrequent { itemset: Vec<usize>, support: u64 },
+    /// The reported support does not match the data
+    WrongSupport {
+        itemset: Vec<usize>,
+        reported: u64,
+        actual: u64,
+    },
+    /// Adding the item to the itemset is still frequent
+    NotMaximal { itemset: Vec<usize>, item: usize },
+}
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::Infrequent { itemset, support } => {
+                write!(f, "{itemset:?} is not frequent (support {support})")
+            }
+            VerifyError::WrongSupport {
+                itemset,
+                reported,
+                actual,
+            } => write!(
+                f,
+                "{itemset:?} has support {actual}, but {reported} was reported"
+            ),
+            VerifyError::NotMaximal { itemset, item } => {
+                write!(
+                    f,
+                    "{itemset:?} is not maximal, it can be extended by {item}"
+                )
+            }
+        }
+    }
+}
+impl std::error::Error for VerifyError {}
+/// Checks that each itemset is frequent, has the reported support,
+/// and is maximal (so it is also closed) among the itemsets up to max_len long.
+/// Each itemset takes one scan of the data,
+/// counting the support of every one item extension at the same time.
+pub fn verify(
+    data: &TransactionSet,
+    min_sup: u64,
+    max_len: usize,
+    itemsets: &[(Vec<usize>, u64)],
+) -> Result<(), VerifyError> {
+    let mut extensions = vec![0u64; data.num_items];
+    for (v, reported) in itemsets {
+        extensions.iter_mut().for_each(|n| *n = 0);
+        let mut support = 0;
+        for t in data.iter() {
+            if !is_subset(v, t) {
+                continue;
+            }
+            support += 1;
+            for &e in t {
+                extensions[e as usize] += 1;
+            }
+        }
+        if support < min_sup {
+            return Err(VerifyError::Infrequent {
+                itemset: v.clone(),
+                support,
+            });
+        }
+        if support != *reported {
+            return Err(VerifyError::WrongSupport {
+                itemset: v.clone(),
+                reported: *reported,
+                actual: support,
+            });
+        }
+        // An itemset that is as long as allowed has no extensions
+        if v.len() >= max_len {
+            continue;
+        }
+        if let Some(item) =
+            (0..data.num_items).find(|e| extensions[*e] >= min_sup && !v.contains(e))
+        {
+            return Err(VerifyError::NotMaximal {
+                itemset: v.clone(),
+                item,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use datasets::transaction_set::TransactionSet;
+
+    use super::{VerifyError, verify};
+
+    #[test]
+    fn test_verify() {
+        let example = TransactionSet::new(
+            vec![
+                vec![0, 1, 4],
+                vec![1, 3],
+                vec![1, 2],
+                vec![0, 1, 3],
+                vec![0, 2],
+                vec![1, 2],
+                vec![0, 2],
+                vec![0, 1, 2, 4],
+                vec![0, 1, 2],
+            ],
+            5,
+        );
+        let maximal = vec![(vec![0, 1, 2], 2), (vec![0, 1, 4], 2), (vec![1, 3], 2)];
+        assert_eq!(verify(&example, 2, usize::MAX, &maximal), Ok(()));
+        // The pairs are maximal up to 2 items
+        assert_eq!(verify(&example, 2, 2, &[(vec![0, 1], 4)]), Ok(()));
+        assert_eq!(
+            verify(&example, 2, usize::MAX, &[(vec![0, 1], 4)]),
+            Err(VerifyError::NotMaximal {
+                itemset: vec![0, 1],
+                item: 2
+            })
+        );
+        assert_eq!(
+            verify(&example, 2, usize::MAX, &[(vec![1, 3], 3)]),
+            Err(VerifyError::WrongSupport {
+                itemset: vec![1, 3],
+                reported: 3,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            verify(&example, 2, usize::MAX, &[(vec![2, 4], 1)]),
+            Err(VerifyError::Infrequent {
+                itemset: vec![2, 4],
+                support: 1
+            })
+        );
+    }
+}
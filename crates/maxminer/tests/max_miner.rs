@@ -0,0 +1,146 @@
+use std::{collections::HashSet, fs::File};
+
+use datasets::{
+    budget::{Budget, Status, Truncation},
+    transaction_set::TransactionSet,
+    transaction_source::DatFile,
+};
+use maxminer::{
+    mafia::Mafia,
+    max_miner::{ItemOrdering, MaxMiner, MaximalMiner},
+    verify::verify,
+};
+use tester::test_utils::{DATABASE, Solved, test_maximal};
+
+#[test]
+fn test_max_miner() {
+    test_maximal(|t, s| {
+        let mut combined = HashSet::new();
+        MaxMiner::new(s, t).run(|v| {
+            combined.insert(v.to_vec());
+        }).unwrap();
+        Solved::new(combined)
+    });
+}
+#[test]
+fn test_max_miner_orderings() {
+    for ordering in [
+        ItemOrdering::Id,
+        ItemOrdering::Support,
+        ItemOrdering::Dynamic,
+    ] {
+        test_maximal(|t, s| {
+            let mut combined = HashSet::new();
+            MaxMiner::with_ordering(s, t, ordering).run(|v| {
+                combined.insert(v.to_vec());
+            }).unwrap();
+            Solved::new(combined)
+        });
+    }
+}
+#[test]
+fn test_max_miner_stream() {
+    test_maximal(|_, s| {
+        let mut combined = HashSet::new();
+        MaxMiner::new(s, DatFile::open(DATABASE).unwrap()).run(|v| {
+            combined.insert(v.to_vec());
+        }).unwrap();
+        Solved::new(combined)
+    });
+}
+#[test]
+fn test_max_miner_support() {
+    test_maximal(|t, s| {
+        let mut combined = HashSet::new();
+        MaxMiner::new(s, t).run_support(|v, _| {
+            combined.insert(v.to_vec());
+        }).unwrap();
+        Solved::new(combined)
+    });
+}
+#[test]
+fn test_mafia() {
+    test_maximal(|t, s| {
+        let mut combined = HashSet::new();
+        Mafia::new(s, t).run(|v| {
+            combined.insert(v.to_vec());
+        }).unwrap();
+        Solved::new(combined)
+    });
+}
+#[test]
+fn test_verify() {
+    let data = || TransactionSet::from_dat(File::open(DATABASE).unwrap());
+    let mut found = Vec::new();
+    MaxMiner::new(10, data()).run_support(|v, n| found.push((v.to_vec(), n))).unwrap();
+    assert!(!found.is_empty());
+    assert_eq!(verify(&data(), 10, usize::MAX, &found), Ok(()));
+    // A subset of a maximal itemset is not maximal
+    let (mut v, _) = found.iter().find(|(v, _)| v.len() > 1).unwrap().clone();
+    v.pop();
+    assert!(verify(&data(), 10, usize::MAX, &[(v, 0)]).is_err());
+    // The orderings find the same itemsets at lower supports as well
+    for support in [2, 4] {
+        let mut id = Vec::new();
+        MaxMiner::with_ordering(support, data(), ItemOrdering::Id)
+            .run_support(|v, n| id.push((v.to_vec(), n)))
+            .unwrap();
+        assert_eq!(verify(&data(), support, usize::MAX, &id), Ok(()));
+        for ordering in [ItemOrdering::Support, ItemOrdering::Dynamic] {
+            let mut found = Vec::new();
+            MaxMiner::with_ordering(support, data(), ordering)
+                .run_support(|v, n| found.push((v.to_vec(), n)))
+                .unwrap();
+            found.sort_unstable();
+            id.sort_unstable();
+            assert_eq!(found, id);
+        }
+        let mut found = Vec::new();
+        Mafia::new(support, data()).run_support(|v, n| found.push((v.to_vec(), n))).unwrap();
+        found.sort_unstable();
+        assert_eq!(found, id);
+    }
+}
+fn run_budgeted(miner: impl MaximalMiner, budget: &Budget) -> (Vec<Vec<usize>>, Status) {
+    let mut found = Vec::new();
+    let status = miner.run_budgeted(budget, |v, _| found.push(v.to_vec()));
+    (found, status)
+}
+#[test]
+fn test_budget() {
+    let data = || TransactionSet::from_dat(File::open(DATABASE).unwrap());
+    let cancelled = Budget::default();
+    cancelled.cancel.cancel();
+    let three = Budget {
+        max_itemsets: Some(3),
+        ..Default::default()
+    };
+    for (all, found, cancelled) in [
+        (
+            run_budgeted(MaxMiner::new(4, data()), &Budget::default()),
+            run_budgeted(MaxMiner::new(4, data()), &three),
+            run_budgeted(MaxMiner::new(4, data()), &cancelled),
+        ),
+        (
+            run_budgeted(Mafia::new(4, data()), &Budget::default()),
+            run_budgeted(Mafia::new(4, data()), &three),
+            run_budgeted(Mafia::new(4, data()), &cancelled),
+        ),
+    ] {
+        assert_eq!(all.1, Status::Complete);
+        assert!(all.0.len() > 3);
+        assert_eq!(found.1, Status::Truncated(Truncation::Itemsets));
+        assert_eq!(found.0.len(), 3);
+        assert_eq!(cancelled.1, Status::Truncated(Truncation::Cancelled));
+        assert!(cancelled.0.len() < all.0.len());
+    }
+    let budget = Budget {
+        max_candidates: Some(1),
+        ..Default::default()
+    };
+    let (_, status) = run_budgeted(MaxMiner::new(4, data()), &budget);
+    assert!(matches!(
+        status,
+        Status::Truncated(Truncation::Candidates { pass: 1, .. })
+    ));
+}
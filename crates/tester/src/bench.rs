@@ -0,0 +1,18 @@
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+/// Runs f on a new input a few times and returns the fastest time with what f found.
+/// The input is made before the timer starts, for miners that take their dataset.
+pub fn time<T>(mut input: impl FnMut() -> T, mut f: impl FnMut(T) -> usize) -> (Duration, usize) {
+    let mut best = Duration::MAX;
+    let mut found = 0;
+    for _ in 0..3 {
+        let input = input();
+        let start = Instant::now();
+        found = black_box(f(input));
+        best = best.min(start.elapsed());
+    }
+    (best, found)
+}
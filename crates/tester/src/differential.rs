@@ -0,0 +1,468 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    panic::{self, AssertUnwindSafe},
+};
+
+use datasets::{
+    constraint::{Aggregate, ItemConstraints, ItemValues, Lengths, ValueBound},
+    quest::{Quest, QuestParams},
+    random::Random,
+    transaction_set::TransactionSet,
+};
+
+/// The itemsets a miner finds, each with its support if it reports one
+pub type Found = Vec<(Vec<usize>, Option<u64>)>;
+/// What a miner is supposed to find
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Every frequent itemset
+    Frequent,
+    /// The frequent itemsets with no frequent proper superset
+    Maximal,
+}
+/// Runs a miner on a case
+type Run = dyn Fn(TransactionSet, u64, usize, Lengths, &ItemConstraints) -> Found;
+/// The itemsets a miner is expected to find on a case, with their supports
+type Oracle = dyn Fn(&Case) -> BTreeMap<Vec<usize>, u64>;
+/// A miner checked by the harness.
+/// It is run with the transactions, the support count, the number of threads,
+/// the lengths of the itemsets to find, and the items they must or must not have.
+pub struct Miner {
+    pub name: String,
+    pub target: Target,
+    run: Box<Run>,
+    /// What the miner finds instead of the target, for miners that find something else
+    oracle: Option<Box<Oracle>>,
+}
+impl Miner {
+    pub fn new(
+        name: impl Into<String>,
+        target: Target,
+        run: impl Fn(TransactionSet, u64, usize, Lengths, &ItemConstraints) -> Found + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            target,
+            run: Box::new(run),
+            oracle: None,
+        }
+    }
+    /// Checks the miner against the itemsets the oracle expects on each case,
+    /// usually built from the brute force miner, instead of its target
+    pub fn oracle(mut self, oracle: impl Fn(&Case) -> BTreeMap<Vec<usize>, u64> + 'static) -> Self {
+        self.oracle = Some(Box::new(oracle));
+        self
+    }
+}
+/// A dataset with the support count, number of threads, lengths and constraints
+/// to mine it with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+    /// The sorted transactions
+    pub transactions: Vec<Vec<usize>>,
+    pub num_items: usize,
+    pub min_sup: u64,
+    pub threads: usize,
+    pub lengths: Lengths,
+    pub constraints: ItemConstraints,
+}
+impl Case {
+    pub fn data(&self) -> TransactionSet {
+        TransactionSet::new(self.transactions.clone(), self.num_items)
+    }
+    /// The cases that are one step smaller, from the largest steps to the smallest
+    fn smaller(&self) -> Vec<Case> {
+        let mut v = Vec::new();
+        let with = |transactions| Case {
+            transactions,
+            ..self.clone()
+        };
+        for i in 0..self.transactions.len() {
+            let mut t = self.transactions.clone();
+            t.remove(i);
+            v.push(with(t));
+        }
+        for n in 0..self.num_items {
+            let t = self
+                .transactions
+                .iter()
+                .map(|t| t.iter().copied().filter(|&m| m != n).collect())
+                .collect();
+            v.push(with(t));
+        }
+        for i in 0..self.transactions.len() {
+            for j in 0..self.transactions[i].len() {
+                let mut t = self.transactions.clone();
+                t[i].remove(j);
+                v.push(with(t));
+            }
+        }
+        let max = self
+            .transactions
+            .iter()
+            .flatten()
+            .max()
+            .map_or(0, |m| m + 1);
+        if max < self.num_items {
+            v.push(Case {
+                num_items: max,
+                ..self.clone()
+            });
+        }
+        if self.min_sup > 1 {
+            v.push(Case {
+                min_sup: self.min_sup - 1,
+                ..self.clone()
+            });
+        }
+        if self.threads > 1 {
+            v.push(Case {
+                threads: self.threads - 1,
+                ..self.clone()
+            });
+        }
+        if self.lengths != Lengths::default() {
+            v.push(Case {
+                lengths: Lengths::default(),
+                ..self.clone()
+            });
+        }
+        if !self.constraints.is_empty() {
+            v.push(Case {
+                constraints: ItemConstraints::default(),
+                ..self.clone()
+            });
+        }
+        // Removing an item or transaction that does not change anything is not a step
+        v.retain(|c| c != self);
+        v
+    }
+}
+impl Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "support {}, {} threads, {} items, lengths {} to {}, {:?}, transactions:",
+            self.min_sup,
+            self.threads,
+            self.num_items,
+            self.lengths.min,
+            self.lengths.max,
+            self.constraints
+        )?;
+        for t in &self.transactions {
+            let t: Vec<_> = t.iter().map(|n| n.to_string()).collect();
+            writeln!(f, "  [{}]", t.join(" "))?;
+        }
+        Ok(())
+    }
+}
+/// Finds every frequent itemset with its support by extending the frequent itemsets
+/// one item at a time and counting each extension with a scan of the transactions
+pub fn brute_force(data: &TransactionSet, min_sup: u64) -> BTreeMap<Vec<usize>, u64> {
+    let mut found = BTreeMap::new();
+    let mut stack: Vec<Vec<usize>> = vec![Vec::new()];
+    while let Some(v) = stack.pop() {
+        let start = v.last().map_or(0, |n| n + 1);
+        for n in start..data.num_items {
+            let mut w = v.clone();
+            w.push(n);
+            let support = data
+                .iter()
+                .filter(|t| w.iter().all(|&a| t.contains(&(a as u32))))
+                .count() as u64;
+            if support >= min_sup {
+                found.insert(w.clone(), support);
+                stack.push(w);
+            }
+        }
+    }
+    found
+}
+/// Keeps the frequent itemsets that have no proper superset in the set.
+/// Every subset of a frequent itemset is frequent,
+/// so it is enough that no one item extension is in the set.
+pub fn maximal(frequent: &BTreeMap<Vec<usize>, u64>) -> BTreeMap<Vec<usize>, u64> {
+    let items: Vec<_> = frequent.keys().filter(|v| v.len() == 1).collect();
+    frequent
+        .iter()
+        .filter(|(v, _)| {
+            !items.iter().any(|i| {
+                let mut w = v.to_vec();
+                match w.binary_search(&i[0]) {
+                    Ok(_) => false,
+                    Err(p) => {
+                        w.insert(p, i[0]);
+                        frequent.contains_key(&w)
+                    }
+                }
+            })
+        })
+        .map(|(v, &n)| (v.clone(), n))
+        .collect()
+}
+/// Generates a small dataset, either with each item in a transaction with its own chance,
+/// or from a few patterns with the Quest generator
+pub fn random_dataset(seed: u64) -> (Vec<Vec<usize>>, usize) {
+    let mut random = Random::new(seed);
+    let num_items = 1 + random.below(12);
+    let len = 1 + random.below(40);
+    if random.below(2) == 0 {
+        let chance: Vec<_> = (0..num_items).map(|_| random.uniform()).collect();
+        let transactions = (0..len)
+            .map(|_| {
+                (0..num_items)
+                    .filter(|&n| random.uniform() < chance[n])
+                    .collect()
+            })
+            .collect();
+        return (transactions, num_items);
+    }
+    let params = QuestParams {
+        transactions: len,
+        avg_len: 1.0 + 4.0 * random.uniform(),
+        avg_pattern_len: 1.0 + 3.0 * random.uniform(),
+        patterns: 1 + random.below(5),
+        items: num_items,
+        seed: random.next_u64(),
+        ..Default::default()
+    };
+    (Quest::new(params).collect(), num_items)
+}
+/// The cases for each seed: its dataset at a few supports, with 1 to 4 threads.
+/// Half of the seeds only find some lengths.
+pub fn cases(seeds: impl Iterator<Item = u64>) -> impl Iterator<Item = Case> {
+    seeds.flat_map(|seed| {
+        let (transactions, num_items) = random_dataset(seed);
+        let lengths = match seed % 4 {
+            0 | 1 => Lengths::default(),
+            2 => Lengths::new(Some(2), Some(3)),
+            _ => Lengths::new(None, Some(1 + (seed as usize / 4) % 3)),
+        };
+        let len = transactions.len() as u64;
+        let mut supports = vec![1, 2, len / 4, len / 2, len];
+        supports.retain(|&s| s >= 1);
+        supports.sort_unstable();
+        supports.dedup();
+        supports.into_iter().map(move |min_sup| Case {
+            transactions: transactions.clone(),
+            num_items,
+            min_sup,
+            threads: 1 + (seed as usize + min_sup as usize) % 4,
+            lengths,
+            constraints: ItemConstraints::default(),
+        })
+    })
+}
+/// Up to max items below n, which may repeat
+fn random_items(random: &mut Random, n: usize, max: usize) -> Vec<usize> {
+    let len = random.below(max + 1);
+    (0..len).map(|_| random.below(n)).collect()
+}
+/// The cases for each seed like cases, each with constraints on a few of its items.
+/// Half of them also bound the values of the itemsets, with items worth -2 to 9.
+pub fn constrained_cases(seeds: impl Iterator<Item = u64>) -> impl Iterator<Item = Case> {
+    seeds.flat_map(|seed| {
+        let mut random = Random::new(seed ^ 0x5eed);
+        cases(seed..seed + 1).map(move |case| {
+            let n = case.num_items;
+            let include = random_items(&mut random, n, 2);
+            let exclude = random_items(&mut random, n, 2);
+            let any_of = random_items(&mut random, n, 3);
+            let allowed = (random.below(4) == 0).then(|| random_items(&mut random, n, n));
+            let mut constraints = ItemConstraints::new(include, exclude, allowed, any_of);
+            if random.below(2) == 0 {
+                let values = (0..n).map(|_| random.below(12) as f64 - 2.0).collect();
+                let bounds = (0..1 + random.below(2))
+                    .map(|_| ValueBound {
+                        aggregate: [Aggregate::Sum, Aggregate::Avg, Aggregate::Min, Aggregate::Max]
+                            [random.below(4)],
+                        at_least: random.below(2) == 0,
+                        bound: random.below(16) as f64 - 2.0,
+                    })
+                    .collect();
+                constraints = constraints.bounds(ItemValues::new(values), bounds);
+            }
+            Case {
+                constraints,
+                ..case
+            }
+        })
+    })
+}
+/// The itemsets the brute force miner finds for each target on the case.
+/// The maximal itemsets have no frequent superset up to the longest length.
+fn expected(case: &Case) -> [BTreeMap<Vec<usize>, u64>; 2] {
+    let mut frequent = brute_force(&case.data(), case.min_sup);
+    frequent.retain(|v, _| v.len() <= case.lengths.max);
+    let mut max = maximal(&frequent);
+    let found = |v: &Vec<usize>| case.lengths.contains(v.len()) && case.constraints.satisfied(v);
+    frequent.retain(|v, _| found(v));
+    max.retain(|v, _| found(v));
+    [frequent, max]
+}
+/// Runs the miner on the case, and describes how its output differs from the brute force miner
+pub fn diff(miner: &Miner, case: &Case) -> Option<String> {
+    diff_expected(miner, case, &expected(case))
+}
+/// Like diff, with the itemsets that are expected
+fn diff_expected(
+    miner: &Miner,
+    case: &Case,
+    expected: &[BTreeMap<Vec<usize>, u64>; 2],
+) -> Option<String> {
+    let found = match panic::catch_unwind(AssertUnwindSafe(|| {
+        (miner.run)(
+            case.data(),
+            case.min_sup,
+            case.threads,
+            case.lengths,
+            &case.constraints,
+        )
+    })) {
+        Ok(found) => found,
+        Err(e) => {
+            let message = e
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| e.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            return Some(format!("it panicked: {message}"));
+        }
+    };
+    let own;
+    let expected = match (&miner.oracle, miner.target) {
+        (Some(oracle), _) => {
+            own = oracle(case);
+            &own
+        }
+        (None, Target::Frequent) => &expected[0],
+        (None, Target::Maximal) => &expected[1],
+    };
+    let mut seen = BTreeMap::new();
+    for (mut v, support) in found {
+        v.sort_unstable();
+        match expected.get(&v) {
+            None => return Some(format!("it found {v:?}, which is not expected")),
+            Some(&n) if support.is_some_and(|s| s != n) => {
+                return Some(format!(
+                    "it reported a support of {} for {v:?}, which has a support of {n}",
+                    support.unwrap()
+                ));
+            }
+            _ => {}
+        }
+        if seen.insert(v.clone(), ()).is_some() {
+            return Some(format!("it found {v:?} more than once"));
+        }
+    }
+    let missing = expected.keys().find(|v| !seen.contains_key(*v))?;
+    Some(format!("it did not find {missing:?}"))
+}
+/// Makes the case smaller while the miner still differs on it
+pub fn shrink(miner: &Miner, case: Case) -> Case {
+    let mut case = case;
+    while let Some(c) = case
+        .smaller()
+        .into_iter()
+        .find(|c| diff(miner, c).is_some())
+    {
+        case = c;
+    }
+    case
+}
+/// Runs every miner on every case, checking its output against the brute force miner.
+/// Panics with the smallest case found that a miner differs on.
+pub fn check(miners: &[Miner], cases: impl Iterator<Item = Case>) {
+    for case in cases {
+        let expected = expected(&case);
+        for miner in miners {
+            if diff_expected(miner, &case, &expected).is_none() {
+                continue;
+            }
+            // The panics of the smaller cases are expected
+            let hook = panic::take_hook();
+            panic::set_hook(Box::new(|_| {}));
+            let small = shrink(miner, case.clone());
+            let message = diff(miner, &small).unwrap();
+            panic::set_hook(hook);
+            panic!(
+                "{} differs from the brute force miner: {message}, on {small}",
+                miner.name
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datasets::constraint::{ItemConstraints, Lengths};
+
+    use super::{Case, Miner, Target, brute_force, check, diff, maximal, shrink};
+
+    fn example() -> Case {
+        Case {
+            transactions: vec![vec![0, 1, 2], vec![0, 1], vec![1, 2], vec![3]],
+            num_items: 5,
+            min_sup: 2,
+            threads: 1,
+            lengths: Lengths::default(),
+            constraints: ItemConstraints::default(),
+        }
+    }
+    #[test]
+    fn test_brute_force() {
+        let found = brute_force(&example().data(), 2);
+        let expected = [
+            (vec![0], 2),
+            (vec![0, 1], 2),
+            (vec![1], 3),
+            (vec![1, 2], 2),
+            (vec![2], 2),
+        ];
+        assert_eq!(found, expected.into_iter().collect());
+        let max: Vec<_> = maximal(&found).into_keys().collect();
+        assert_eq!(max, vec![vec![0, 1], vec![1, 2]]);
+    }
+    #[test]
+    fn test_shrink() {
+        // Drops the supports of the itemsets with item 2
+        let broken = Miner::new("broken", Target::Frequent, |data, s, _, _, _| {
+            brute_force(&data, s)
+                .into_iter()
+                .map(|(v, n)| {
+                    let n = if v.contains(&2) { n - 1 } else { n };
+                    (v, Some(n))
+                })
+                .collect()
+        });
+        assert!(diff(&broken, &example()).is_some());
+        let small = shrink(&broken, example());
+        assert_eq!(small.transactions, vec![vec![2]]);
+        assert_eq!((small.min_sup, small.num_items), (1, 3));
+        let correct = Miner::new("correct", Target::Maximal, |data, s, _, _, _| {
+            maximal(&brute_force(&data, s))
+                .into_keys()
+                .map(|v| (v, None))
+                .collect()
+        });
+        check(&[correct], [example()].into_iter());
+    }
+    #[test]
+    fn test_oracle() {
+        // Only finds the pairs, which the brute force miner does not expect on its own
+        let pairs = |data, s| {
+            let mut found = brute_force(&data, s);
+            found.retain(|v, _| v.len() == 2);
+            found
+        };
+        let run = move |data, s, _, _, _: &_| {
+            pairs(data, s).into_iter().map(|(v, n)| (v, Some(n))).collect()
+        };
+        let miner = Miner::new("pairs", Target::Frequent, run);
+        assert!(diff(&miner, &example()).is_some());
+        let miner = miner.oracle(move |case| pairs(case.data(), case.min_sup));
+        check(&[miner], [example()].into_iter());
+    }
+}
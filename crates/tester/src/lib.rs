@@ -1,2 +1,4 @@
 // #[cfg(test)]
-pub mod test_utils;
\ No newline at end of file
+pub mod bench;
+pub mod differential;
+pub mod test_utils;
@@ -56,3 +56,32 @@ pub fn test_generic(f: impl Fn(TransactionSet, u64) -> Solved) {
     assert_eq!(s.set.len(), s2.set.len());
     assert_eq!(s, s2);
 }
+impl Solved {
+    /// Keeps only the itemsets that have no proper superset in the set
+    pub fn maximal(&self) -> Self {
+        let set = self
+            .set
+            .iter()
+            .filter(|v| {
+                !self
+                    .set
+                    .iter()
+                    .any(|w| w.len() > v.len() && v.iter().all(|e| w.contains(e)))
+            })
+            .cloned()
+            .collect();
+        Self { set }
+    }
+}
+/// Like test_generic, but compares against the maximal itemsets of the solved file
+pub fn test_maximal(f: impl Fn(TransactionSet, u64) -> Solved) {
+    let data = File::open(DATABASE).expect("Database file does not exist");
+    let t = TransactionSet::from_dat(data);
+    let s = f(t, 10);
+    let f = File::open(SOLVED).expect("Solved File Not Found");
+    let s2 = Solved::from_file(f)
+        .expect("Invalid Format Error")
+        .maximal();
+    assert_eq!(s.set.len(), s2.set.len());
+    assert_eq!(s, s2);
+}
@@ -1,24 +1,70 @@
-use std::{fs::File, path::PathBuf};
+use std::{error::Error, path::PathBuf};
 
-use apriori::apriori::Apriori;
+use apriori::apriori::{Apriori, CountingStructure};
 use clap::Args;
-use datasets::transaction_set::TransactionSet;
+use datasets::{
+    budget::Status,
+    transaction_set::TransactionSet,
+    transaction_source::{FileSource, TransactionSource},
+};
 
-use crate::{get_writer, out_writer, Arguments};
+use crate::{
+    checkpoint::CheckpointArgs, constraint::ItemConstraintArgs, output::ItemsetWriter,
+    passes::PassLog, report_status, Arguments,
+};
 
 #[derive(Args)]
 pub struct AprioriArgs {
     path: PathBuf,
     support_count: u64,
+    /// Counts the candidates with a flat trie instead of a hash tree
+    #[arg(long, default_value_t = false)]
+    flat_trie: bool,
+    /// Reads the file again on every pass instead of loading it into memory
+    #[arg(long, default_value_t = false)]
+    stream: bool,
+    #[command(flatten)]
+    checkpoint: CheckpointArgs,
+    #[command(flatten)]
+    constraints: ItemConstraintArgs,
 }
 impl AprioriArgs {
     pub fn run(&self, config: &Arguments) -> Result<(), Box<dyn std::error::Error>> {
-        let mut out = get_writer(&config.output_file);
-
-        let f = File::open(&self.path)?;
-        let data = TransactionSet::from_dat(f);
-        Apriori::new(self.support_count).run_fn(&data, |v| out_writer(v, &mut out));
-        let _ = out.flush();
-        Ok(())
+        let counting = if self.flat_trie {
+            CountingStructure::FlatTrie
+        } else {
+            CountingStructure::HashTree
+        };
+        let apriori = Apriori::with_counting(self.support_count, counting)
+            .lengths(config.lengths()?)
+            .constraints(self.constraints.constraints()?);
+        let mut log = PassLog::new(config);
+        let status = if self.stream {
+            let data = FileSource::open(&self.path)?;
+            self.mine(apriori, &data, &mut log, config)?
+        } else {
+            let data = TransactionSet::open(&self.path)?;
+            self.mine(apriori, &data, &mut log, config)?
+        };
+        report_status(status);
+        log.finish(config)
+    }
+    /// Runs Apriori, saving checkpoints if asked to
+    fn mine(
+        &self,
+        apriori: Apriori,
+        data: &impl TransactionSource,
+        log: &mut PassLog,
+        config: &Arguments,
+    ) -> Result<Status, Box<dyn Error>> {
+        let mut out = ItemsetWriter::new(config, data.num_transactions()?)?.levelwise();
+        let budget = config.budget();
+        let f = |v: &[usize], n| out.write(v, n);
+        let status = match self.checkpoint.checkpointer() {
+            Some(mut c) => apriori.run_checkpointed(data, log, &budget, &mut c, f)?,
+            None => apriori.run_observed(data, log, &budget, f),
+        };
+        out.finish()?;
+        Ok(status)
     }
 }
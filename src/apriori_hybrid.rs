@@ -1,25 +1,50 @@
-use std::{error::Error, fs::File, path::PathBuf};
+use std::{error::Error, path::PathBuf};
 
-use apriori::apriori_hybrid::AprioriHybrid;
+use apriori::{apriori_hybrid::AprioriHybrid, apriori_tid::TIDEncoding};
 use clap::Args;
 use datasets::transaction_set::TransactionSet;
 
-use crate::{get_writer, out_writer, Arguments};
+use crate::{
+    output::ItemsetWriter, passes::PassLog, report_status, Arguments, SwitchArg, mebibytes,
+};
 
 #[derive(Args)]
 pub struct AprioriHybridArgs {
     path: PathBuf,
     support_count: u64,
-    #[arg(default_value_t = 3)]
-    switch: usize,
+    /// The pass to switch to AprioriTID (> 2), or auto to estimate it
+    #[arg(default_value_t = SwitchArg::Pass(3))]
+    switch: SwitchArg,
+    /// The memory in MiB the TIDs may use when switching automatically
+    #[arg(long, value_parser = mebibytes)]
+    memory: Option<usize>,
+    /// Stores the TIDs as indices into the itemsets of each pass
+    #[arg(long, default_value_t = false)]
+    indices: bool,
 }
 
 impl AprioriHybridArgs {
     pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn Error>> {
-        let t = TransactionSet::from_dat(File::open(&self.path)?);
-        let mut out = get_writer(&a.output_file);
-        AprioriHybrid::new(self.support_count, self.switch)
-            .run_fn(&t, |v| out_writer(v, &mut out));
-        Ok(())
+        let t = TransactionSet::open(&self.path)?;
+        let mut out = ItemsetWriter::new(a, t.len())?.levelwise();
+        let encoding = if self.indices {
+            TIDEncoding::Indices
+        } else {
+            TIDEncoding::Itemsets
+        };
+        let mut hybrid = AprioriHybrid::with_switch(
+            self.support_count,
+            self.switch.switch_pass(self.memory),
+            encoding,
+        )
+        .lengths(a.lengths()?);
+        let mut log = PassLog::new(a);
+        let status = hybrid.run_observed(&t, &mut log, &a.budget(), |v, n| out.write(v, n));
+        out.finish()?;
+        if let Some(n) = hybrid.switched() {
+            eprintln!("Switched to AprioriTID at pass {n}");
+        }
+        report_status(status);
+        log.finish(a)
     }
 }
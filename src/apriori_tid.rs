@@ -1,22 +1,43 @@
-use std::{fs::File, path::PathBuf};
+use std::path::PathBuf;
 
-use apriori::apriori_tid::AprioriTID;
+use apriori::apriori_tid::{AprioriTID, TIDEncoding};
 use clap::Args;
 use datasets::transaction_set::TransactionSet;
 
-use crate::{get_writer, out_writer, Arguments};
+use crate::{
+    checkpoint::CheckpointArgs, output::ItemsetWriter, passes::PassLog, report_status, Arguments,
+};
 
 #[derive(Args)]
 pub struct AprioriTIDArgs {
     path: PathBuf,
     support_count: u64,
+    /// Stores the TIDs as indices into the itemsets of each pass
+    #[arg(long, default_value_t = false)]
+    indices: bool,
+    #[command(flatten)]
+    checkpoint: CheckpointArgs,
 }
 
 impl AprioriTIDArgs {
     pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn std::error::Error>> {
-        let t = TransactionSet::from_dat(File::open(&self.path)?);
-        let mut out = get_writer(&a.output_file);
-        AprioriTID::new(self.support_count).run_fn(&t, |v| out_writer(v, &mut out));
-        Ok(())
+        let t = TransactionSet::open(&self.path)?;
+        let mut out = ItemsetWriter::new(a, t.len())?.levelwise();
+        let encoding = if self.indices {
+            TIDEncoding::Indices
+        } else {
+            TIDEncoding::Itemsets
+        };
+        let mut log = PassLog::new(a);
+        let tid = AprioriTID::with_encoding(self.support_count, encoding).lengths(a.lengths()?);
+        let budget = a.budget();
+        let f = |v: &[usize], n| out.write(v, n);
+        let status = match self.checkpoint.checkpointer() {
+            Some(mut c) => tid.run_checkpointed(&t, &mut log, &budget, &mut c, f)?,
+            None => tid.run_observed(&t, &mut log, &budget, f),
+        };
+        out.finish()?;
+        report_status(status);
+        log.finish(a)
     }
 }
\ No newline at end of file
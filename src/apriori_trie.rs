@@ -1,27 +1,60 @@
-use std::{fs::File, path::PathBuf};
+use std::path::PathBuf;
 
-use apriori::apriori_trie::AprioriT;
+use apriori::apriori_trie::{AprioriT, Trimming};
 use clap::Args;
-use datasets::transaction_set::TransactionSet;
+use datasets::{
+    transaction_set::TransactionSet,
+    transaction_source::{FileSource, TransactionSource},
+};
 
-use crate::{get_writer, out_writer, Arguments};
+use crate::{output::ItemsetWriter, passes::PassLog, report_status, threads, Arguments};
 
 #[derive(Args)]
 pub struct AprioriTrieArgs {
     path: PathBuf,
     support_count: u64,
+    #[arg(default_value_t = 1, value_parser = threads)]
+    threads: usize,
+    /// Removes the items that are in no candidate from the transactions before each pass
+    #[arg(long, default_value_t = false)]
+    trim: bool,
+    /// Trims, and keeps a trimmed copy of the transactions once it has at most this fraction of the items
+    #[arg(long)]
+    project: Option<f64>,
+    /// Reads the file again on every pass instead of loading it into memory
+    #[arg(long, default_value_t = false)]
+    stream: bool,
 }
 impl AprioriTrieArgs {
     pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn std::error::Error>> {
-        let f = File::open(&self.path)?;
-        let data = TransactionSet::from_dat(f);
-        let mut result = AprioriT::new(self.support_count);
-        result.run(&data);
+        let trimming = match (self.project, self.trim) {
+            (Some(f), _) => Trimming::Project(f),
+            (None, true) => Trimming::Filter,
+            (None, false) => Trimming::None,
+        };
+        let lengths = a.lengths()?;
+        let mut result =
+            AprioriT::with_threads(self.support_count, trimming, self.threads).lengths(lengths);
+        let mut log = PassLog::new(a);
+        let budget = a.budget();
+        let (status, transactions) = if self.stream {
+            let data = FileSource::open(&self.path)?;
+            (result.run_observed(&data, &mut log, &budget), data.num_transactions()?)
+        } else {
+            let data = TransactionSet::open(&self.path)?;
+            (result.run_observed(&data, &mut log, &budget), data.len())
+        };
         let trie = result.trie();
-        let mut out = get_writer(&a.output_file);
-        trie.for_each(self.support_count, |v| {
-            out_writer(v, &mut out);
+        let mut out = ItemsetWriter::new(a, transactions)?;
+        // The run counts the itemsets of a whole pass, so it can find more than the limit
+        let max = a.max_itemsets.unwrap_or(usize::MAX);
+        trie.for_each_support(self.support_count, |v, n| {
+            if out.written() < max && lengths.contains(v.len()) {
+                out.write(v, n);
+            }
         });
-        Ok(())
+        out.finish()?;
+        report_status(status);
+        log.finish(a)
     }
 }
\ No newline at end of file
@@ -0,0 +1,331 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ::apriori::{
+    apriori::{Apriori, CountingStructure},
+    apriori_hybrid::{AprioriHybrid, SwitchPass, available_memory},
+    apriori_tid::{AprioriTID, TIDEncoding},
+    apriori_trie::{AprioriT, Trimming},
+    observer::PassStats,
+};
+use ::count_distribution::{
+    count_distribution::CountDistribution, count_distribution_hybrid::CountDistributionHybrid,
+};
+use ::fp_growth::fp_growth::FPGrowth;
+use clap::{Args, ValueEnum};
+use datasets::{budget::Budget, transaction_set::TransactionSet};
+use maxminer::{mafia::Mafia, max_miner::MaxMiner};
+use serde::Serialize;
+
+use crate::{
+    Arguments, get_writer, memory,
+    passes::{PassReport, ms},
+    threads,
+};
+
+#[derive(Args)]
+pub struct BenchArgs {
+    path: PathBuf,
+    /// The support counts to run at
+    #[arg(short, long, value_delimiter = ',', required = true)]
+    supports: Vec<u64>,
+    /// The algorithms to run
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "apriori,apriori-trie,fp-growth"
+    )]
+    algorithms: Vec<Algorithm>,
+    /// The runs before timing, which are not reported
+    #[arg(long, default_value_t = 1)]
+    warmup: usize,
+    /// The timed runs of each algorithm at each support
+    #[arg(short, long, default_value_t = 3)]
+    repetitions: usize,
+    /// The threads of the algorithms that count in parallel
+    #[arg(long, default_value_t = 1, value_parser = threads)]
+    threads: usize,
+    /// Writes the report as CSV, with a row for each algorithm and support
+    #[arg(long)]
+    csv: Option<PathBuf>,
+    /// Writes the report as JSON, with the stats of every pass
+    #[arg(long)]
+    json: Option<PathBuf>,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Algorithm {
+    Apriori,
+    AprioriFlatTrie,
+    AprioriTid,
+    AprioriHybrid,
+    AprioriTrie,
+    CountDistribution,
+    CountDistributionHybrid,
+    FpGrowth,
+    MaxMiner,
+    Mafia,
+}
+impl Algorithm {
+    /// The name used on the command line
+    fn name(&self) -> String {
+        self.to_possible_value().unwrap().get_name().to_string()
+    }
+    /// Runs the algorithm, returning the number of itemsets found and the stats of each pass
+    /// (which only some of the algorithms report)
+    fn run(self, data: TransactionSet, support: u64, threads: usize) -> (usize, Vec<PassStats>) {
+        let mut found = 0;
+        let mut passes = Vec::new();
+        let count = |_: &[usize], _| found += 1;
+        let budget = Budget::default();
+        match self {
+            Algorithm::Apriori => {
+                Apriori::new(support).run_observed(&data, &mut passes, &budget, count);
+            }
+            Algorithm::AprioriFlatTrie => {
+                Apriori::with_counting(support, CountingStructure::FlatTrie)
+                    .run_observed(&data, &mut passes, &budget, count);
+            }
+            Algorithm::AprioriTid => {
+                AprioriTID::new(support).run_observed(&data, &mut passes, &budget, count);
+            }
+            Algorithm::AprioriHybrid => {
+                let switch = SwitchPass::Auto(available_memory());
+                AprioriHybrid::with_switch(support, switch, TIDEncoding::default())
+                    .run_observed(&data, &mut passes, &budget, count);
+            }
+            Algorithm::AprioriTrie => {
+                let mut a = AprioriT::with_threads(support, Trimming::None, threads);
+                a.run_observed(&data, &mut passes, &budget);
+                a.trie().for_each_support(support, count);
+            }
+            Algorithm::CountDistribution => {
+                let (c, _) = CountDistribution::new(Arc::new(data), threads, support)
+                    .run_observed(&mut passes, &budget);
+                found = c.iter().map(|c| c.len()).sum();
+            }
+            Algorithm::CountDistributionHybrid => {
+                let switch = SwitchPass::Auto(available_memory());
+                let cd = CountDistributionHybrid::with_switch(&data, threads, support, switch);
+                let (c, _, _) = cd.run_observed(&mut passes, &budget);
+                found = c.iter().map(|c| c.len()).sum();
+            }
+            Algorithm::FpGrowth => {
+                FPGrowth::new(support, data).run_budgeted(&budget, count);
+            }
+            Algorithm::MaxMiner => {
+                MaxMiner::new(support, data).run_budgeted(&budget, count);
+            }
+            Algorithm::Mafia => {
+                Mafia::new(support, data).run_budgeted(&budget, count);
+            }
+        }
+        (found, passes)
+    }
+}
+/// The runs of an algorithm at a support
+#[derive(Debug, Serialize)]
+struct Report {
+    algorithm: Algorithm,
+    support: u64,
+    /// The number of itemsets found
+    itemsets: usize,
+    /// The candidates counted in every pass (if the algorithm reports its passes)
+    candidates: Option<usize>,
+    times_ms: Vec<f64>,
+    mean_ms: f64,
+    min_ms: f64,
+    max_ms: f64,
+    /// The most bytes allocated at once during a run, more than before it
+    peak_memory: usize,
+    /// The passes of the fastest run
+    passes: Vec<PassReport>,
+}
+impl BenchArgs {
+    pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn Error>> {
+        if self.repetitions == 0 {
+            return Err("There must be at least one repetition".into());
+        }
+        let data = TransactionSet::open(&self.path)?;
+        if !cfg!(feature = "count-allocations") {
+            eprintln!("The peak memory is only measured when built with count-allocations");
+        }
+        memory::enable();
+        let mut reports = Vec::new();
+        for &support in &self.supports {
+            for &algorithm in &self.algorithms {
+                for _ in 0..self.warmup {
+                    algorithm.run(data.clone(), support, self.threads);
+                }
+                let mut times = Vec::new();
+                let mut peak_memory = 0;
+                let mut fastest = (Duration::MAX, 0, Vec::new());
+                for _ in 0..self.repetitions {
+                    // The copy of the data is made before the run, so it is not in the peak
+                    let copy = data.clone();
+                    let start_memory = memory::reset_peak();
+                    let start = Instant::now();
+                    let (found, passes) = algorithm.run(copy, support, self.threads);
+                    let time = start.elapsed();
+                    peak_memory = peak_memory.max(memory::peak_since(start_memory));
+                    times.push(time);
+                    if time < fastest.0 {
+                        fastest = (time, found, passes);
+                    }
+                }
+                let (_, itemsets, passes) = fastest;
+                let times_ms: Vec<_> = times.into_iter().map(ms).collect();
+                reports.push(Report {
+                    algorithm,
+                    support,
+                    itemsets,
+                    candidates: (!passes.is_empty())
+                        .then(|| passes.iter().map(|p| p.candidates).sum()),
+                    mean_ms: times_ms.iter().sum::<f64>() / times_ms.len() as f64,
+                    min_ms: times_ms.iter().copied().fold(f64::MAX, f64::min),
+                    max_ms: times_ms.iter().copied().fold(0.0, f64::max),
+                    times_ms,
+                    peak_memory,
+                    passes: passes.iter().map(PassReport::from).collect(),
+                });
+            }
+        }
+        let mut out = get_writer(&a.output_file)?;
+        write_table(&mut out, &reports)?;
+        out.flush()?;
+        if let Some(path) = &self.csv {
+            write_csv(&mut BufWriter::new(File::create(path)?), &reports)?;
+        }
+        if let Some(path) = &self.json {
+            let mut out = BufWriter::new(File::create(path)?);
+            serde_json::to_writer_pretty(&mut out, &reports)?;
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+/// Writes a row for each report, with the passes under it
+fn write_table(out: &mut impl Write, reports: &[Report]) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "{:<26} {:>8} {:>9} {:>11} {:>11} {:>11} {:>11} {:>11}",
+        "algorithm", "support", "itemsets", "candidates", "mean ms", "min ms", "max ms", "peak MiB"
+    )?;
+    for r in reports {
+        let candidates = r.candidates.map_or("-".to_string(), |c| c.to_string());
+        writeln!(
+            out,
+            "{:<26} {:>8} {:>9} {:>11} {:>11.2} {:>11.2} {:>11.2} {:>11.2}",
+            r.algorithm.name(),
+            r.support,
+            r.itemsets,
+            candidates,
+            r.mean_ms,
+            r.min_ms,
+            r.max_ms,
+            r.peak_memory as f64 / (1 << 20) as f64
+        )?;
+        for p in &r.passes {
+            writeln!(
+                out,
+                "  pass {:<3} {:>12} candidates {:>10} pruned {:>10} frequent {:>11.2} ms",
+                p.pass, p.candidates, p.pruned, p.frequent, p.time_ms
+            )?;
+        }
+    }
+    Ok(())
+}
+/// Writes a row for each report, with the times of the passes separated by semicolons
+fn write_csv(out: &mut impl Write, reports: &[Report]) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "algorithm,support,itemsets,candidates,mean_ms,min_ms,max_ms,peak_memory,pass_ms"
+    )?;
+    for r in reports {
+        let passes: Vec<_> = r
+            .passes
+            .iter()
+            .map(|p| format!("{:.3}", p.time_ms))
+            .collect();
+        writeln!(
+            out,
+            "{},{},{},{},{:.3},{:.3},{:.3},{},{}",
+            r.algorithm.name(),
+            r.support,
+            r.itemsets,
+            r.candidates.map_or(String::new(), |c| c.to_string()),
+            r.mean_ms,
+            r.min_ms,
+            r.max_ms,
+            r.peak_memory,
+            passes.join(";")
+        )?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum;
+    use datasets::transaction_set::TransactionSet;
+
+    use super::{Algorithm, Report, write_csv, write_table};
+    use crate::passes::PassReport;
+
+    #[test]
+    fn test_algorithms() {
+        let data = TransactionSet::new(
+            vec![vec![0, 1, 2], vec![0, 1], vec![1, 2], vec![0, 1, 2, 3]],
+            4,
+        );
+        // {0}, {1}, {2}, {0, 1}, {0, 2}, {1, 2}, and {0, 1, 2} have a support of at least 2
+        for &algorithm in Algorithm::value_variants() {
+            let (found, passes) = algorithm.run(data.clone(), 2, 2);
+            let expected = match algorithm {
+                Algorithm::MaxMiner | Algorithm::Mafia => 1,
+                _ => 7,
+            };
+            assert_eq!(found, expected, "{}", algorithm.name());
+            if !passes.is_empty() {
+                assert_eq!(passes.iter().map(|p| p.frequent).sum::<usize>(), 7);
+            }
+        }
+    }
+    #[test]
+    fn test_reports() {
+        let (_, passes) = Algorithm::Apriori.run(TransactionSet::new(vec![vec![0, 1]], 2), 1, 1);
+        let report = Report {
+            algorithm: Algorithm::AprioriTrie,
+            support: 1,
+            itemsets: 3,
+            candidates: Some(3),
+            times_ms: vec![1.0, 3.0],
+            mean_ms: 2.0,
+            min_ms: 1.0,
+            max_ms: 3.0,
+            peak_memory: 2048,
+            passes: passes.iter().map(PassReport::from).collect(),
+        };
+        let mut table = Vec::new();
+        write_table(&mut table, std::slice::from_ref(&report)).unwrap();
+        let table = String::from_utf8(table).unwrap();
+        let lines: Vec<_> = table.lines().collect();
+        assert!(lines[0].starts_with("algorithm"));
+        assert!(lines[1].starts_with("apriori-trie"));
+        // A line for each pass
+        assert_eq!(lines.len(), 2 + report.passes.len());
+        let mut csv = Vec::new();
+        write_csv(&mut csv, std::slice::from_ref(&report)).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let row: Vec<_> = csv.lines().nth(1).unwrap().split(',').collect();
+        assert_eq!(row[..8], ["apriori-trie", "1", "3", "3", "2.000", "1.000", "3.000", "2048"]);
+        assert_eq!(row[8].split(';').count(), report.passes.len());
+    }
+}
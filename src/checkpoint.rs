@@ -0,0 +1,26 @@
+use std::path::PathBuf;
+
+use apriori::checkpoint::Checkpointer;
+use clap::Args;
+
+/// The options to save the state of a level-wise run after each pass, and resume it
+#[derive(Args)]
+pub struct CheckpointArgs {
+    /// Saves the frequent itemsets (and TIDs) to this file after each pass
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Continues from the last pass saved in the checkpoint, writing all the itemsets again
+    #[arg(long, requires = "checkpoint", default_value_t = false)]
+    resume: bool,
+}
+impl CheckpointArgs {
+    /// The checkpointer to run with (if a checkpoint was given)
+    pub fn checkpointer(&self) -> Option<Checkpointer> {
+        let path = self.checkpoint.as_ref()?;
+        Some(if self.resume {
+            Checkpointer::resume(path)
+        } else {
+            Checkpointer::new(path)
+        })
+    }
+}
@@ -0,0 +1,45 @@
+use std::{io, path::PathBuf};
+
+use clap::Args;
+use datasets::constraint::{ItemConstraints, ItemValues, ValueBound};
+
+/// The options for the items the itemsets found must or must not have, as item ids,
+/// and for the bounds on their values
+#[derive(Args)]
+pub struct ItemConstraintArgs {
+    /// Only finds itemsets with all of these items
+    #[arg(long, value_delimiter = ',')]
+    include: Vec<usize>,
+    /// Only finds itemsets with none of these items
+    #[arg(long, value_delimiter = ',')]
+    exclude: Vec<usize>,
+    /// Only finds itemsets with no items other than these
+    #[arg(long, value_delimiter = ',')]
+    allowed: Option<Vec<usize>>,
+    /// Only finds itemsets with at least one of these items
+    #[arg(long, value_delimiter = ',')]
+    any_of: Vec<usize>,
+    /// Reads the value of each item (such as its price) from this file,
+    /// with line i being the value of item i
+    #[arg(long)]
+    values: Option<PathBuf>,
+    /// Only finds itemsets whose values are within these bounds,
+    /// like sum<=100 or avg>=20 (with sum, avg, min, or max)
+    #[arg(long, value_delimiter = ',', requires = "values")]
+    bound: Vec<ValueBound>,
+}
+impl ItemConstraintArgs {
+    /// The constraints to run with, reading the values of the items if there are any
+    pub fn constraints(&self) -> io::Result<ItemConstraints> {
+        let constraints = ItemConstraints::new(
+            self.include.clone(),
+            self.exclude.clone(),
+            self.allowed.clone(),
+            self.any_of.clone(),
+        );
+        let Some(path) = &self.values else {
+            return Ok(constraints);
+        };
+        Ok(constraints.bounds(ItemValues::open(path)?, self.bound.clone()))
+    }
+}
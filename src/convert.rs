@@ -0,0 +1,21 @@
+use std::{error::Error, path::PathBuf};
+
+use clap::Args;
+use datasets::{binary::write_binary, transaction_source::FileSource};
+
+/// Converts a dataset into the binary format
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// The .dat file (or binary dataset) to convert
+    path: PathBuf,
+    /// Where to write the binary dataset
+    output: PathBuf,
+}
+impl ConvertArgs {
+    pub fn run(&self) -> Result<(), Box<dyn Error>> {
+        // Streams the input so that it does not have to fit in memory
+        let source = FileSource::open(&self.path)?;
+        write_binary(&source, &self.output)?;
+        Ok(())
+    }
+}
@@ -1,30 +1,71 @@
-use std::{error::Error, fs::File, path::PathBuf, sync::Arc};
+use std::{error::Error, io, path::PathBuf, sync::Arc};
 
+use apriori::candidates::Candidates;
 use clap::Args;
 use count_distribution::count_distribution::CountDistribution;
-use datasets::transaction_set::TransactionSet;
+use datasets::{
+    budget::{Budget, Status},
+    transaction_set::TransactionSet,
+    transaction_source::{FileSource, TransactionSource},
+};
 
-use crate::{get_writer, out_writer, Arguments};
+use crate::{
+    checkpoint::CheckpointArgs, output::ItemsetWriter, passes::PassLog, report_status, threads,
+    Arguments,
+};
 
 #[derive(Args)]
 pub struct CountDistributionArgs {
     path: PathBuf,
     support_count: u64,
-    #[arg(default_value_t = 1)]
+    #[arg(default_value_t = 1, value_parser = threads)]
     threads: usize,
+    /// Reads the file again on every pass instead of loading it into memory
+    #[arg(long, default_value_t = false)]
+    stream: bool,
+    #[command(flatten)]
+    checkpoint: CheckpointArgs,
 }
 
 impl CountDistributionArgs {
     pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn Error>> {
-        let t = TransactionSet::from_dat(File::open(&self.path)?);
-        let candidates = CountDistribution::new(Arc::new(t), self.threads, self.support_count);
-        let c = candidates.run();
-        let mut out = get_writer(&a.output_file);
-        for c in c {
-            for c in c.iter() {
-                out_writer(c, &mut out);
-            }
+        let mut log = PassLog::new(a);
+        let budget = a.budget();
+        let lengths = a.lengths()?;
+        let ((c, status), transactions) = if self.stream {
+            let t = FileSource::open(&self.path)?;
+            let transactions = t.num_transactions()?;
+            let cd = CountDistribution::new(Arc::new(t), self.threads, self.support_count)
+                .lengths(lengths);
+            (self.mine(cd, &mut log, &budget)?, transactions)
+        } else {
+            let t = TransactionSet::open(&self.path)?;
+            let transactions = t.len();
+            let cd = CountDistribution::new(Arc::new(t), self.threads, self.support_count)
+                .lengths(lengths);
+            (self.mine(cd, &mut log, &budget)?, transactions)
+        };
+        let mut out = ItemsetWriter::new(a, transactions)?.levelwise();
+        // The run counts the itemsets of a whole pass, so it can find more than the limit
+        let max = a.max_itemsets.unwrap_or(usize::MAX);
+        let found = c.iter().flat_map(|c| c.iter());
+        for (v, &n) in found.filter(|(v, _)| lengths.contains(v.len())).take(max) {
+            out.write(v, n);
         }
-        Ok(())
+        out.finish()?;
+        report_status(status);
+        log.finish(a)
     }
-}
\ No newline at end of file
+    /// Runs Count Distribution, saving checkpoints if asked to
+    fn mine<T: TransactionSource + Send + Sync + 'static>(
+        &self,
+        cd: CountDistribution<T>,
+        log: &mut PassLog,
+        budget: &Budget,
+    ) -> io::Result<(Vec<Arc<Candidates>>, Status)> {
+        match self.checkpoint.checkpointer() {
+            Some(mut c) => cd.run_checkpointed(log, budget, &mut c),
+            None => Ok(cd.run_observed(log, budget)),
+        }
+    }
+}
@@ -1,32 +1,52 @@
-use std::{error::Error, fs::File, path::PathBuf};
+use std::{error::Error, path::PathBuf};
 
 use clap::Args;
 use count_distribution::count_distribution_hybrid::CountDistributionHybrid;
 use datasets::transaction_set::TransactionSet;
 
-use crate::{get_writer, out_writer, Arguments};
+use crate::{
+    output::ItemsetWriter, passes::PassLog, report_status, threads, Arguments, SwitchArg, mebibytes,
+};
 
 #[derive(Args)]
 pub struct CountDistributionHybridArgs {
     path: PathBuf,
     support_count: u64,
-    #[arg(default_value_t = 3)]
-    switch: usize,
-    #[arg(default_value_t = 1)]
+    /// The pass to switch to AprioriTID (> 2), or auto to estimate it
+    #[arg(default_value_t = SwitchArg::Pass(3))]
+    switch: SwitchArg,
+    #[arg(default_value_t = 1, value_parser = threads)]
     threads: usize,
+    /// The memory in MiB the TIDs may use when switching automatically
+    #[arg(long, value_parser = mebibytes)]
+    memory: Option<usize>,
 }
 
 impl CountDistributionHybridArgs {
     pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn Error>> {
-        let t = TransactionSet::from_dat(File::open(&self.path)?);
-        let candidates = CountDistributionHybrid::new(&t, self.threads, self.support_count, self.switch);
-        let c = candidates.run();
-        let mut out = get_writer(&a.output_file);
-        for c in c {
-            for c in c.iter() {
-                out_writer(c, &mut out);
-            }
+        let t = TransactionSet::open(&self.path)?;
+        let lengths = a.lengths()?;
+        let candidates = CountDistributionHybrid::with_switch(
+            &t,
+            self.threads,
+            self.support_count,
+            self.switch.switch_pass(self.memory),
+        )
+        .lengths(lengths);
+        let mut log = PassLog::new(a);
+        let (c, switched, status) = candidates.run_observed(&mut log, &a.budget());
+        if let Some(n) = switched {
+            eprintln!("Switched to AprioriTID at pass {n}");
         }
-        Ok(())
+        let mut out = ItemsetWriter::new(a, t.len())?.levelwise();
+        // The run counts the itemsets of a whole pass, so it can find more than the limit
+        let max = a.max_itemsets.unwrap_or(usize::MAX);
+        let found = c.iter().flat_map(|c| c.iter());
+        for (v, &n) in found.filter(|(v, _)| lengths.contains(v.len())).take(max) {
+            out.write(v, n);
+        }
+        out.finish()?;
+        report_status(status);
+        log.finish(a)
     }
 }
\ No newline at end of file
@@ -0,0 +1,31 @@
+use std::{error::Error, path::PathBuf};
+
+use apriori::cumulate::Cumulate;
+use clap::Args;
+use datasets::{taxonomy::Taxonomy, transaction_set::TransactionSet};
+
+use crate::{output::ItemsetWriter, passes::PassLog, report_status, Arguments};
+
+#[derive(Args)]
+pub struct CumulateArgs {
+    path: PathBuf,
+    /// The taxonomy, with a child item and its parent on each line
+    taxonomy: PathBuf,
+    /// The minimum support count of each level, starting with the items with no parent.
+    /// The last is used for the levels below it.
+    #[arg(required = true, value_delimiter = ',')]
+    support_counts: Vec<u64>,
+}
+impl CumulateArgs {
+    pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn Error>> {
+        let taxonomy = Taxonomy::open(&self.taxonomy)?;
+        let t = TransactionSet::open(&self.path)?;
+        let mut out = ItemsetWriter::new(a, t.len())?.levelwise();
+        let mut log = PassLog::new(a);
+        let cumulate = Cumulate::new(taxonomy, self.support_counts.clone()).lengths(a.lengths()?);
+        let status = cumulate.run_observed(&t, &mut log, &a.budget(), |v, n| out.write(v, n));
+        out.finish()?;
+        report_status(status);
+        log.finish(a)
+    }
+}
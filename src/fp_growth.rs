@@ -1,23 +1,28 @@
-use std::{error::Error, fs::File, path::PathBuf};
+use std::{error::Error, path::PathBuf};
 
 use clap::Args;
 use datasets::transaction_set::TransactionSet;
 use fp_growth::fp_growth::FPGrowth;
 
-use crate::{Arguments, get_writer, out_writer};
+use crate::{Arguments, constraint::ItemConstraintArgs, output::ItemsetWriter, report_status};
 
 #[derive(Args)]
 pub struct FPGrowthArgs {
     path: PathBuf,
     support_count: u64,
+    #[command(flatten)]
+    constraints: ItemConstraintArgs,
 }
 impl FPGrowthArgs {
     pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn Error>> {
-        let f = File::open(&self.path)?;
-        let data = TransactionSet::from_dat(f);
-        let fp_growth = FPGrowth::new(self.support_count, data);
-        let mut out = get_writer(&a.output_file);
-        fp_growth.run_fn(|v| out_writer(v, &mut out));
+        let data = TransactionSet::open(&self.path)?;
+        let mut out = ItemsetWriter::new(a, data.len())?;
+        let fp_growth = FPGrowth::new(self.support_count, data)
+            .lengths(a.lengths()?)
+            .constraints(self.constraints.constraints()?);
+        let status = fp_growth.run_budgeted(&a.budget(), |v, n| out.write(v, n));
+        out.finish()?;
+        report_status(status);
         Ok(())
     }
 }
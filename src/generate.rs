@@ -0,0 +1,66 @@
+use std::{error::Error, io::Write};
+
+use clap::Args;
+use datasets::quest::{Quest, QuestParams};
+
+use crate::{Arguments, get_writer};
+
+/// The parameters of the generator, named as in the paper
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// The number of transactions
+    #[arg(short = 'D', long, default_value_t = 100_000)]
+    transactions: usize,
+    /// The average size of a transaction
+    #[arg(short = 'T', long, default_value_t = 10.0)]
+    avg_len: f64,
+    /// The average size of the potentially large itemsets
+    #[arg(short = 'I', long, default_value_t = 4.0)]
+    avg_pattern_len: f64,
+    /// The number of items
+    #[arg(short = 'N', long, default_value_t = 1000)]
+    items: usize,
+    /// The number of potentially large itemsets
+    #[arg(short = 'L', long, default_value_t = 2000)]
+    patterns: usize,
+    /// The mean fraction of each potentially large itemset's items taken from the one before
+    #[arg(long, default_value_t = 0.5)]
+    correlation: f64,
+    /// The mean chance that an item is dropped from a potentially large itemset in a transaction
+    #[arg(long, default_value_t = 0.5)]
+    corruption: f64,
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+impl GenerateArgs {
+    pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn Error>> {
+        if self.items == 0 || self.patterns == 0 {
+            return Err("There must be at least one item and one potentially large itemset".into());
+        }
+        let quest = Quest::new(QuestParams {
+            transactions: self.transactions,
+            avg_len: self.avg_len,
+            avg_pattern_len: self.avg_pattern_len,
+            patterns: self.patterns,
+            items: self.items,
+            correlation: self.correlation,
+            corruption: self.corruption,
+            seed: self.seed,
+        });
+        let mut out = get_writer(&a.output_file)?;
+        let mut line = String::new();
+        for t in quest {
+            line.clear();
+            for (i, n) in t.iter().enumerate() {
+                if i > 0 {
+                    line.push(' ');
+                }
+                line += &n.to_string();
+            }
+            line.push('\n');
+            out.write_all(line.as_bytes())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
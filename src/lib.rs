@@ -2,28 +2,56 @@ mod apriori;
 mod apriori_hybrid;
 mod apriori_tid;
 mod apriori_trie;
+mod bench;
+mod checkpoint;
+mod constraint;
+mod convert;
 mod count_distribution;
 mod count_distribution_hybrid;
+mod cumulate;
 mod fp_growth;
+mod generate;
+mod mafia;
 mod maxminer;
+pub mod memory;
+mod ms_apriori;
+pub mod output;
+mod passes;
+mod stats;
 
 use std::{
     error::Error,
-    fs::{self, File},
+    fmt::Display,
+    fs::File,
     io::{self, BufWriter},
     path::PathBuf,
+    str::FromStr,
+    time::Duration,
 };
 
 use apriori::AprioriArgs;
 use apriori_hybrid::AprioriHybridArgs;
 use apriori_tid::AprioriTIDArgs;
 use apriori_trie::AprioriTrieArgs;
+use bench::BenchArgs;
 use clap::{Parser, Subcommand};
+use convert::ConvertArgs;
 use count_distribution::CountDistributionArgs;
 use count_distribution_hybrid::CountDistributionHybridArgs;
+use cumulate::CumulateArgs;
+use datasets::{
+    budget::{Budget, Status},
+    constraint::Lengths,
+};
 use fp_growth::FPGrowthArgs;
+use generate::GenerateArgs;
+use output::{OutputFormat, SortOrder};
+use ms_apriori::MSAprioriArgs;
+use stats::StatsArgs;
+
+use ::apriori::apriori_hybrid::{SwitchPass, available_memory};
 
-use crate::maxminer::MaxMinerArgs;
+use crate::{mafia::MafiaArgs, maxminer::MaxMinerArgs};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -35,18 +63,92 @@ pub struct Arguments {
     pub time: bool,
     #[arg(short, long, global = true)]
     pub output_file: Option<PathBuf>,
+    /// How the frequent itemsets are written
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output_format: OutputFormat,
+    /// A file with the name of each item, one per line, to write along with the ids
+    #[arg(long, global = true)]
+    pub labels: Option<PathBuf>,
+    /// Sorts the frequent itemsets (and the items in each), so every run writes the same output
+    #[arg(long, global = true, value_enum, default_value_t = SortOrder::None)]
+    pub sort: SortOrder,
+    /// Prints the candidates and frequent itemsets of each pass of a level-wise algorithm
+    #[arg(short, long, global = true, default_value_t = false)]
+    pub verbose: bool,
+    /// Writes the stats of each pass of a level-wise algorithm to a JSON file
+    #[arg(long, global = true)]
+    pub stats_json: Option<PathBuf>,
+    /// Stops mining after this many seconds, keeping the itemsets found so far
+    #[arg(long, global = true, value_parser = seconds)]
+    pub time_limit: Option<Duration>,
+    /// Stops mining before a pass with more candidates than this
+    #[arg(long, global = true)]
+    pub max_candidates: Option<usize>,
+    /// Stops mining once this many itemsets are written
+    #[arg(long, global = true)]
+    pub max_itemsets: Option<usize>,
+    /// Only finds itemsets with at least this many items
+    #[arg(long, global = true)]
+    pub min_len: Option<usize>,
+    /// Only finds itemsets with at most this many items, without searching past them
+    #[arg(long, global = true)]
+    pub max_len: Option<usize>,
+}
+impl Arguments {
+    /// The budget of a run from the limits given
+    pub fn budget(&self) -> Budget {
+        Budget {
+            time_limit: self.time_limit,
+            max_candidates: self.max_candidates,
+            max_itemsets: self.max_itemsets,
+            ..Default::default()
+        }
+    }
+    /// The lengths of the itemsets to find, which must include at least one length
+    pub fn lengths(&self) -> io::Result<Lengths> {
+        let lengths = Lengths::new(self.min_len, self.max_len);
+        if lengths.max == 0 || lengths.min > lengths.max {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--max-len must be at least 1 and at least --min-len",
+            ));
+        }
+        Ok(lengths)
+    }
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Runs Apriori, counting the candidates of each pass with a hash tree
     Apriori(AprioriArgs),
+    /// Runs AprioriTID, which counts the candidates from the candidates of each transaction
     AprioriTID(AprioriTIDArgs),
+    /// Runs AprioriHybrid, which switches from Apriori to AprioriTID
     AprioriHybrid(AprioriHybridArgs),
+    /// Runs Count Distribution, where each thread counts a part of the transactions
     CountDistribution(CountDistributionArgs),
+    /// Runs Apriori, counting the candidates with a hash trie
     AprioriTrie(AprioriTrieArgs),
+    /// Runs the parallel version of AprioriHybrid
     CountDistributionHybrid(CountDistributionHybridArgs),
+    /// Runs FP-Growth, which mines the frequent itemsets from an FP-tree
     FPGrowth(FPGrowthArgs),
+    /// Runs MaxMiner, which finds the maximal frequent itemsets
     MaxMiner(MaxMinerArgs),
+    /// Runs MAFIA, which finds the maximal frequent itemsets
+    Mafia(MafiaArgs),
+    /// Runs MSApriori, where each item has its own minimum support
+    MSApriori(MSAprioriArgs),
+    /// Runs Cumulate, which finds itemsets over the levels of a taxonomy of the items
+    Cumulate(CumulateArgs),
+    /// Converts a dataset into the binary format, which every algorithm can read
+    Convert(ConvertArgs),
+    /// Prints the shape of a dataset, such as its transaction lengths and item supports
+    Stats(StatsArgs),
+    /// Generates a synthetic dataset like the IBM Quest generator, such as T10I4D100K
+    Generate(GenerateArgs),
+    /// Times algorithms on a dataset at a list of supports, with the memory and passes they use
+    Bench(BenchArgs),
 }
 impl Commands {
     pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn Error>> {
@@ -63,34 +165,124 @@ impl Commands {
             }
             Commands::FPGrowth(fpgrowth_args) => fpgrowth_args.run(a)?,
             Commands::MaxMiner(max_miner_args) => max_miner_args.run(a)?,
+            Commands::Mafia(mafia_args) => mafia_args.run(a)?,
+            Commands::MSApriori(ms_apriori_args) => ms_apriori_args.run(a)?,
+            Commands::Cumulate(cumulate_args) => cumulate_args.run(a)?,
+            Commands::Convert(convert_args) => convert_args.run()?,
+            Commands::Stats(stats_args) => stats_args.run(a)?,
+            Commands::Generate(generate_args) => generate_args.run(a)?,
+            Commands::Bench(bench_args) => bench_args.run(a)?,
         };
         Ok(())
     }
 }
 
-pub fn print_candidate<'a, T: Iterator<Item = &'a Vec<usize>>>(v: T) {
-    for v in v {
-        for &e in v {
-            print!("{e} ");
+/// The pass to switch to AprioriTID, or auto to decide with a heuristic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchArg {
+    Auto,
+    Pass(usize),
+}
+impl SwitchArg {
+    /// Gets when to switch, with the memory for the heuristic in bytes
+    /// (defaults to the available memory)
+    pub fn switch_pass(&self, memory: Option<usize>) -> SwitchPass {
+        match self {
+            SwitchArg::Auto => SwitchPass::Auto(memory.unwrap_or_else(available_memory)),
+            &SwitchArg::Pass(n) => SwitchPass::Fixed(n),
         }
-        println!()
     }
 }
+impl FromStr for SwitchArg {
+    type Err = String;
 
-pub fn get_writer(path: &Option<PathBuf>) -> Box<dyn io::Write> {
-    if let Some(path) = path {
-        let file = File::create(path).unwrap();
-        return Box::new(BufWriter::new(file));
-    } else {
-        return Box::new(io::stdout().lock());
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "auto" {
+            return Ok(SwitchArg::Auto);
+        }
+        let n = s.parse().map_err(|e: std::num::ParseIntError| e.to_string())?;
+        // Passes 1 and 2 are always counted by Apriori
+        if n <= 2 {
+            return Err(format!("The pass to switch at must be more than 2, not {n}"));
+        }
+        Ok(SwitchArg::Pass(n))
+    }
+}
+impl Display for SwitchArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SwitchArg::Auto => write!(f, "auto"),
+            SwitchArg::Pass(n) => write!(f, "{n}"),
+        }
     }
 }
 
-pub fn out_writer(v: &[usize], out: &mut impl std::io::Write) {
-    let mut string = String::new();
-    for &e in v {
-        string += format!("{e} ").as_str();
+/// Parses a number of threads, which must be at least 1
+fn threads(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("There must be at least one thread".to_string()),
+        n => n.map_err(|e: std::num::ParseIntError| e.to_string()),
+    }
+}
+/// Parses a number of MiB into bytes, which must fit in a usize
+fn mebibytes(s: &str) -> Result<usize, String> {
+    let n: usize = s.parse().map_err(|e: std::num::ParseIntError| e.to_string())?;
+    n.checked_mul(1 << 20).ok_or_else(|| format!("{n} MiB is more memory than can be used"))
+}
+/// Parses a number of seconds, which must be finite and not negative
+fn seconds(s: &str) -> Result<Duration, String> {
+    let n: f64 = s.parse().map_err(|e: std::num::ParseFloatError| e.to_string())?;
+    let invalid = |_| format!("Not a number of seconds of at least 0: {s}");
+    Duration::try_from_secs_f64(n).map_err(invalid)
+}
+/// Tells the user if a run was truncated, since its output is then incomplete
+pub fn report_status(status: Status) {
+    if let Status::Truncated(t) = status {
+        eprintln!("Truncated: {t}");
+    }
+}
+
+/// Creates the output file, or writes to stdout without one
+pub fn get_writer(path: &Option<PathBuf>) -> io::Result<Box<dyn io::Write>> {
+    Ok(if let Some(path) = path {
+        let file = File::create(path)?;
+        Box::new(BufWriter::new(file))
+    } else {
+        Box::new(io::stdout().lock())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::{Arguments, get_writer};
+
+    #[test]
+    fn test_lengths() {
+        let lengths = |v: &[&str]| {
+            let args = ["data_mining", "fp-growth", "data.dat", "2"].iter().chain(v);
+            Arguments::try_parse_from(args).unwrap().lengths().map(|l| (l.min, l.max))
+        };
+        assert_eq!(lengths(&[]).unwrap(), (1, usize::MAX));
+        assert_eq!(lengths(&["--min-len", "2", "--max-len", "2"]).unwrap(), (2, 2));
+        assert!(lengths(&["--min-len", "3", "--max-len", "2"]).is_err());
+        assert!(lengths(&["--max-len", "0"]).is_err());
+    }
+    #[test]
+    fn test_memory() {
+        let parse = |m: &str| {
+            let args = ["data_mining", "apriori-hybrid", "data.dat", "2", "auto", "--memory", m];
+            Arguments::try_parse_from(args)
+        };
+        assert!(parse("3").is_ok());
+        assert!(parse(&(usize::MAX >> 19).to_string()).is_err());
+    }
+    #[test]
+    fn test_writer() {
+        let dir = std::env::temp_dir().join(format!("missing_{}", std::process::id()));
+        // The output cannot be created in a directory that does not exist
+        assert!(get_writer(&Some(dir.join("out.txt"))).is_err());
+        assert!(get_writer(&None).is_ok());
     }
-    string += "\n";
-    let _ = out.write(string.as_bytes());
 }
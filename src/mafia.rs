@@ -0,0 +1,24 @@
+use std::{error::Error, path::PathBuf};
+
+use clap::Args;
+use datasets::transaction_set::TransactionSet;
+use maxminer::mafia::Mafia;
+
+use crate::{Arguments, maxminer::MaximalOutput};
+
+#[derive(Args)]
+pub struct MafiaArgs {
+    path: PathBuf,
+    support_count: u64,
+    #[command(flatten)]
+    output: MaximalOutput,
+}
+impl MafiaArgs {
+    pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn Error>> {
+        let data = TransactionSet::open(&self.path)?;
+        let transactions = data.len();
+        let mafia = Mafia::new(self.support_count, data).lengths(a.lengths()?);
+        self.output
+            .write(mafia, &self.path, self.support_count, transactions, a)
+    }
+}
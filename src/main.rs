@@ -4,7 +4,14 @@ use std::
 
 use clap::Parser;
 use data_mining::Arguments;
+#[cfg(feature = "count-allocations")]
+use data_mining::memory::CountingAllocator;
 
+/// Counts the allocations for the bench subcommand.
+/// Every allocation goes through it, so it is only used with the count-allocations feature.
+#[cfg(feature = "count-allocations")]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Arguments::parse();
@@ -1,23 +1,121 @@
-use std::{error::Error, fs::File, path::PathBuf};
+use std::{error::Error, path::PathBuf};
 
-use clap::Args;
-use datasets::transaction_set::TransactionSet;
-use maxminer::max_miner::MaxMiner;
+use clap::{Args, ValueEnum};
+use datasets::{
+    transaction_set::TransactionSet,
+    transaction_source::{FileSource, TransactionSource},
+};
+use maxminer::{
+    max_miner::{ItemOrdering, MaxMiner, MaximalMiner},
+    verify::verify,
+};
 
-use crate::{Arguments, get_writer, out_writer};
+use crate::{
+    Arguments,
+    output::{ItemsetWriter, OutputFormat, SortOrder},
+    report_status,
+};
 
 #[derive(Args)]
 pub struct MaxMinerArgs {
     path: PathBuf,
     support_count: u64,
+    #[command(flatten)]
+    output: MaximalOutput,
+    /// How the tail items of each candidate group are ordered
+    #[arg(long, value_enum, default_value_t = OrderingArg::Dynamic)]
+    ordering: OrderingArg,
+    /// Reads the file again on every pass instead of loading it into memory
+    #[arg(long, default_value_t = false)]
+    stream: bool,
+}
+#[derive(Clone, Copy, ValueEnum)]
+enum OrderingArg {
+    /// By item id
+    Id,
+    /// By increasing support of the items
+    Support,
+    /// By increasing support with the head of each candidate group
+    Dynamic,
+}
+impl From<OrderingArg> for ItemOrdering {
+    fn from(value: OrderingArg) -> Self {
+        match value {
+            OrderingArg::Id => ItemOrdering::Id,
+            OrderingArg::Support => ItemOrdering::Support,
+            OrderingArg::Dynamic => ItemOrdering::Dynamic,
+        }
+    }
 }
 impl MaxMinerArgs {
     pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn Error>> {
-        let f = File::open(&self.path)?;
-        let data = TransactionSet::from_dat(f);
-        let max_miner = MaxMiner::new(self.support_count, data);
-        let mut out = get_writer(&a.output_file);
-        max_miner.run(|v| out_writer(v, &mut out));
+        let ordering = self.ordering.into();
+        if self.stream {
+            let data = FileSource::open(&self.path)?;
+            let transactions = data.num_transactions()?;
+            let max_miner =
+                MaxMiner::with_ordering(self.support_count, data, ordering).lengths(a.lengths()?);
+            return self
+                .output
+                .write(max_miner, &self.path, self.support_count, transactions, a);
+        }
+        let data = TransactionSet::open(&self.path)?;
+        let transactions = data.len();
+        let max_miner =
+            MaxMiner::with_ordering(self.support_count, data, ordering).lengths(a.lengths()?);
+        self.output
+            .write(max_miner, &self.path, self.support_count, transactions, a)
+    }
+}
+// The output options of the maximal itemset algorithms
+#[derive(Args)]
+pub struct MaximalOutput {
+    /// Writes the support after each itemset, like --output-format spmf
+    #[arg(long, default_value_t = false)]
+    supports: bool,
+    /// Checks that the output is frequent and maximal against the data
+    #[arg(long, default_value_t = false)]
+    verify: bool,
+}
+impl MaximalOutput {
+    /// Runs the miner on the data from path and writes its output
+    pub fn write(
+        &self,
+        miner: impl MaximalMiner,
+        path: &PathBuf,
+        support_count: u64,
+        transactions: usize,
+        a: &Arguments,
+    ) -> Result<(), Box<dyn Error>> {
+        let format = match a.output_format {
+            OutputFormat::Text if self.supports => OutputFormat::Spmf,
+            format => format,
+        };
+        let mut out = ItemsetWriter::with_format(a, format, transactions)?;
+        let mut found = Vec::new();
+        // Only the supports that are written, sorted by, or verified need to be counted
+        let supports =
+            format != OutputFormat::Text || a.sort == SortOrder::Support || self.verify;
+        let status = if supports {
+            miner.run_budgeted(&a.budget(), |v, n| {
+                out.write(v, n);
+                if self.verify {
+                    found.push((v.to_vec(), n));
+                }
+            })
+        } else {
+            // The support is not written
+            miner.run_itemsets(&a.budget(), |v| out.write(v, 0))
+        };
+        out.finish()?;
+        report_status(status);
+        // A truncated run may be missing the supersets of the itemsets it found
+        if self.verify && status.is_complete() {
+            // The miner took the data, so it is read again
+            let data = TransactionSet::open(path)?;
+            verify(&data, support_count, a.lengths()?.max, &found)?;
+            eprintln!("Verified {} maximal itemsets", found.len());
+        }
         Ok(())
     }
 }
@@ -0,0 +1,81 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicBool, AtomicIsize, Ordering},
+};
+
+/// The system allocator, keeping track of the bytes allocated and the most at once
+/// while it is enabled (so that it costs nothing otherwise)
+pub struct CountingAllocator;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// The bytes allocated since it was enabled (negative if more were freed)
+static ALLOCATED: AtomicIsize = AtomicIsize::new(0);
+static PEAK: AtomicIsize = AtomicIsize::new(0);
+
+fn add(n: isize) {
+    if ENABLED.load(Ordering::Relaxed) {
+        let now = ALLOCATED.fetch_add(n, Ordering::Relaxed) + n;
+        PEAK.fetch_max(now, Ordering::Relaxed);
+    }
+}
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let p = unsafe { System.alloc(layout) };
+        if !p.is_null() {
+            add(layout.size() as isize);
+        }
+        p
+    }
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let p = unsafe { System.alloc_zeroed(layout) };
+        if !p.is_null() {
+            add(layout.size() as isize);
+        }
+        p
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        add(-(layout.size() as isize));
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let p = unsafe { System.realloc(ptr, layout, new_size) };
+        if !p.is_null() {
+            add(new_size as isize - layout.size() as isize);
+        }
+        p
+    }
+}
+/// Starts counting the allocations
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+/// Starts measuring the peak from what is allocated now, which is returned
+pub fn reset_peak() -> isize {
+    let now = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(now, Ordering::Relaxed);
+    now
+}
+/// The most bytes allocated at once more than start
+/// (0 if the allocator is not the global allocator)
+pub fn peak_since(start: isize) -> usize {
+    (PEAK.load(Ordering::Relaxed) - start).max(0) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CountingAllocator, enable, peak_since, reset_peak};
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn test_peak() {
+        enable();
+        let start = reset_peak();
+        drop(std::hint::black_box(vec![0u8; 1 << 20]));
+        // The peak stays after the memory is freed
+        assert!(peak_since(start) >= 1 << 20);
+        let start = reset_peak();
+        assert!(peak_since(start) < 1 << 20);
+    }
+}
@@ -0,0 +1,47 @@
+use std::{error::Error, path::PathBuf};
+
+use apriori::ms_apriori::{ItemSupports, MSApriori};
+use clap::Args;
+use datasets::transaction_set::TransactionSet;
+
+use crate::{output::ItemsetWriter, passes::PassLog, report_status, Arguments};
+
+#[derive(Args)]
+pub struct MSAprioriArgs {
+    path: PathBuf,
+    /// Reads the minimum support count of each item from this file,
+    /// with line i being that of item i
+    #[arg(long, required_unless_present = "beta", conflicts_with = "beta")]
+    supports: Option<PathBuf>,
+    /// Sets the minimum support of each item to beta times its support
+    #[arg(long)]
+    beta: Option<f64>,
+    /// The lowest minimum support count of an item with --beta
+    #[arg(long, default_value_t = 1)]
+    floor: u64,
+    /// Only finds itemsets whose items have support counts that differ by at most this much
+    #[arg(long)]
+    support_difference: Option<u64>,
+}
+impl MSAprioriArgs {
+    pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn Error>> {
+        let supports = match (&self.supports, self.beta) {
+            (Some(path), _) => ItemSupports::open(path)?,
+            (None, Some(beta)) => ItemSupports::Scaled {
+                beta,
+                floor: self.floor,
+            },
+            (None, None) => unreachable!("clap requires one of them"),
+        };
+        let t = TransactionSet::open(&self.path)?;
+        let mut out = ItemsetWriter::new(a, t.len())?.levelwise();
+        let mut log = PassLog::new(a);
+        let ms = MSApriori::new(supports)
+            .support_difference(self.support_difference.unwrap_or(u64::MAX))
+            .lengths(a.lengths()?);
+        let status = ms.run_observed(&t, &mut log, &a.budget(), |v, n| out.write(v, n));
+        out.finish()?;
+        report_status(status);
+        log.finish(a)
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{Arguments, get_writer};
+
+/// How the itemsets are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// The items of each itemset separated by spaces, one itemset per line
+    #[default]
+    Text,
+    /// A JSON array of itemset objects
+    Json,
+    /// One JSON itemset object per line
+    Jsonl,
+    /// A header and then one row per itemset
+    Csv,
+    /// The items and then "#SUP:" and the support, like SPMF writes them
+    Spmf,
+}
+/// The order the itemsets are written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SortOrder {
+    /// The order the algorithm finds them in
+    #[default]
+    None,
+    /// By length and then by items, with the items of each itemset sorted
+    Length,
+    /// By support from highest to lowest, and then like length
+    Support,
+}
+/// An itemset as it is written in JSON
+#[derive(Debug, Serialize)]
+struct ItemsetRecord<'a> {
+    items: &'a [usize],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    labels: Option<Vec<&'a str>>,
+    support: u64,
+    relative_support: f64,
+    length: usize,
+}
+/// Reads the names of the items, one per line, where line i is the name of item i
+pub fn read_labels(path: &Path) -> io::Result<Vec<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(|s| s.trim().to_string())
+        .collect())
+}
+/// Writes the frequent itemsets of a run in the format given by --output-format
+pub struct ItemsetWriter {
+    out: Box<dyn Write>,
+    format: OutputFormat,
+    labels: Option<Vec<String>>,
+    /// The number of transactions, for the relative support
+    transactions: usize,
+    sort: SortOrder,
+    /// Whether the itemsets come one length at a time, shortest first
+    levelwise: bool,
+    /// The itemsets waiting to be sorted
+    buffer: Vec<(Vec<usize>, u64)>,
+    /// The number of itemsets given to write
+    written: usize,
+    /// The number of itemsets in the output
+    records: usize,
+    /// The first error from writing, which finish returns
+    error: Option<io::Error>,
+}
+impl ItemsetWriter {
+    /// Opens the output of a run over the given number of transactions
+    pub fn new(a: &Arguments, transactions: usize) -> io::Result<Self> {
+        Self::with_format(a, a.output_format, transactions)
+    }
+    /// Opens the output, writing in the given format instead of --output-format
+    pub fn with_format(
+        a: &Arguments,
+        format: OutputFormat,
+        transactions: usize,
+    ) -> io::Result<Self> {
+        let labels = a.labels.as_deref().map(read_labels).transpose()?;
+        let out = get_writer(&a.output_file)?;
+        Ok(Self::from_writer(out, format, labels, transactions).sort_by(a.sort))
+    }
+    /// Writes to out, starting with the CSV header or the opening of the JSON array
+    pub fn from_writer(
+        out: Box<dyn Write>,
+        format: OutputFormat,
+        labels: Option<Vec<String>>,
+        transactions: usize,
+    ) -> Self {
+        let mut this = Self {
+            out,
+            format,
+            labels,
+            transactions,
+            sort: SortOrder::None,
+            levelwise: false,
+            buffer: Vec::new(),
+            written: 0,
+            records: 0,
+            error: None,
+        };
+        let start = match format {
+            OutputFormat::Json => "[".to_string(),
+            OutputFormat::Csv if this.labels.is_some() => {
+                "items,support,relative_support,length,labels\n".to_string()
+            }
+            OutputFormat::Csv => "items,support,relative_support,length\n".to_string(),
+            _ => String::new(),
+        };
+        this.write_str(&start);
+        this
+    }
+    /// Sorts the itemsets before writing them.
+    /// Every itemset is kept until the end, unless they are sorted by length and come levelwise.
+    pub fn sort_by(mut self, sort: SortOrder) -> Self {
+        self.sort = sort;
+        self
+    }
+    /// Tells the writer that the itemsets come one length at a time, shortest first,
+    /// as they do from a level-wise algorithm.
+    /// Sorting them by length then only keeps the itemsets of one length.
+    pub fn levelwise(mut self) -> Self {
+        self.levelwise = true;
+        self
+    }
+    /// The names of the items if there are labels, using the id of an item without one
+    fn labels(&self, v: &[usize]) -> Option<Vec<String>> {
+        let labels = self.labels.as_ref()?;
+        Some(
+            v.iter()
+                .map(|&n| match labels.get(n) {
+                    Some(s) if !s.is_empty() => s.clone(),
+                    _ => n.to_string(),
+                })
+                .collect(),
+        )
+    }
+    /// Writes an itemset with its support, or keeps it to write once sorted
+    pub fn write(&mut self, v: &[usize], support: u64) {
+        self.written += 1;
+        if self.sort == SortOrder::None {
+            self.write_record(v, support);
+            return;
+        }
+        let mut v = v.to_vec();
+        v.sort_unstable();
+        // The shorter itemsets are all in the buffer
+        if self.sort == SortOrder::Length
+            && self.levelwise
+            && self.buffer.last().is_some_and(|b| b.0.len() < v.len())
+        {
+            self.flush_sorted();
+        }
+        self.buffer.push((v, support));
+    }
+    /// Writes the itemsets in the buffer in order
+    fn flush_sorted(&mut self) {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        match self.sort {
+            SortOrder::None => {}
+            SortOrder::Length => {
+                buffer.sort_unstable_by(|a, b| (a.0.len(), &a.0).cmp(&(b.0.len(), &b.0)))
+            }
+            SortOrder::Support => {
+                buffer.sort_unstable_by(|a, b| (b.1, a.0.len(), &a.0).cmp(&(a.1, b.0.len(), &b.0)))
+            }
+        }
+        for (v, support) in buffer {
+            self.write_record(&v, support);
+        }
+    }
+    /// Writes an itemset in the output format
+    fn write_record(&mut self, v: &[usize], support: u64) {
+        let relative = if self.transactions == 0 {
+            0.0
+        } else {
+            support as f64 / self.transactions as f64
+        };
+        let ids = join(v.iter().map(|n| n.to_string()));
+        let line = match self.format {
+            OutputFormat::Text => match self.labels(v) {
+                Some(labels) => join(labels) + "\n",
+                None => ids + "\n",
+            },
+            OutputFormat::Spmf => format!("{ids} #SUP: {support}\n"),
+            OutputFormat::Csv => {
+                let mut line = format!("{ids},{support},{relative},{}", v.len());
+                if let Some(labels) = self.labels(v) {
+                    line += ",";
+                    line += &csv_field(&join(labels));
+                }
+                line + "\n"
+            }
+            OutputFormat::Json | OutputFormat::Jsonl => {
+                let labels = self.labels(v);
+                let record = ItemsetRecord {
+                    items: v,
+                    labels: labels
+                        .as_ref()
+                        .map(|l| l.iter().map(|s| s.as_str()).collect()),
+                    support,
+                    relative_support: relative,
+                    length: v.len(),
+                };
+                // Serializing numbers and strings cannot fail
+                let record = serde_json::to_string(&record).unwrap();
+                match self.format {
+                    OutputFormat::Json if self.records == 0 => format!("\n  {record}"),
+                    OutputFormat::Json => format!(",\n  {record}"),
+                    _ => record + "\n",
+                }
+            }
+        };
+        self.records += 1;
+        self.write_str(&line);
+    }
+    fn write_str(&mut self, s: &str) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(e) = self.out.write_all(s.as_bytes()) {
+            self.error = Some(e);
+        }
+    }
+    /// The number of itemsets written
+    pub fn written(&self) -> usize {
+        self.written
+    }
+    /// Writes the itemsets left to sort, closes the JSON array, and flushes the output,
+    /// returning the first error from writing
+    pub fn finish(mut self) -> io::Result<()> {
+        self.flush_sorted();
+        if self.format == OutputFormat::Json {
+            let end = if self.records == 0 { "]\n" } else { "\n]\n" };
+            self.write_str(end);
+        }
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.out.flush()
+    }
+}
+/// Joins the strings with spaces
+fn join(v: impl IntoIterator<Item = String>) -> String {
+    v.into_iter().collect::<Vec<_>>().join(" ")
+}
+/// Quotes a CSV field if it has a comma, quote, or line break
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        io::{self, Write},
+        rc::Rc,
+    };
+
+    use super::{ItemsetWriter, OutputFormat, SortOrder};
+
+    /// A writer that the test can read after the ItemsetWriter is done with it
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    fn written(format: OutputFormat, labels: Option<Vec<String>>) -> String {
+        let out = Shared::default();
+        let mut w = ItemsetWriter::from_writer(Box::new(out.clone()), format, labels, 4);
+        w.write(&[0, 2], 2);
+        w.write(&[1], 3);
+        w.finish().unwrap();
+        String::from_utf8(out.0.take()).unwrap()
+    }
+    #[test]
+    fn test_formats() {
+        assert_eq!(written(OutputFormat::Text, None), "0 2\n1\n");
+        assert_eq!(
+            written(OutputFormat::Spmf, None),
+            "0 2 #SUP: 2\n1 #SUP: 3\n"
+        );
+        assert_eq!(
+            written(OutputFormat::Csv, None),
+            "items,support,relative_support,length\n0 2,2,0.5,2\n1,3,0.75,1\n"
+        );
+        let jsonl = written(OutputFormat::Jsonl, None);
+        assert_eq!(
+            jsonl.lines().next(),
+            Some(r#"{"items":[0,2],"support":2,"relative_support":0.5,"length":2}"#)
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&written(OutputFormat::Json, None)).unwrap();
+        assert_eq!(json.as_array().map(|v| v.len()), Some(2));
+        assert_eq!(json[1]["support"], 3);
+        let empty =
+            ItemsetWriter::from_writer(Box::new(Shared::default()), OutputFormat::Json, None, 0);
+        empty.finish().unwrap();
+        // Labels replace the ids in text, and items without one keep their id
+        let labels = Some(vec!["bread".to_string(), "milk, whole".to_string()]);
+        assert_eq!(
+            written(OutputFormat::Text, labels.clone()),
+            "bread 2\nmilk, whole\n"
+        );
+        let csv = written(OutputFormat::Csv, labels.clone());
+        assert_eq!(csv.lines().nth(2), Some("1,3,0.75,1,\"milk, whole\""));
+        let jsonl = written(OutputFormat::Jsonl, labels);
+        assert!(jsonl.starts_with(r#"{"items":[0,2],"labels":["bread","2"],"support":2"#));
+    }
+    fn sorted(sort: SortOrder, levelwise: bool, v: &[(&[usize], u64)]) -> String {
+        let out = Shared::default();
+        let mut w = ItemsetWriter::from_writer(Box::new(out.clone()), OutputFormat::Spmf, None, 4)
+            .sort_by(sort);
+        if levelwise {
+            w = w.levelwise();
+        }
+        for &(v, n) in v {
+            w.write(v, n);
+        }
+        w.finish().unwrap();
+        String::from_utf8(out.0.take()).unwrap()
+    }
+    #[test]
+    fn test_sort() {
+        let v: [(&[usize], u64); 5] = [
+            (&[3, 1], 2),
+            (&[2], 3),
+            (&[0, 1, 2], 5),
+            (&[1], 4),
+            (&[0, 2], 3),
+        ];
+        let length = "1 #SUP: 4\n2 #SUP: 3\n0 2 #SUP: 3\n1 3 #SUP: 2\n0 1 2 #SUP: 5\n";
+        assert_eq!(sorted(SortOrder::Length, false, &v), length);
+        let support = "0 1 2 #SUP: 5\n1 #SUP: 4\n2 #SUP: 3\n0 2 #SUP: 3\n1 3 #SUP: 2\n";
+        assert_eq!(sorted(SortOrder::Support, false, &v), support);
+        // Levelwise, each length is sorted once the next one starts
+        let levels = [v[1], v[3], v[0], v[4], v[2]];
+        assert_eq!(sorted(SortOrder::Length, true, &levels), length);
+    }
+}
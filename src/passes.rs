@@ -0,0 +1,79 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Write},
+    time::Duration,
+};
+
+use apriori::observer::{PassObserver, PassStats};
+use serde::Serialize;
+
+use crate::Arguments;
+
+/// The stats of a pass, as they are written out
+#[derive(Debug, Serialize)]
+pub struct PassReport {
+    pub pass: usize,
+    pub generated: usize,
+    pub candidates: usize,
+    pub pruned: usize,
+    pub frequent: usize,
+    pub time_ms: f64,
+}
+impl From<&PassStats> for PassReport {
+    fn from(p: &PassStats) -> Self {
+        Self {
+            pass: p.pass,
+            generated: p.generated(),
+            candidates: p.candidates,
+            pruned: p.pruned,
+            frequent: p.frequent,
+            time_ms: ms(p.duration),
+        }
+    }
+}
+pub fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+/// Prints each pass to stderr with --verbose, and keeps them to write with --stats-json
+#[derive(Debug, Default)]
+pub struct PassLog {
+    verbose: bool,
+    passes: Vec<PassStats>,
+}
+impl PassLog {
+    pub fn new(a: &Arguments) -> Self {
+        Self {
+            verbose: a.verbose,
+            passes: Vec::new(),
+        }
+    }
+    /// Writes the passes to the --stats-json file (if given)
+    pub fn finish(self, a: &Arguments) -> Result<(), Box<dyn Error>> {
+        let Some(path) = &a.stats_json else {
+            return Ok(());
+        };
+        let passes: Vec<_> = self.passes.iter().map(PassReport::from).collect();
+        let mut out = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut out, &passes)?;
+        writeln!(out)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+impl PassObserver for PassLog {
+    fn pass_end(&mut self, stats: &PassStats) {
+        if self.verbose {
+            eprintln!(
+                "pass {}: {} generated, {} pruned, {} candidates, {} frequent, {:.2} ms",
+                stats.pass,
+                stats.generated(),
+                stats.pruned,
+                stats.candidates,
+                stats.frequent,
+                ms(stats.duration)
+            );
+        }
+        self.passes.push(stats.clone());
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use ::apriori::apriori::apriori_run_one_count;
+use clap::Args;
+use datasets::transaction_set::TransactionSet;
+use serde::Serialize;
+
+use crate::{Arguments, get_writer};
+
+/// The percentiles of the transaction lengths that are reported
+const PERCENTILES: [u8; 5] = [25, 50, 75, 90, 99];
+/// The fractions of the transactions used as thresholds when none are given
+const DEFAULT_THRESHOLDS: [f64; 5] = [0.5, 0.25, 0.1, 0.05, 0.01];
+
+#[derive(Args)]
+pub struct StatsArgs {
+    path: PathBuf,
+    /// The support counts to count the frequent items at
+    /// (defaults to 50%, 25%, 10%, 5%, and 1% of the transactions)
+    #[arg(short, long, value_delimiter = ',')]
+    supports: Vec<u64>,
+    /// The number of most frequent items to list
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+    /// Writes the statistics as JSON
+    #[arg(long)]
+    json: bool,
+}
+impl StatsArgs {
+    pub fn run(&self, a: &Arguments) -> Result<(), Box<dyn Error>> {
+        let data = TransactionSet::open(&self.path)?;
+        let supports = if self.supports.is_empty() {
+            DEFAULT_THRESHOLDS
+                .iter()
+                .map(|f| ((f * data.len() as f64).ceil() as u64).max(1))
+                .collect()
+        } else {
+            self.supports.clone()
+        };
+        let stats = Stats::new(&data, self.top, &supports)?;
+        let mut out = get_writer(&a.output_file)?;
+        if self.json {
+            serde_json::to_writer_pretty(&mut out, &stats)?;
+            writeln!(out)?;
+        } else {
+            write!(out, "{stats}")?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+/// The shape of a dataset
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub transactions: usize,
+    /// One more than the largest item id
+    pub num_items: usize,
+    /// The number of items in at least one transaction
+    pub distinct_items: usize,
+    /// The number of items in all the transactions
+    pub entries: usize,
+    pub average_length: f64,
+    pub max_length: usize,
+    pub length_percentiles: Vec<Percentile>,
+    /// The average fraction of the distinct items in a transaction
+    pub density: f64,
+    /// The most frequent items, most frequent first
+    pub top_items: Vec<ItemSupport>,
+    /// The number of items by their support, in buckets of powers of two
+    pub support_histogram: Vec<Bucket>,
+    /// The number of frequent items at each threshold
+    pub frequent_items: Vec<Threshold>,
+}
+#[derive(Debug, Serialize)]
+pub struct Percentile {
+    pub percentile: u8,
+    pub length: usize,
+}
+#[derive(Debug, Serialize)]
+pub struct ItemSupport {
+    pub item: usize,
+    pub support: u64,
+}
+/// The items with a support from min to max (inclusive)
+#[derive(Debug, Serialize)]
+pub struct Bucket {
+    pub min: u64,
+    pub max: u64,
+    pub items: usize,
+}
+#[derive(Debug, Serialize)]
+pub struct Threshold {
+    pub support: u64,
+    pub items: usize,
+}
+impl Stats {
+    /// Computes the statistics, listing the top items and the frequent items at the supports
+    pub fn new(data: &TransactionSet, top: usize, supports: &[u64]) -> io::Result<Self> {
+        let counts = apriori_run_one_count(data)?;
+        let mut lengths: Vec<_> = data.iter().map(|t| t.len()).collect();
+        lengths.sort_unstable();
+        let entries: usize = lengths.iter().sum();
+        let distinct_items = counts.iter().filter(|&&n| n > 0).count();
+        let average_length = if lengths.is_empty() {
+            0.0
+        } else {
+            entries as f64 / lengths.len() as f64
+        };
+        // The nearest rank percentiles
+        let length_percentiles = PERCENTILES
+            .iter()
+            .map(|&p| {
+                let rank = (p as usize * lengths.len()).div_ceil(100);
+                Percentile {
+                    percentile: p,
+                    length: lengths.get(rank.saturating_sub(1)).copied().unwrap_or(0),
+                }
+            })
+            .collect();
+        let mut by_support: Vec<_> = (0..counts.len()).filter(|&i| counts[i] > 0).collect();
+        by_support.sort_by_key(|&i| (std::cmp::Reverse(counts[i]), i));
+        let top_items = by_support
+            .iter()
+            .take(top)
+            .map(|&item| ItemSupport {
+                item,
+                support: counts[item],
+            })
+            .collect();
+        let mut support_histogram: Vec<Bucket> = Vec::new();
+        for &n in counts.iter().filter(|&&n| n > 0) {
+            let k = n.ilog2() as usize;
+            while support_histogram.len() <= k {
+                let min = 1 << support_histogram.len();
+                support_histogram.push(Bucket {
+                    min,
+                    max: 2 * min - 1,
+                    items: 0,
+                });
+            }
+            support_histogram[k].items += 1;
+        }
+        // The buckets start at the least frequent item
+        let first = support_histogram.iter().position(|b| b.items > 0);
+        support_histogram.drain(..first.unwrap_or(0));
+        let frequent_items = supports
+            .iter()
+            .map(|&support| Threshold {
+                support,
+                items: counts.iter().filter(|&&n| n >= support).count(),
+            })
+            .collect();
+        Ok(Self {
+            transactions: data.len(),
+            num_items: data.num_items,
+            distinct_items,
+            entries,
+            average_length,
+            max_length: lengths.last().copied().unwrap_or(0),
+            length_percentiles,
+            density: if distinct_items == 0 {
+                0.0
+            } else {
+                average_length / distinct_items as f64
+            },
+            top_items,
+            support_histogram,
+            frequent_items,
+        })
+    }
+}
+impl Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Transactions: {}", self.transactions)?;
+        writeln!(
+            f,
+            "Items: {} distinct (ids up to {})",
+            self.distinct_items,
+            self.num_items.saturating_sub(1)
+        )?;
+        writeln!(f, "Entries: {}", self.entries)?;
+        writeln!(
+            f,
+            "Transaction length: average {:.2}, max {}",
+            self.average_length, self.max_length
+        )?;
+        for p in &self.length_percentiles {
+            writeln!(f, "  {}th percentile: {}", p.percentile, p.length)?;
+        }
+        writeln!(f, "Density: {:.4}", self.density)?;
+        writeln!(f, "Top items (item: support):")?;
+        for i in &self.top_items {
+            writeln!(f, "  {}: {}", i.item, i.support)?;
+        }
+        writeln!(f, "Items by support:")?;
+        for b in &self.support_histogram {
+            writeln!(f, "  {}-{}: {}", b.min, b.max, b.items)?;
+        }
+        writeln!(f, "Frequent items by support count:")?;
+        for t in &self.frequent_items {
+            writeln!(f, "  {}: {}", t.support, t.items)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datasets::transaction_set::TransactionSet;
+
+    use super::Stats;
+
+    #[test]
+    fn test_stats() {
+        // Item 4 is in no transaction
+        let data = TransactionSet::new(
+            vec![vec![0, 2], vec![], vec![1, 2, 3], vec![2, 3], vec![2]],
+            5,
+        );
+        let stats = Stats::new(&data, 2, &[1, 2, 5]).unwrap();
+        assert_eq!(stats.transactions, 5);
+        assert_eq!(stats.num_items, 5);
+        assert_eq!(stats.distinct_items, 4);
+        assert_eq!(stats.entries, 8);
+        assert_eq!(stats.average_length, 1.6);
+        assert_eq!(stats.max_length, 3);
+        let percentiles: Vec<_> = stats
+            .length_percentiles
+            .iter()
+            .map(|p| (p.percentile, p.length))
+            .collect();
+        assert_eq!(percentiles, [(25, 1), (50, 2), (75, 2), (90, 3), (99, 3)]);
+        assert_eq!(stats.density, 0.4);
+        let top: Vec<_> = stats.top_items.iter().map(|i| (i.item, i.support)).collect();
+        assert_eq!(top, [(2, 4), (3, 2)]);
+        let buckets: Vec<_> = stats
+            .support_histogram
+            .iter()
+            .map(|b| (b.min, b.max, b.items))
+            .collect();
+        assert_eq!(buckets, [(1, 1, 2), (2, 3, 1), (4, 7, 1)]);
+        let frequent: Vec<_> = stats
+            .frequent_items
+            .iter()
+            .map(|t| (t.support, t.items))
+            .collect();
+        assert_eq!(frequent, [(1, 4), (2, 2), (5, 0)]);
+
+        // The JSON has a key for each statistic
+        let json = serde_json::to_value(&stats).unwrap();
+        let mut keys: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            [
+                "average_length",
+                "density",
+                "distinct_items",
+                "entries",
+                "frequent_items",
+                "length_percentiles",
+                "max_length",
+                "num_items",
+                "support_histogram",
+                "top_items",
+                "transactions",
+            ]
+        );
+        assert_eq!(json["top_items"][0]["item"], 2);
+        assert_eq!(json["support_histogram"][1]["max"], 3);
+        assert_eq!(json["frequent_items"][2]["support"], 5);
+    }
+}
@@ -0,0 +1,277 @@
+//! Checks every algorithm against the brute force miner on random datasets
+use std::{collections::HashSet, ops::Range, sync::Arc};
+
+use apriori::{
+    apriori::{Apriori, CountingStructure},
+    apriori_hybrid::AprioriHybrid,
+    apriori_tid::{AprioriTID, TIDEncoding},
+    apriori_trie::{AprioriT, Trimming},
+    candidates::Candidates,
+    cumulate::Cumulate,
+    ms_apriori::{ItemSupports, MSApriori},
+};
+use count_distribution::{
+    count_distribution::CountDistribution, count_distribution_hybrid::CountDistributionHybrid,
+};
+use datasets::{
+    budget::Budget, constraint::Lengths, random::Random, taxonomy::Taxonomy,
+    transaction_set::TransactionSet,
+};
+use fp_growth::fp_growth::FPGrowth;
+use maxminer::{
+    mafia::Mafia,
+    max_miner::{ItemOrdering, MaxMiner},
+};
+use tester::differential::{
+    Found, Miner, Target, brute_force, cases, check, constrained_cases,
+};
+
+/// The seeds of the random datasets.
+/// Set DIFFERENTIAL_SEEDS to run more (or fewer) of them.
+fn seeds() -> Range<u64> {
+    0..std::env::var("DIFFERENTIAL_SEEDS").map_or(64, |s| s.parse().unwrap())
+}
+/// The random parameters of a test that are not part of its cases,
+/// seeded by the case so the miner and its oracle agree on them
+fn random(min_sup: u64, data: &TransactionSet) -> Random {
+    Random::new(min_sup * 1000 + data.len() as u64)
+}
+/// Collects the itemsets passed to f with their supports
+fn collect<R>(f: impl FnOnce(&mut dyn FnMut(&[usize], u64)) -> R) -> Found {
+    let mut found = Vec::new();
+    f(&mut |v, n| found.push((v.to_vec(), Some(n))));
+    found
+}
+/// Passes the itemsets with the lengths from the passes of a Count Distribution to f
+fn passes(found: &[Arc<Candidates>], lengths: Lengths, f: &mut dyn FnMut(&[usize], u64)) {
+    for (v, &n) in found.iter().flat_map(|c| c.iter()) {
+        if lengths.contains(v.len()) {
+            f(v, n);
+        }
+    }
+}
+fn miners() -> Vec<Miner> {
+    let mut miners = vec![
+        Miner::new("Apriori", Target::Frequent, |t, s, _, lengths, _| {
+            let apriori = Apriori::new(s).lengths(lengths);
+            collect(|f| apriori.run_observed(&t, &mut (), &Budget::default(), f))
+        }),
+        Miner::new("Apriori with a flat trie", Target::Frequent, |t, s, _, lengths, _| {
+            let apriori = Apriori::with_counting(s, CountingStructure::FlatTrie).lengths(lengths);
+            collect(|f| apriori.run_observed(&t, &mut (), &Budget::default(), f))
+        }),
+        Miner::new("FP-Growth", Target::Frequent, |t, s, _, lengths, _| {
+            let fp_growth = FPGrowth::new(s, t).lengths(lengths);
+            collect(|f| fp_growth.run_budgeted(&Budget::default(), f))
+        }),
+        Miner::new("Count Distribution", Target::Frequent, |t, s, threads, lengths, _| {
+            let found = CountDistribution::new(Arc::new(t), threads, s)
+                .lengths(lengths)
+                .run()
+                .unwrap();
+            collect(|f| passes(&found, lengths, f))
+        }),
+        Miner::new("MAFIA", Target::Maximal, |t, s, _, lengths, _| {
+            let mut found = Vec::new();
+            Mafia::new(s, t)
+                .lengths(lengths)
+                .run_support(|v, n| found.push((v.to_vec(), Some(n))))
+                .unwrap();
+            found
+        }),
+    ];
+    for encoding in [TIDEncoding::Itemsets, TIDEncoding::Indices] {
+        miners.push(Miner::new(
+            format!("AprioriTID encoding={encoding:?}"),
+            Target::Frequent,
+            move |t, s, _, lengths, _| {
+                let tid = AprioriTID::with_encoding(s, encoding).lengths(lengths);
+                collect(|f| tid.run_observed(&t, &mut (), &Budget::default(), f))
+            },
+        ));
+        for switch in [2, 3] {
+            miners.push(Miner::new(
+                format!("AprioriHybrid switch={switch} encoding={encoding:?}"),
+                Target::Frequent,
+                move |t, s, _, lengths, _| {
+                    let mut hybrid =
+                        AprioriHybrid::with_encoding(s, switch, encoding).lengths(lengths);
+                    collect(|f| hybrid.run_observed(&t, &mut (), &Budget::default(), f))
+                },
+            ));
+        }
+    }
+    for trimming in [Trimming::None, Trimming::Filter, Trimming::Project(0.5)] {
+        miners.push(Miner::new(
+            format!("Apriori Trie trimming={trimming:?}"),
+            Target::Frequent,
+            move |t, s, threads, lengths, _| {
+                let mut a = AprioriT::with_threads(s, trimming, threads).lengths(lengths);
+                a.run(&t).unwrap();
+                collect(|f| {
+                    a.trie().for_each_support(s, |v, n| {
+                        if lengths.contains(v.len()) {
+                            f(v, n)
+                        }
+                    })
+                })
+            },
+        ));
+    }
+    for switch in [3, 4] {
+        miners.push(Miner::new(
+            format!("Count Distribution Hybrid switch={switch}"),
+            Target::Frequent,
+            move |t, s, threads, lengths, _| {
+                let found = CountDistributionHybrid::new(&t, threads, s, switch)
+                    .lengths(lengths)
+                    .run();
+                collect(|f| passes(&found, lengths, f))
+            },
+        ));
+    }
+    for ordering in [
+        ItemOrdering::Id,
+        ItemOrdering::Support,
+        ItemOrdering::Dynamic,
+    ] {
+        let name = format!("MaxMiner ordering={ordering:?}");
+        miners.push(Miner::new(name, Target::Maximal, move |t, s, _, lengths, _| {
+            let mut found = Vec::new();
+            MaxMiner::with_ordering(s, t, ordering)
+                .lengths(lengths)
+                .run_support(|v, n| found.push((v.to_vec(), Some(n))))
+                .unwrap();
+            found
+        }));
+    }
+    miners
+}
+#[test]
+fn test_differential() {
+    let miners = miners();
+    // A miner that differs is only known by its name
+    let names: HashSet<_> = miners.iter().map(|m| &m.name).collect();
+    assert_eq!(names.len(), miners.len());
+    check(&miners, cases(seeds()));
+}
+/// The miners that push item constraints into their search
+fn constrained_miners() -> Vec<Miner> {
+    let mut miners = Vec::new();
+    for counting in [CountingStructure::HashTree, CountingStructure::FlatTrie] {
+        let name = format!("Apriori counting={counting:?}");
+        miners.push(Miner::new(name, Target::Frequent, move |t, s, _, lengths, c| {
+            let apriori = Apriori::with_counting(s, counting)
+                .lengths(lengths)
+                .constraints(c.clone());
+            collect(|f| apriori.run_observed(&t, &mut (), &Budget::default(), f))
+        }));
+    }
+    miners.push(Miner::new("FP-Growth", Target::Frequent, |t, s, _, lengths, c| {
+        let fp_growth = FPGrowth::new(s, t).lengths(lengths).constraints(c.clone());
+        collect(|f| fp_growth.run_budgeted(&Budget::default(), f))
+    }));
+    miners
+}
+#[test]
+fn test_item_constraints() {
+    check(&constrained_miners(), constrained_cases(seeds()));
+}
+/// The minimum supports of the items MSApriori runs with, which are either random
+/// or scaled by beta, and the largest difference between the supports of an itemset's items
+fn ms_parameters(min_sup: u64, data: &TransactionSet) -> (ItemSupports, u64) {
+    let mut random = random(min_sup, data);
+    let len = data.len() as u64;
+    let supports = if random.below(2) == 0 {
+        let mis = (0..data.num_items).map(|_| 1 + random.below(len as usize) as u64);
+        ItemSupports::Given(mis.collect())
+    } else {
+        ItemSupports::Scaled {
+            beta: random.uniform(),
+            floor: min_sup,
+        }
+    };
+    (supports, [u64::MAX, len / 4, len / 2][random.below(3)])
+}
+/// MSApriori against the itemsets of the brute force miner that are frequent
+/// for the minimum supports of their items
+#[test]
+fn test_ms_apriori() {
+    let miner = Miner::new("MSApriori", Target::Frequent, |t, s, _, lengths, _| {
+        let (supports, difference) = ms_parameters(s, &t);
+        let ms_apriori = MSApriori::new(supports)
+            .support_difference(difference)
+            .lengths(lengths);
+        collect(|f| ms_apriori.run_observed(&t, &mut (), &Budget::default(), f))
+    });
+    let miner = miner.oracle(|case| {
+        let data = case.data();
+        let (supports, difference) = ms_parameters(case.min_sup, &data);
+        let mut all = brute_force(&data, 1);
+        let count = |i| all.get(&vec![i]).copied().unwrap_or(0);
+        let counts: Vec<_> = (0..case.num_items).map(count).collect();
+        let mis = supports.resolve(&counts);
+        all.retain(|v, n| {
+            let least = v.iter().map(|&i| mis[i]).min().unwrap();
+            let sup = v.iter().map(|&i| counts[i]);
+            let spread = sup.clone().max().unwrap() - sup.min().unwrap();
+            *n >= least && spread <= difference && case.lengths.contains(v.len())
+        });
+        all
+    });
+    check(&[miner], cases(seeds()));
+}
+/// A taxonomy that puts the items of data under a few categories,
+/// which may have a parent of their own, and the minimum supports of its levels
+fn cumulate_parameters(min_sup: u64, data: &TransactionSet) -> (Taxonomy, Vec<u64>) {
+    let mut random = random(min_sup, data);
+    let n = data.num_items;
+    let categories = 1 + random.below(3);
+    let mut parents = Vec::new();
+    for c in 1..categories {
+        if random.below(2) == 0 {
+            parents.push((n + c, n + random.below(c)));
+        }
+    }
+    for i in 0..n {
+        for _ in 0..random.below(3) {
+            parents.push((i, n + random.below(categories)));
+        }
+    }
+    let supports = (0..1 + random.below(3))
+        .map(|_| min_sup + random.below(data.len() / 2 + 1) as u64)
+        .collect();
+    (Taxonomy::new(&parents).unwrap(), supports)
+}
+/// Cumulate against the itemsets of the brute force miner on the transactions extended with
+/// the ancestors of their items, without an item and its ancestor, that are frequent for the
+/// minimum supports of the levels of their items
+#[test]
+fn test_cumulate() {
+    let miner = Miner::new("Cumulate", Target::Frequent, |t, s, _, lengths, _| {
+        let (taxonomy, supports) = cumulate_parameters(s, &t);
+        let cumulate = Cumulate::new(taxonomy, supports).lengths(lengths);
+        collect(|f| cumulate.run_observed(&t, &mut (), &Budget::default(), f))
+    });
+    let miner = miner.oracle(|case| {
+        let data = case.data();
+        let (taxonomy, supports) = cumulate_parameters(case.min_sup, &data);
+        let min_support = |i: usize| supports[taxonomy.level(i).min(supports.len() - 1)];
+        let extended = case.transactions.iter().map(|t| {
+            let mut t = t.clone();
+            t.extend(t.iter().flat_map(|&i| taxonomy.ancestors(i)).collect::<Vec<_>>());
+            t.sort_unstable();
+            t.dedup();
+            t
+        });
+        let num_items = data.num_items.max(taxonomy.num_items());
+        let extended = TransactionSet::new(extended.collect(), num_items);
+        let mut found = brute_force(&extended, *supports.iter().min().unwrap());
+        found.retain(|v, s| {
+            let frequent = v.iter().any(|&i| *s >= min_support(i));
+            frequent && !taxonomy.has_ancestor(v) && case.lengths.contains(v.len())
+        });
+        found
+    });
+    check(&[miner], cases(seeds()));
+}